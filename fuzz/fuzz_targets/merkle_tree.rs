@@ -0,0 +1,58 @@
+//! Every proof of a tree built by `MerkleTree` must verify against its root, and must not
+//! verify a different amount.
+
+#![no_main]
+
+use libfuzzer_sys::arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use publicai_airdrop::{AirdropContract, HashAlgorithm, LeafFormat, MerkleTree};
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    sha256: bool,
+    v2: bool,
+    amounts: Vec<u128>,
+}
+
+fuzz_target!(|input: Input| {
+    if input.amounts.is_empty() || input.amounts.len() > 256 {
+        return;
+    }
+    let hash_algorithm = if input.sha256 {
+        HashAlgorithm::Sha256
+    } else {
+        HashAlgorithm::Keccak256
+    };
+    let leaf_format = if input.v2 {
+        LeafFormat::V2
+    } else {
+        LeafFormat::V1
+    };
+    let entries = input
+        .amounts
+        .iter()
+        .enumerate()
+        .map(|(i, amount)| (format!("user{}.near", i).parse().unwrap(), *amount))
+        .collect();
+    let tree = MerkleTree::from_entries_with(hash_algorithm, leaf_format, entries);
+    let root = tree.root();
+    for (index, (account_id, amount)) in tree.entries().iter().enumerate() {
+        let proof = tree.proof(index).unwrap();
+        let verify = |amount: u128| {
+            AirdropContract::verify_merkle_proof_with(
+                hash_algorithm,
+                leaf_format,
+                format!("{}:{}", account_id, amount),
+                &root,
+                &proof,
+            )
+            .unwrap()
+        };
+        assert!(verify(*amount), "Valid proof of entry {} rejected", index);
+        assert!(
+            !verify(amount.wrapping_add(1)),
+            "Proof of entry {} accepts another amount",
+            index
+        );
+    }
+});
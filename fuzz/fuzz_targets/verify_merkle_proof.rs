@@ -0,0 +1,48 @@
+//! Arbitrary leaves, roots and proofs, in any encoding, must be rejected with an error or
+//! `false`, never a panic.
+
+#![no_main]
+
+use libfuzzer_sys::arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use publicai_airdrop::{AirdropContract, HashAlgorithm, LeafFormat};
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    sha256: bool,
+    v2: bool,
+    leaves: Vec<String>,
+    root: String,
+    proof: Vec<String>,
+    flags: Vec<bool>,
+}
+
+fuzz_target!(|input: Input| {
+    let hash_algorithm = if input.sha256 {
+        HashAlgorithm::Sha256
+    } else {
+        HashAlgorithm::Keccak256
+    };
+    let leaf_format = if input.v2 {
+        LeafFormat::V2
+    } else {
+        LeafFormat::V1
+    };
+    if let Some(leaf) = input.leaves.first() {
+        let _ = AirdropContract::verify_merkle_proof_with(
+            hash_algorithm,
+            leaf_format,
+            leaf.clone(),
+            &input.root,
+            &input.proof,
+        );
+    }
+    let _ = AirdropContract::verify_merkle_multiproof_with(
+        hash_algorithm,
+        leaf_format,
+        input.leaves,
+        &input.root,
+        &input.proof,
+        &input.flags,
+    );
+});
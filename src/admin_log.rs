@@ -0,0 +1,48 @@
+use near_sdk::json_types::{Base58CryptoHash, U64};
+use near_sdk::{env, near, AccountId};
+
+use crate::{AirdropContract, AirdropContractExt};
+
+/// Administrative call recorded in the audit log.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdminAction {
+    pub method: String,
+    pub caller: AccountId,
+    /// sha256 of the raw call arguments, to check against the transaction.
+    pub args_hash: Base58CryptoHash,
+    pub block_height: U64,
+}
+
+#[near]
+impl AirdropContract {
+    /// Returns up to `limit` administrative actions starting at log index `from_index`, oldest
+    /// first. The log is append-only, so reviewers can resume from the last index they have seen.
+    pub fn get_admin_log(&self, from_index: u64, limit: u64) -> Vec<(U64, AdminAction)> {
+        let from_index = from_index.min(self.admin_log.len() as u64);
+        let to_index = from_index
+            .saturating_add(limit)
+            .min(self.admin_log.len() as u64);
+        (from_index..to_index)
+            .filter_map(|index| Some((U64(index), self.admin_log.get(index as u32)?.clone())))
+            .collect()
+    }
+
+    /// Returns the number of recorded administrative actions.
+    pub fn get_admin_log_length(&self) -> U64 {
+        U64(self.admin_log.len() as u64)
+    }
+}
+
+impl AirdropContract {
+    /// Appends the current call of `method` to the audit log.
+    pub(crate) fn record_admin_action(&mut self, method: &str) {
+        let args = env::input().unwrap_or_default();
+        self.admin_log.push(AdminAction {
+            method: method.to_string(),
+            caller: env::predecessor_account_id(),
+            args_hash: env::sha256_array(&args).into(),
+            block_height: U64(env::block_height()),
+        });
+    }
+}
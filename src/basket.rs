@@ -0,0 +1,250 @@
+use near_sdk::json_types::U128;
+use near_sdk::{
+    assert_one_yocto, env, near, serde_json, AccountId, Gas, NearToken, Promise, PromiseOrValue,
+};
+
+use crate::{
+    AirdropContract, AirdropContractExt, AirdropEvent, BasketClaimEvent, ClaimError, ClaimStatus,
+    CLAIM_GAS_RESERVE, GAS_FOR_FT_TRANSFER, GAS_FOR_STORAGE_DEPOSIT, STORAGE_DEPOSIT_AMOUNT,
+};
+
+/// Maximum number of tokens in a basket leaf.
+pub const MAX_BASKET_LEGS: usize = 5;
+// Static gas of `on_basket_leg_transferred`
+const GAS_FOR_BASKET_LEG_CALLBACK: Gas = Gas::from_tgas(10);
+
+/// Encodes a basket leaf as `account:token_a=amount_a,token_b=amount_b`, in the order of `basket`.
+pub fn encode_basket_leaf(account_id: &AccountId, basket: &[(AccountId, U128)]) -> String {
+    let legs: Vec<String> = basket
+        .iter()
+        .map(|(token_contract, amount)| format!("{}={}", token_contract, amount.0))
+        .collect();
+    format!("{}:{}", account_id, legs.join(","))
+}
+
+#[near]
+impl AirdropContract {
+    /// Claims a basket leaf paying several tokens at once, e.g. the airdropped token and a
+    /// stablecoin bonus. Each token is registered and transferred in its own leg: paid legs
+    /// are skipped and failed legs can be claimed again. Legs of the airdropped token count
+    /// against the distribution cap and the maximum claim amount. Returns the number of legs
+    /// started.
+    #[payable]
+    #[handle_result]
+    pub fn claim_basket(
+        &mut self,
+        basket: Vec<(AccountId, U128)>,
+        merkle_proof: Vec<String>,
+    ) -> Result<u32, ClaimError> {
+        assert_one_yocto();
+        self.assert_claims_open()?;
+        if basket.is_empty() {
+            return Err(ClaimError::NoEntries);
+        }
+        if basket.len() > MAX_BASKET_LEGS {
+            return Err(ClaimError::BasketTooLarge);
+        }
+        if basket.iter().any(|(_, amount)| amount.0 == 0) {
+            return Err(ClaimError::ZeroAmount);
+        }
+        let account_id = env::predecessor_account_id();
+        self.assert_terms_accepted(&account_id)?;
+        if self.is_on_legal_hold(account_id.clone()) {
+            return Err(ClaimError::LegalHold);
+        }
+        let leaf = self.domain_leaf(encode_basket_leaf(&account_id, &basket));
+        if !Self::verify_merkle_proof_with(
+            self.hash_algorithm,
+            self.leaf_format,
+            leaf,
+            &self.merkle_root,
+            &merkle_proof,
+        )? {
+            return Err(ClaimError::InvalidProof);
+        }
+
+        let mut paid = self
+            .basket_legs
+            .get(&account_id)
+            .cloned()
+            .unwrap_or_default();
+        let legs: Vec<(AccountId, U128)> = basket
+            .into_iter()
+            .filter(|(token_contract, _)| !paid.contains(token_contract))
+            .collect();
+        if legs.is_empty() {
+            return Err(ClaimError::AlreadyClaimed);
+        }
+        let leg_gas = GAS_FOR_STORAGE_DEPOSIT
+            .saturating_add(GAS_FOR_FT_TRANSFER)
+            .saturating_add(GAS_FOR_BASKET_LEG_CALLBACK.saturating_mul(2));
+        let required = leg_gas
+            .saturating_mul(legs.len() as u64)
+            .saturating_add(CLAIM_GAS_RESERVE);
+        if env::prepaid_gas().saturating_sub(env::used_gas()) < required {
+            return Err(ClaimError::InsufficientGas);
+        }
+        self.internal_count_claim()?;
+        for (token_contract, amount) in &legs {
+            if *token_contract == self.token_contract {
+                self.internal_reserve_basket_leg(amount.0)?;
+            }
+            paid.push(token_contract.clone());
+        }
+        self.basket_legs.insert(account_id.clone(), paid);
+
+        for (token_contract, amount) in &legs {
+            let storage_deposit = if *token_contract == self.token_contract {
+                self.token_storage_deposit()
+            } else {
+                STORAGE_DEPOSIT_AMOUNT
+            };
+            Self::storage_deposits(token_contract, vec![(account_id.clone(), storage_deposit)])
+                .unwrap_or_else(|| env::abort())
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(
+                            GAS_FOR_FT_TRANSFER
+                                .saturating_add(GAS_FOR_BASKET_LEG_CALLBACK.saturating_mul(2)),
+                        )
+                        .on_basket_storage_then_transfer(
+                            account_id.clone(),
+                            token_contract.clone(),
+                            *amount,
+                            self.root_version,
+                        ),
+                );
+        }
+        Ok(legs.len() as u32)
+    }
+
+    /// Callback: After the storage_deposit of a basket leg, transfers its tokens, or releases
+    /// the leg if the Merkle root changed since `root_version`.
+    #[private]
+    pub fn on_basket_storage_then_transfer(
+        &mut self,
+        account_id: AccountId,
+        token_contract: AccountId,
+        amount: U128,
+        root_version: u32,
+        #[callback_result] call_result: Result<Option<serde_json::Value>, near_sdk::PromiseError>,
+    ) -> PromiseOrValue<bool> {
+        let status = if call_result.is_err() {
+            Some(ClaimStatus::StorageFailed)
+        } else if root_version != self.root_version {
+            Some(ClaimStatus::RootChanged)
+        } else {
+            None
+        };
+        if let Some(status) = status {
+            self.internal_release_basket_leg(
+                &account_id,
+                &token_contract,
+                amount,
+                root_version,
+                status,
+            );
+            return PromiseOrValue::Value(false);
+        }
+        Promise::new(token_contract.clone())
+            .function_call(
+                "ft_transfer".to_string(),
+                Self::ft_transfer_args(&account_id, amount.0, None),
+                NearToken::from_yoctonear(1),
+                GAS_FOR_FT_TRANSFER,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_BASKET_LEG_CALLBACK)
+                    .on_basket_leg_transferred(account_id, token_contract, amount, root_version),
+            )
+            .into()
+    }
+
+    /// Callback: After the ft_transfer of a basket leg, marks it as paid or releases it.
+    #[private]
+    pub fn on_basket_leg_transferred(
+        &mut self,
+        account_id: AccountId,
+        token_contract: AccountId,
+        amount: U128,
+        root_version: u32,
+        #[callback_result] call_result: Result<(), near_sdk::PromiseError>,
+    ) -> bool {
+        if call_result.is_err() {
+            self.internal_release_basket_leg(
+                &account_id,
+                &token_contract,
+                amount,
+                root_version,
+                ClaimStatus::TransferFailed,
+            );
+            return false;
+        }
+        if token_contract == self.token_contract {
+            self.reserved = self.reserved.saturating_sub(amount.0);
+        }
+        AirdropEvent::BasketClaim(vec![BasketClaimEvent {
+            account_id,
+            token_contract,
+            amount,
+        }])
+        .emit();
+        true
+    }
+
+    /// Returns the tokens of the basket of `account_id` already paid or in flight.
+    pub fn get_basket_legs(&self, account_id: AccountId) -> Vec<AccountId> {
+        self.basket_legs
+            .get(&account_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl AirdropContract {
+    /// Reserves a basket leg of the airdropped token against the distribution cap and the
+    /// declared root total.
+    pub(crate) fn internal_reserve_basket_leg(&mut self, amount: u128) -> Result<(), ClaimError> {
+        if self.max_claim_amount.is_some_and(|max| amount > max.0) {
+            return Err(ClaimError::AmountTooLarge);
+        }
+        let distributed = self
+            .distributed
+            .checked_add(amount)
+            .ok_or(ClaimError::AmountOverflow)?;
+        if self.distribution_cap.is_some_and(|cap| distributed > cap.0) {
+            return Err(ClaimError::CapExceeded);
+        }
+        self.internal_count_root_claim(amount)?;
+        self.distributed = distributed;
+        self.reserved = self
+            .reserved
+            .checked_add(amount)
+            .ok_or(ClaimError::AmountOverflow)?;
+        Ok(())
+    }
+
+    /// Reverts a failed basket leg, reserved against the root of `root_version`, so it can be
+    /// claimed again.
+    fn internal_release_basket_leg(
+        &mut self,
+        account_id: &AccountId,
+        token_contract: &AccountId,
+        amount: U128,
+        root_version: u32,
+        status: ClaimStatus,
+    ) {
+        if let Some(paid) = self.basket_legs.get_mut(account_id) {
+            paid.retain(|paid_token| paid_token != token_contract);
+        }
+        if *token_contract == self.token_contract {
+            self.distributed = self.distributed.saturating_sub(amount.0);
+            self.reserved = self.reserved.saturating_sub(amount.0);
+            if root_version == self.root_version {
+                self.root_claimed = self.root_claimed.saturating_sub(amount.0);
+            }
+        }
+        self.internal_record_failure(account_id, amount, status);
+    }
+}
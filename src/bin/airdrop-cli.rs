@@ -0,0 +1,173 @@
+//! Builds airdrop Merkle trees and checks proofs with the contract's own hashing.
+//!
+//! ```text
+//! airdrop-cli build <accounts.csv> [proofs.json] [--sha256] [--v2]
+//! airdrop-cli verify <root> <account_id> <amount> [proof...] [--sha256] [--v2]
+//! ```
+
+use anyhow::{anyhow, bail, Context, Result};
+use near_sdk::serde_json::{self, json};
+use near_sdk::AccountId;
+use publicai_airdrop::{
+    normalize_account_id, AirdropContract, HashAlgorithm, LeafExtras, LeafFormat, MerkleTree,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+
+const USAGE: &str = "Usage:
+  airdrop-cli build <accounts.csv> [proofs.json] [--sha256] [--v2]
+      Reads `account,amount` rows, prints the root and totals and writes the proofs
+      (default: proofs.json).
+  airdrop-cli verify <root> <account_id> <amount> [proof...] [--sha256] [--v2]
+      Checks a proof against a root, exactly like claim_airdrop does.";
+
+fn main() {
+    if let Err(err) = run(std::env::args().skip(1).collect()) {
+        eprintln!("Error: {:#}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<()> {
+    let (flags, args): (Vec<String>, Vec<String>) =
+        args.into_iter().partition(|arg| arg.starts_with("--"));
+    let mut hash_algorithm = HashAlgorithm::Keccak256;
+    let mut leaf_format = LeafFormat::V1;
+    for flag in &flags {
+        match flag.as_str() {
+            "--sha256" => hash_algorithm = HashAlgorithm::Sha256,
+            "--v2" => leaf_format = LeafFormat::V2,
+            _ => bail!("unknown flag {}\n\n{}", flag, USAGE),
+        }
+    }
+    match args.first().map(String::as_str) {
+        Some("build") if args.len() == 2 || args.len() == 3 => build(
+            hash_algorithm,
+            leaf_format,
+            &args[1],
+            args.get(2).map_or("proofs.json", String::as_str),
+        ),
+        Some("verify") if args.len() >= 4 => verify(
+            hash_algorithm,
+            leaf_format,
+            &args[1],
+            &args[2],
+            &args[3],
+            &args[4..],
+        ),
+        _ => bail!("{}", USAGE),
+    }
+}
+
+fn build(
+    hash_algorithm: HashAlgorithm,
+    leaf_format: LeafFormat,
+    csv_path: &str,
+    proofs_path: &str,
+) -> Result<()> {
+    let csv = fs::read_to_string(csv_path).with_context(|| format!("reading {}", csv_path))?;
+    let entries = parse_csv(&csv)?;
+    let total = entries
+        .iter()
+        .try_fold(0u128, |total, (_, amount)| total.checked_add(*amount))
+        .ok_or_else(|| anyhow!("the total amount overflows u128"))?;
+    let tree = MerkleTree::from_entries_with(hash_algorithm, leaf_format, entries);
+
+    let claims: BTreeMap<String, serde_json::Value> = tree
+        .entries()
+        .iter()
+        .enumerate()
+        .map(|(index, (account_id, amount))| {
+            let claim = json!({
+                "amount": amount.to_string(),
+                "leaf": LeafExtras::default().encode_leaf(account_id, *amount),
+                "proof": tree.proof(index).unwrap_or_default(),
+            });
+            (account_id.to_string(), claim)
+        })
+        .collect();
+    let proofs = json!({
+        "root": tree.root(),
+        "hash_algorithm": hash_algorithm,
+        "leaf_format": leaf_format,
+        "total": total.to_string(),
+        "claims": claims,
+    });
+    fs::write(proofs_path, serde_json::to_string_pretty(&proofs)?)
+        .with_context(|| format!("writing {}", proofs_path))?;
+
+    println!("Root:     {}", tree.root());
+    println!("Accounts: {}", tree.entries().len());
+    println!("Total:    {}", total);
+    println!("Proofs written to {}", proofs_path);
+    Ok(())
+}
+
+fn verify(
+    hash_algorithm: HashAlgorithm,
+    leaf_format: LeafFormat,
+    root: &str,
+    account_id: &str,
+    amount: &str,
+    proof: &[String],
+) -> Result<()> {
+    let account_id = normalize_account_id(account_id)
+        .ok_or_else(|| anyhow!("invalid account ID {}", account_id))?;
+    let amount: u128 = amount
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid amount {}", amount))?;
+    let leaf = LeafExtras::default().encode_leaf(&account_id, amount);
+    let valid = AirdropContract::verify_merkle_proof_with(
+        hash_algorithm,
+        leaf_format,
+        leaf.clone(),
+        root,
+        proof,
+    )
+    .map_err(|err| anyhow!("{}", err))?;
+    if !valid {
+        bail!("the proof of {} does not match the root", leaf);
+    }
+    println!("Valid proof of {}", leaf);
+    Ok(())
+}
+
+/// Parses `account,amount` rows, skipping blank lines, `#` comments and a header row.
+fn parse_csv(csv: &str) -> Result<Vec<(AccountId, u128)>> {
+    let mut entries = Vec::new();
+    let mut seen = HashSet::new();
+    let mut first_line = None;
+    for (index, line) in csv.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let header_line = *first_line.get_or_insert(line_number);
+        let (account_id, amount) = line
+            .split_once(',')
+            .ok_or_else(|| anyhow!("line {}: expected `account,amount`", line_number))?;
+        let amount = amount.trim();
+        if line_number == header_line && amount.parse::<u128>().is_err() {
+            // Header row
+            continue;
+        }
+        let account_id = normalize_account_id(account_id)
+            .ok_or_else(|| anyhow!("line {}: invalid account ID {}", line_number, account_id))?;
+        let amount: u128 = amount
+            .parse()
+            .with_context(|| format!("line {}: invalid amount {}", line_number, amount))?;
+        if amount == 0 {
+            bail!("line {}: the amount of {} is zero", line_number, account_id);
+        }
+        if !seen.insert(account_id.clone()) {
+            bail!("line {}: duplicate account {}", line_number, account_id);
+        }
+        entries.push((account_id, amount));
+    }
+    if entries.is_empty() {
+        bail!("no entries in the CSV");
+    }
+    Ok(entries)
+}
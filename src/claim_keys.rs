@@ -0,0 +1,123 @@
+use near_sdk::json_types::U128;
+use near_sdk::{
+    env, log, near, require, AccountId, Allowance, NearToken, Promise, PromiseOrValue, PublicKey,
+};
+
+use crate::{
+    AirdropContract, AirdropContractExt, ClaimError, ClaimOutcome, PendingClaim, NO_DEPOSIT,
+};
+
+/// Method the claim keys are restricted to.
+const CLAIM_WITH_KEY_METHOD: &str = "claim_with_key";
+
+#[near]
+impl AirdropContract {
+    /// Adds function-call access keys on this contract, each allowing a single `claim_with_key`
+    /// for `amount` tokens (only callable by the owner, through the council if one is set). The
+    /// private keys are handed out as links, e.g. by Keypom, so new users can claim before they
+    /// own any NEAR. With a withdrawal delay, the total of the keys must have been announced,
+    /// see `announce_withdrawal`.
+    /// - `allowance`: NEAR each key may spend on gas, paid from the contract balance.
+    #[payable]
+    pub fn add_claim_keys(
+        &mut self,
+        public_keys: Vec<PublicKey>,
+        amount: U128,
+        allowance: NearToken,
+    ) -> Promise {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("add_claim_keys");
+        self.assert_no_council();
+        require!(!public_keys.is_empty(), "No keys to add");
+        require!(amount.0 > 0, "The amount must be positive");
+        let total = amount
+            .0
+            .checked_mul(public_keys.len() as u128)
+            .unwrap_or_else(|| env::panic_str("Accounting overflow"));
+        self.internal_take_announced_withdrawal(U128(total));
+        let allowance = Allowance::limited(allowance).unwrap_or_else(|| {
+            env::panic_str("The allowance must be positive");
+        });
+        let contract_id = env::current_account_id();
+        let mut promise = Promise::new(contract_id.clone());
+        for public_key in public_keys {
+            self.claim_keys.insert(public_key.clone(), amount);
+            promise = promise.add_access_key_allowance(
+                public_key,
+                allowance,
+                contract_id.clone(),
+                CLAIM_WITH_KEY_METHOD.to_string(),
+            );
+        }
+        promise
+    }
+
+    /// Removes unused claim keys and their access keys (only callable by the owner).
+    #[payable]
+    pub fn remove_claim_keys(&mut self, public_keys: Vec<PublicKey>) -> Promise {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("remove_claim_keys");
+        require!(!public_keys.is_empty(), "No keys to remove");
+        let mut promise = Promise::new(env::current_account_id());
+        for public_key in public_keys {
+            self.claim_keys.remove(&public_key);
+            promise = promise.delete_key(public_key);
+        }
+        promise
+    }
+
+    /// Claims the tokens of the signing claim key for `account_id`, then deletes the key once
+    /// the claim succeeds; if it fails, the key can be used again.
+    /// Must be signed with a key added by `add_claim_keys`; no deposit is needed, the contract
+    /// pays for the registration of `account_id`. Key claims are tracked apart from leaves:
+    /// `account_id` can still claim its leaf, and the tokens do not count against the root
+    /// totals.
+    #[handle_result]
+    pub fn claim_with_key(
+        &mut self,
+        account_id: AccountId,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        require!(
+            env::predecessor_account_id() == env::current_account_id(),
+            "Only callable with a claim key"
+        );
+        let public_key = env::signer_account_pk();
+        let amount = self
+            .claim_keys
+            .remove(&public_key)
+            .ok_or(ClaimError::InvalidClaimKey)?;
+        self.assert_claims_open()?;
+        // Escrowed claims are held per account
+        if self.escrow.contains_key(&account_id) {
+            return Err(ClaimError::AlreadyClaimed);
+        }
+        log!("Claim key used by @{}", account_id);
+        let claim = PendingClaim {
+            deposit: U128(0),
+            claim_key: Some(public_key),
+            ..PendingClaim::new(account_id, amount, NO_DEPOSIT)
+        };
+        self.internal_start_claim(claim)
+    }
+
+    /// Returns the tokens claimable with `public_key`, `None` if it is not an unused claim key.
+    pub fn get_claim_key(&self, public_key: PublicKey) -> Option<U128> {
+        self.claim_keys.get(&public_key).copied()
+    }
+}
+
+impl AirdropContract {
+    /// Deletes the access key of the claim key that paid `claim`, if any, once it is settled.
+    pub(crate) fn internal_delete_claim_key(&self, claim: &PendingClaim) {
+        if let Some(public_key) = &claim.claim_key {
+            Promise::new(env::current_account_id()).delete_key(public_key.clone());
+        }
+    }
+
+    /// Makes the claim key that paid the failed `claim`, if any, usable again.
+    pub(crate) fn internal_restore_claim_key(&mut self, claim: &PendingClaim) {
+        if let Some(public_key) = &claim.claim_key {
+            self.claim_keys.insert(public_key.clone(), claim.amount);
+        }
+    }
+}
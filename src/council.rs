@@ -0,0 +1,271 @@
+use near_sdk::json_types::{Base58CryptoHash, U128, U64};
+use near_sdk::{assert_one_yocto, env, log, near, require, AccountId};
+
+use crate::{AirdropContract, AirdropContractExt, LeafFormat, RootTotals, UnclaimedBurn};
+
+/// Members who must confirm sensitive owner actions, `threshold` of them.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Council {
+    pub members: Vec<AccountId>,
+    pub threshold: u32,
+}
+
+/// Sensitive action executed once confirmed by the council.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CouncilAction {
+    UpdateMerkleRoot {
+        merkle_root: String,
+        total_amount: U128,
+        leaf_count: u32,
+        leaf_format: Option<LeafFormat>,
+    },
+    WithdrawToken {
+        amount: U128,
+    },
+    DeployStagedCode {
+        code_hash: Base58CryptoHash,
+    },
+    /// Replaces the council, or removes it with `None`.
+    SetCouncil {
+        council: Option<Council>,
+    },
+    SetTreasury {
+        treasury_id: AccountId,
+    },
+    SetClaimDeadline {
+        claim_deadline: Option<U64>,
+    },
+    SetUnclaimedBurn {
+        unclaimed_burn: Option<UnclaimedBurn>,
+    },
+    Distribute {
+        recipients: Vec<(AccountId, U128)>,
+    },
+    /// Runs one batch of the finalization, see `finalize_campaign`.
+    FinalizeCampaign {
+        batch_size: u32,
+        send_recovered_near: bool,
+    },
+}
+
+/// Proposed council action and the members who confirmed it.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CouncilProposal {
+    pub action: CouncilAction,
+    pub proposer: AccountId,
+    pub confirmations: Vec<AccountId>,
+    /// Block time of the proposal, in nanoseconds since the Unix epoch.
+    pub proposed_at: U64,
+}
+
+#[near]
+impl AirdropContract {
+    /// Sets the council confirming sensitive actions (only callable by the owner, and only while
+    /// there is no council). Once set, root updates, withdrawals, distributions, treasury,
+    /// deadline and burn changes, finalization and upgrades go through `propose_action`, and so
+    /// do council changes.
+    #[payable]
+    pub fn set_council(&mut self, council: Council) {
+        self.assert_owner("Owner's method");
+        require!(
+            self.council.is_none(),
+            "The council can only be changed by a SetCouncil action"
+        );
+        Self::assert_valid_council(&council);
+        self.record_admin_action("set_council");
+        log!(
+            "Council set to {} of {:?}",
+            council.threshold,
+            council.members
+        );
+        self.council = Some(council);
+    }
+
+    /// Proposes a sensitive action (callable by the owner and council members), confirmed by
+    /// the proposer if it is a member. Returns the ID of the proposal.
+    #[payable]
+    pub fn propose_action(&mut self, action: CouncilAction) -> u32 {
+        self.assert_admin_deposit();
+        let proposer = env::predecessor_account_id();
+        require!(
+            proposer == self.owner_id || self.is_council_member(&proposer),
+            "Only the owner or a council member can propose"
+        );
+        require!(self.council.is_some(), "No council is set");
+        self.record_admin_action("propose_action");
+        let id = self.next_council_proposal;
+        self.next_council_proposal += 1;
+        log!("Council proposal {} by @{}: {:?}", id, proposer, action);
+        self.council_proposals.insert(
+            id,
+            CouncilProposal {
+                action,
+                proposer: proposer.clone(),
+                confirmations: vec![],
+                proposed_at: U64(env::block_timestamp()),
+            },
+        );
+        if self.is_council_member(&proposer) {
+            self.internal_confirm(id, proposer);
+        }
+        id
+    }
+
+    /// Confirms proposal `id` (only callable by council members), executing it once it has
+    /// `threshold` confirmations. Returns whether it was executed.
+    #[payable]
+    pub fn confirm_action(&mut self, id: u32) -> bool {
+        self.assert_admin_deposit();
+        let member = env::predecessor_account_id();
+        require!(
+            self.is_council_member(&member),
+            "Only council members can confirm"
+        );
+        self.record_admin_action("confirm_action");
+        self.internal_confirm(id, member)
+    }
+
+    /// Cancels proposal `id` (callable by the owner and its proposer).
+    #[payable]
+    pub fn cancel_action(&mut self, id: u32) {
+        self.assert_admin_deposit();
+        let proposal = self
+            .council_proposals
+            .get(&id)
+            .unwrap_or_else(|| env::panic_str("No such proposal"));
+        let caller = env::predecessor_account_id();
+        require!(
+            caller == self.owner_id || caller == proposal.proposer,
+            "Only the owner or the proposer can cancel"
+        );
+        self.record_admin_action("cancel_action");
+        self.council_proposals.remove(&id);
+        log!("Council proposal {} cancelled by @{}", id, caller);
+    }
+
+    /// Returns the council, if any.
+    pub fn get_council(&self) -> Option<Council> {
+        self.council.clone()
+    }
+
+    /// Returns the pending proposal `id`, if any.
+    pub fn get_council_proposal(&self, id: u32) -> Option<CouncilProposal> {
+        self.council_proposals.get(&id).cloned()
+    }
+
+    /// Returns up to `limit` pending proposals with IDs from `from_id`.
+    pub fn get_council_proposals(&self, from_id: u32, limit: u32) -> Vec<(u32, CouncilProposal)> {
+        (from_id..self.next_council_proposal)
+            .filter_map(|id| Some((id, self.council_proposals.get(&id)?.clone())))
+            .take(limit as usize)
+            .collect()
+    }
+}
+
+impl AirdropContract {
+    /// Panics if sensitive actions require council confirmation.
+    pub(crate) fn assert_no_council(&self) {
+        require!(
+            self.council.is_none(),
+            "Requires council confirmation, see propose_action"
+        );
+    }
+
+    fn assert_admin_deposit(&self) {
+        if self.admin_deposit_required {
+            assert_one_yocto();
+        }
+    }
+
+    fn assert_valid_council(council: &Council) {
+        let mut members = council.members.clone();
+        members.sort();
+        members.dedup();
+        require!(
+            members.len() == council.members.len(),
+            "Duplicate council members"
+        );
+        require!(
+            council.threshold > 0 && council.threshold as usize <= members.len(),
+            "The threshold must be between 1 and the number of members"
+        );
+    }
+
+    fn is_council_member(&self, account_id: &AccountId) -> bool {
+        self.council
+            .as_ref()
+            .is_some_and(|council| council.members.contains(account_id))
+    }
+
+    /// Adds the confirmation of `member` to proposal `id` and executes it once confirmed.
+    fn internal_confirm(&mut self, id: u32, member: AccountId) -> bool {
+        let threshold = self.council.as_ref().map_or(0, |council| council.threshold);
+        let proposal = self
+            .council_proposals
+            .get_mut(&id)
+            .unwrap_or_else(|| env::panic_str("No such proposal"));
+        require!(
+            !proposal.confirmations.contains(&member),
+            "Already confirmed"
+        );
+        proposal.confirmations.push(member.clone());
+        log!("Council proposal {} confirmed by @{}", id, member);
+        if (proposal.confirmations.len() as u32) < threshold {
+            return false;
+        }
+        let proposal = self
+            .council_proposals
+            .remove(&id)
+            .unwrap_or_else(|| env::abort());
+        log!("Council proposal {} executed", id);
+        match proposal.action {
+            CouncilAction::UpdateMerkleRoot {
+                merkle_root,
+                total_amount,
+                leaf_count,
+                leaf_format,
+            } => self.internal_update_merkle_root(
+                &merkle_root,
+                RootTotals {
+                    total_amount,
+                    leaf_count,
+                },
+                leaf_format,
+            ),
+            CouncilAction::WithdrawToken { amount } => {
+                self.assert_announced_withdrawal(amount);
+                self.internal_withdraw_token(amount);
+            }
+            CouncilAction::DeployStagedCode { code_hash } => {
+                self.internal_deploy_staged_code(code_hash)
+            }
+            CouncilAction::SetCouncil { council } => {
+                if let Some(council) = &council {
+                    Self::assert_valid_council(council);
+                }
+                log!("Council set to {:?}", council);
+                self.council = council;
+            }
+            CouncilAction::SetTreasury { treasury_id } => self.internal_set_treasury(treasury_id),
+            CouncilAction::SetClaimDeadline { claim_deadline } => {
+                self.internal_set_claim_deadline(claim_deadline)
+            }
+            CouncilAction::SetUnclaimedBurn { unclaimed_burn } => {
+                self.internal_set_unclaimed_burn(unclaimed_burn)
+            }
+            CouncilAction::Distribute { recipients } => {
+                self.internal_distribute(recipients);
+            }
+            CouncilAction::FinalizeCampaign {
+                batch_size,
+                send_recovered_near,
+            } => {
+                self.internal_finalize(batch_size, send_recovered_near);
+            }
+        }
+        true
+    }
+}
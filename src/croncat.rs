@@ -0,0 +1,91 @@
+use near_sdk::{env, log, near, require, AccountId, PromiseOrValue};
+
+use crate::{AirdropContract, AirdropContractExt};
+
+/// Claim records deleted per tick while finalizing.
+const CRONCAT_FINALIZE_BATCH: u32 = 100;
+/// Escrowed claims released per tick.
+const CRONCAT_MAX_RELEASES: u32 = 10;
+/// Expired IOUs reverted per tick.
+const CRONCAT_MAX_EXPIRIES: u32 = 10;
+/// Outstanding IOUs checked for expiry per tick.
+const CRONCAT_IOU_SCAN: u32 = 50;
+
+#[near]
+impl AirdropContract {
+    /// Sets the Croncat manager contract allowed to call `croncat_tick`, or disables scheduled
+    /// maintenance with `None` (only callable by the owner).
+    #[payable]
+    pub fn set_croncat_manager(&mut self, croncat_manager: Option<AccountId>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_croncat_manager");
+        self.croncat_manager = croncat_manager;
+        log!("Croncat manager set to {:?}", self.croncat_manager);
+    }
+
+    /// Scheduled maintenance, callable by the Croncat manager or, without a council, the owner.
+    /// Releases up to
+    /// `CRONCAT_MAX_RELEASES` escrowed claims whose dispute window has ended, reverts up to
+    /// `CRONCAT_MAX_EXPIRIES` IOUs past the IOU expiry and, once the claim deadline has passed,
+    /// finalizes the airdrop in batches, sweeping the unclaimed tokens like `finalize_campaign`
+    /// without sending the recovered NEAR. Returns the number of escrowed claims released.
+    pub fn croncat_tick(&mut self) -> u32 {
+        let caller = env::predecessor_account_id();
+        if self.croncat_manager.as_ref() != Some(&caller) {
+            require!(caller == self.owner_id, "Only the Croncat manager can tick");
+            // Ticks may finalize and sweep, which the owner only does through the council
+            self.assert_no_council();
+        }
+
+        let released = self.internal_release_matured_escrow(CRONCAT_MAX_RELEASES);
+        let expired = self.internal_expire_ious(CRONCAT_MAX_EXPIRIES, CRONCAT_IOU_SCAN);
+        if expired > 0 {
+            log!("{} IOUs expired", expired);
+        }
+        let deadline_passed = self
+            .claim_deadline
+            .is_some_and(|deadline| env::block_timestamp() >= deadline.0);
+        if deadline_passed && (!self.finalized || !self.claim_index.is_empty()) {
+            if let PromiseOrValue::Value(false) =
+                self.internal_finalize(CRONCAT_FINALIZE_BATCH, false)
+            {
+                log!("Finalization continues on the next tick");
+            }
+        }
+        released
+    }
+
+    /// Returns the Croncat manager allowed to call `croncat_tick`, if any.
+    pub fn get_croncat_manager(&self) -> Option<AccountId> {
+        self.croncat_manager.clone()
+    }
+}
+
+impl AirdropContract {
+    /// Releases up to `limit` escrowed claims in the order they were escrowed, stopping at the
+    /// first one still in its dispute window or when the prepaid gas runs out.
+    fn internal_release_matured_escrow(&mut self, limit: u32) -> u32 {
+        let mut released = 0;
+        while released < limit && self.escrow_cursor < self.escrow_queue.len() {
+            let account_id = self.escrow_queue[self.escrow_cursor].clone();
+            let Some(escrowed) = self.escrow.get(&account_id) else {
+                // Cancelled or released manually
+                self.escrow_cursor += 1;
+                continue;
+            };
+            if env::block_timestamp() < escrowed.releases_at.0
+                || self.assert_claim_gas(&escrowed.claim).is_err()
+            {
+                break;
+            }
+            let escrowed = self
+                .escrow
+                .remove(&account_id)
+                .unwrap_or_else(|| env::abort());
+            self.internal_transfer_unless_held(escrowed.claim);
+            self.escrow_cursor += 1;
+            released += 1;
+        }
+        released
+    }
+}
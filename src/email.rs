@@ -0,0 +1,113 @@
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::{env, log, near, AccountId, CurveType, PromiseOrValue, PublicKey};
+
+use crate::{AirdropContract, AirdropContractExt, ClaimError, ClaimOutcome, Denomination};
+
+/// Message the attestor signs to bind `email_hash` to `account_id` on `contract_id`.
+pub fn email_attestation_message(
+    contract_id: &AccountId,
+    email_hash: &str,
+    account_id: &AccountId,
+) -> String {
+    format!("{}:{}:{}", contract_id, email_hash, account_id)
+}
+
+#[near]
+impl AirdropContract {
+    /// Sets the ed25519 key of the attestor signing email mappings, or disables email claims
+    /// with `None` (only callable by the owner).
+    #[payable]
+    pub fn set_attestor_key(&mut self, attestor_key: Option<PublicKey>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_attestor_key");
+        if let Some(key) = &attestor_key {
+            assert!(
+                key.curve_type() == CurveType::ED25519,
+                "The attestor key must be an ed25519 key."
+            );
+        }
+        self.attestor_key = attestor_key;
+        log!("Attestor key set to {:?}", self.attestor_key);
+    }
+
+    /// Claims a leaf `email_hash:amount` keyed by the salted hash of an email. `signature` is the
+    /// attestor's ed25519 signature of `email_attestation_message`, binding the email to the
+    /// caller. Each email hash can only be claimed by one account.
+    #[payable]
+    #[handle_result]
+    pub fn claim_with_email(
+        &mut self,
+        email_hash: String,
+        amount: U128,
+        merkle_proof: Vec<String>,
+        signature: Base64VecU8,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_claim_deposit()?;
+        self.internal_count_relayed_claim()?;
+        self.assert_denomination(Denomination::Tokens)?;
+        self.assert_claims_open()?;
+        let account_id = env::predecessor_account_id();
+        if amount.0 == 0 {
+            return Err(ClaimError::ZeroAmount);
+        }
+        if self.is_claimed(&account_id)
+            || self
+                .claimed_emails
+                .get(&email_hash)
+                .is_some_and(|claimant| *claimant != account_id)
+        {
+            return Err(ClaimError::AlreadyClaimed);
+        }
+        self.assert_email_attestation(&email_hash, &account_id, &signature.0)?;
+        if !Self::verify_merkle_proof_with(
+            self.hash_algorithm,
+            self.leaf_format,
+            self.domain_leaf(format!("{}:{}", email_hash, amount.0)),
+            &self.merkle_root,
+            &merkle_proof,
+        )? {
+            return Err(ClaimError::InvalidProof);
+        }
+
+        // The email stays bound to the account, which can retry if the transfer fails
+        self.claimed_emails.insert(email_hash, account_id.clone());
+        let claim = self.internal_new_claim(account_id, amount);
+        self.internal_start_claim(claim)
+    }
+
+    /// Returns the ed25519 key of the attestor, `None` if email claims are disabled.
+    pub fn get_attestor_key(&self) -> Option<PublicKey> {
+        self.attestor_key.clone()
+    }
+
+    /// Returns the account that claimed `email_hash`, if any.
+    pub fn get_email_claimant(&self, email_hash: String) -> Option<AccountId> {
+        self.claimed_emails.get(&email_hash).cloned()
+    }
+}
+
+impl AirdropContract {
+    /// Ensures the attestor signed the mapping of `email_hash` to `account_id`.
+    fn assert_email_attestation(
+        &self,
+        email_hash: &str,
+        account_id: &AccountId,
+        signature: &[u8],
+    ) -> Result<(), ClaimError> {
+        let key = self
+            .attestor_key
+            .as_ref()
+            .ok_or(ClaimError::InvalidAttestation)?;
+        let key: &[u8; 32] = key.as_bytes()[1..]
+            .try_into()
+            .map_err(|_| ClaimError::InvalidAttestation)?;
+        let signature: &[u8; 64] = signature
+            .try_into()
+            .map_err(|_| ClaimError::InvalidAttestation)?;
+        let message = email_attestation_message(&env::current_account_id(), email_hash, account_id);
+        if !env::ed25519_verify(signature, message.as_bytes(), key) {
+            return Err(ClaimError::InvalidAttestation);
+        }
+        Ok(())
+    }
+}
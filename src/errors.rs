@@ -0,0 +1,226 @@
+use near_sdk::FunctionError;
+use std::fmt;
+
+/// Errors returned by the claim path. The contract panics with `"<code>: <message>"`,
+/// so frontends can match on the stable code instead of the message wording.
+#[derive(Debug, Clone, PartialEq, Eq, FunctionError)]
+pub enum ClaimError {
+    /// The account is already in the claimed set.
+    AlreadyClaimed,
+    /// The attached deposit is not exactly 1 yoctoNEAR plus the claim fee.
+    InvalidDeposit,
+    /// The leaf's own expiry has passed.
+    LeafExpired,
+    /// The proof does not rebuild the current Merkle root.
+    InvalidProof,
+    /// A proof element is not a valid 32-byte hash in hex, base58 or base64.
+    InvalidProofEncoding,
+    /// A multi-entry claim was submitted without entries.
+    NoEntries,
+    /// The claimed amounts do not fit in a `u128`.
+    AmountOverflow,
+    /// Nothing is left to claim, e.g. the amount has fully decayed or the deadline has passed.
+    CampaignClosed,
+    /// The token balance could not be queried.
+    BalanceUnavailable,
+    /// The contract holds fewer tokens than requested.
+    Underfunded,
+    /// `claim_and_stake` was called without a configured staking contract.
+    StakingUnavailable,
+    /// The `ft_transfer_call` message passed by the claimer is not allowlisted.
+    MsgNotAllowed,
+    /// The claim method does not match the denomination (tokens, USD or weights) of the leaves.
+    WrongDenomination,
+    /// The oracle price is missing or older than allowed.
+    PriceUnavailable,
+    /// The oracle price yields fewer tokens than the claimer's minimum.
+    SlippageExceeded,
+    /// There is no raffle open for registration.
+    RaffleClosed,
+    /// The account is already registered for the raffle.
+    AlreadyRegistered,
+    /// The account has not won a raffle.
+    NotAWinner,
+    /// The pro-rata registration window has closed.
+    RegistrationClosed,
+    /// The pro-rata registration window is still open.
+    RegistrationOpen,
+    /// The account has not registered for the pro-rata distribution.
+    NotRegistered,
+    /// The claim rate limit of the current block or epoch is reached.
+    RateLimited,
+    /// The claim would exceed the distribution cap of the airdrop.
+    CapExceeded,
+    /// The claim would exceed the total declared with the Merkle root.
+    RootTotalExceeded,
+    /// The current round of the airdrop is closed.
+    RoundClosed,
+    /// The claimed amount exceeds the maximum per account.
+    AmountTooLarge,
+    /// The proof has more than 64 siblings per leaf.
+    ProofTooLong,
+    /// The claimed leaf amount is zero.
+    ZeroAmount,
+    /// The prepaid gas does not cover the storage_deposit/ft_transfer chain of the claim.
+    InsufficientGas,
+    /// The basket leaf has more tokens than `MAX_BASKET_LEGS`.
+    BasketTooLarge,
+    /// No attestor is configured, or its signature of the email mapping is invalid.
+    InvalidAttestation,
+    /// The transaction is not signed with an unused claim key.
+    InvalidClaimKey,
+    /// The claim authorization is missing or not signed by the key of its implicit account,
+    /// or relayed claims are disabled.
+    InvalidAuthorization,
+    /// The claim is relayed by an account that is not a registered relayer.
+    RelayerNotAllowed,
+    /// The relayer has used up its daily claim quota.
+    RelayerQuotaExceeded,
+    /// A near.social post is requested while social posts are disabled.
+    SocialPostsDisabled,
+    /// The account is owed no shortfall from a partial fill.
+    NoShortfall,
+    /// The claims of the tier of the leaf have not started.
+    TierNotOpen,
+    /// The account has not accepted the current terms of service.
+    TermsNotAccepted,
+    /// The account is on legal hold and the claim cannot be deferred.
+    LegalHold,
+    /// No swap route is configured for `claim_and_swap`.
+    SwapUnavailable,
+    /// No liquid staking contract is configured for `claim_and_liquid_stake`.
+    LiquidStakingUnavailable,
+    /// The leaf streams its tokens, which needs a streaming contract and a plain token payout.
+    StreamingUnavailable,
+    /// No lockup factory is configured, or the leaf has no lockup duration.
+    LockupUnavailable,
+    /// The leaf has a lockup duration and must be claimed with `claim_into_lockup`.
+    LockupRequired,
+    /// The lock period is not offered, or its bonus pool is exhausted.
+    LockUnavailable,
+    /// The receivers of a split claim are empty, too many, or do not add up to the leaf amount.
+    InvalidSplit,
+    /// The weight of the leaf is below the lowest weight class.
+    NoWeightClass,
+}
+
+impl ClaimError {
+    /// Machine-readable error code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ClaimError::AlreadyClaimed => "ERR_ALREADY_CLAIMED",
+            ClaimError::InvalidDeposit => "ERR_INVALID_DEPOSIT",
+            ClaimError::LeafExpired => "ERR_LEAF_EXPIRED",
+            ClaimError::InvalidProof => "ERR_INVALID_PROOF",
+            ClaimError::InvalidProofEncoding => "ERR_INVALID_PROOF_ENCODING",
+            ClaimError::NoEntries => "ERR_NO_ENTRIES",
+            ClaimError::AmountOverflow => "ERR_AMOUNT_OVERFLOW",
+            ClaimError::CampaignClosed => "ERR_CAMPAIGN_CLOSED",
+            ClaimError::BalanceUnavailable => "ERR_BALANCE_UNAVAILABLE",
+            ClaimError::Underfunded => "ERR_UNDERFUNDED",
+            ClaimError::StakingUnavailable => "ERR_STAKING_UNAVAILABLE",
+            ClaimError::MsgNotAllowed => "ERR_MSG_NOT_ALLOWED",
+            ClaimError::WrongDenomination => "ERR_WRONG_DENOMINATION",
+            ClaimError::PriceUnavailable => "ERR_PRICE_UNAVAILABLE",
+            ClaimError::SlippageExceeded => "ERR_SLIPPAGE_EXCEEDED",
+            ClaimError::RaffleClosed => "ERR_RAFFLE_CLOSED",
+            ClaimError::AlreadyRegistered => "ERR_ALREADY_REGISTERED",
+            ClaimError::NotAWinner => "ERR_NOT_A_WINNER",
+            ClaimError::RegistrationClosed => "ERR_REGISTRATION_CLOSED",
+            ClaimError::RegistrationOpen => "ERR_REGISTRATION_OPEN",
+            ClaimError::NotRegistered => "ERR_NOT_REGISTERED",
+            ClaimError::RateLimited => "ERR_RATE_LIMITED",
+            ClaimError::CapExceeded => "ERR_CAP_EXCEEDED",
+            ClaimError::RootTotalExceeded => "ERR_ROOT_TOTAL_EXCEEDED",
+            ClaimError::RoundClosed => "ERR_ROUND_CLOSED",
+            ClaimError::AmountTooLarge => "ERR_AMOUNT_TOO_LARGE",
+            ClaimError::ProofTooLong => "ERR_PROOF_TOO_LONG",
+            ClaimError::ZeroAmount => "ERR_ZERO_AMOUNT",
+            ClaimError::InsufficientGas => "ERR_INSUFFICIENT_GAS",
+            ClaimError::BasketTooLarge => "ERR_BASKET_TOO_LARGE",
+            ClaimError::InvalidAttestation => "ERR_INVALID_ATTESTATION",
+            ClaimError::InvalidClaimKey => "ERR_INVALID_CLAIM_KEY",
+            ClaimError::InvalidAuthorization => "ERR_INVALID_AUTHORIZATION",
+            ClaimError::RelayerNotAllowed => "ERR_RELAYER_NOT_ALLOWED",
+            ClaimError::RelayerQuotaExceeded => "ERR_RELAYER_QUOTA_EXCEEDED",
+            ClaimError::SocialPostsDisabled => "ERR_SOCIAL_POSTS_DISABLED",
+            ClaimError::NoShortfall => "ERR_NO_SHORTFALL",
+            ClaimError::TierNotOpen => "ERR_TIER_NOT_OPEN",
+            ClaimError::TermsNotAccepted => "ERR_TERMS_NOT_ACCEPTED",
+            ClaimError::LegalHold => "ERR_LEGAL_HOLD",
+            ClaimError::SwapUnavailable => "ERR_SWAP_UNAVAILABLE",
+            ClaimError::LiquidStakingUnavailable => "ERR_LIQUID_STAKING_UNAVAILABLE",
+            ClaimError::StreamingUnavailable => "ERR_STREAMING_UNAVAILABLE",
+            ClaimError::LockupUnavailable => "ERR_LOCKUP_UNAVAILABLE",
+            ClaimError::LockupRequired => "ERR_LOCKUP_REQUIRED",
+            ClaimError::LockUnavailable => "ERR_LOCK_UNAVAILABLE",
+            ClaimError::InvalidSplit => "ERR_INVALID_SPLIT",
+            ClaimError::NoWeightClass => "ERR_NO_WEIGHT_CLASS",
+        }
+    }
+
+    fn message(&self) -> &'static str {
+        match self {
+            ClaimError::AlreadyClaimed => "You have already claimed your airdrop.",
+            ClaimError::InvalidDeposit => "Attach exactly 1 yoctoNEAR plus the claim fee.",
+            ClaimError::LeafExpired => "This allocation has expired.",
+            ClaimError::InvalidProof => "Merkle proof verification failed.",
+            ClaimError::InvalidProofEncoding => "Invalid hash in Merkle proof.",
+            ClaimError::NoEntries => "No entries to claim.",
+            ClaimError::AmountOverflow => "Claimed amount overflow.",
+            ClaimError::CampaignClosed => "Nothing is left to claim.",
+            ClaimError::BalanceUnavailable => "Failed to get token balance",
+            ClaimError::Underfunded => "Not enough token balance",
+            ClaimError::StakingUnavailable => "No staking contract is configured.",
+            ClaimError::MsgNotAllowed => "This transfer message is not allowed.",
+            ClaimError::WrongDenomination => "Use the claim method matching the leaf denomination.",
+            ClaimError::PriceUnavailable => "No recent token price is available.",
+            ClaimError::SlippageExceeded => "The token price moved beyond the minimum amount.",
+            ClaimError::RaffleClosed => "No raffle is open for registration.",
+            ClaimError::AlreadyRegistered => "You are already registered for the raffle.",
+            ClaimError::NotAWinner => "You have not won a raffle.",
+            ClaimError::RegistrationClosed => "The registration window has closed.",
+            ClaimError::RegistrationOpen => "Claims open when the registration window closes.",
+            ClaimError::NotRegistered => "You have not registered a weight.",
+            ClaimError::RateLimited => "Too many claims right now, try again shortly.",
+            ClaimError::CapExceeded => "The distribution cap of the airdrop is reached.",
+            ClaimError::RootTotalExceeded => "The declared total of the airdrop list is reached.",
+            ClaimError::RoundClosed => "This round is closed, wait for the next one.",
+            ClaimError::AmountTooLarge => "The amount exceeds the maximum per account.",
+            ClaimError::ProofTooLong => "The Merkle proof is too long.",
+            ClaimError::ZeroAmount => "The amount must be positive.",
+            ClaimError::InsufficientGas => "Attach more gas to cover the whole claim.",
+            ClaimError::BasketTooLarge => "The basket has too many tokens.",
+            ClaimError::InvalidAttestation => "The email attestation is invalid.",
+            ClaimError::InvalidClaimKey => "This claim link has already been used.",
+            ClaimError::InvalidAuthorization => "The claim authorization is invalid.",
+            ClaimError::RelayerNotAllowed => "This relayer is not allowed to submit claims.",
+            ClaimError::RelayerQuotaExceeded => {
+                "The relayer has reached its daily quota, try again tomorrow."
+            }
+            ClaimError::SocialPostsDisabled => "This airdrop does not post claims to near.social.",
+            ClaimError::NoShortfall => "You are owed nothing from a partially filled claim.",
+            ClaimError::TierNotOpen => "Claims of your cohort have not started yet.",
+            ClaimError::TermsNotAccepted => "Accept the current terms of service before claiming.",
+            ClaimError::LegalHold => "Your account is on hold, contact the campaign operator.",
+            ClaimError::SwapUnavailable => "No swap route is configured.",
+            ClaimError::LiquidStakingUnavailable => "No liquid staking contract is configured.",
+            ClaimError::StreamingUnavailable => {
+                "This allocation is streamed and must be claimed with a plain token payout."
+            }
+            ClaimError::LockupUnavailable => "No lockup is configured for this allocation.",
+            ClaimError::LockupRequired => {
+                "This allocation is locked and must be claimed into your lockup."
+            }
+            ClaimError::LockUnavailable => "This lock period is not available.",
+            ClaimError::InvalidSplit => "The split amounts must add up to your allocation.",
+            ClaimError::NoWeightClass => "Your weight does not qualify for an allocation.",
+        }
+    }
+}
+
+impl fmt::Display for ClaimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())
+    }
+}
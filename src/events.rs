@@ -0,0 +1,139 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::Serialize;
+use near_sdk::{env, serde_json, AccountId};
+
+use crate::ClaimStatus;
+
+const EVENT_STANDARD: &str = "publicai-airdrop";
+const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// NEP-297 events emitted by the airdrop contract.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum AirdropEvent {
+    Claim(Vec<ClaimEvent>),
+    Unclaim(Vec<UnclaimEvent>),
+    Burn(Vec<BurnEvent>),
+    ClaimFailed(Vec<ClaimFailedEvent>),
+    TokenChange(Vec<TokenChangeEvent>),
+    BasketClaim(Vec<BasketClaimEvent>),
+    LegalHold(Vec<LegalHoldEvent>),
+    LegalHoldLifted(Vec<LegalHoldLiftedEvent>),
+    WithdrawalAnnounced(Vec<WithdrawalEvent>),
+    WithdrawalVetoed(Vec<WithdrawalEvent>),
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimEvent {
+    pub account_id: AccountId,
+    pub amount: U128,
+    /// Fee in yoctoNEAR paid by the claimer and forwarded to the treasury.
+    pub fee: U128,
+    /// Tokens deducted from `amount` and transferred to the treasury.
+    pub protocol_fee: U128,
+    /// Tokens lost to the decay schedule and returned to the treasury.
+    pub forfeited: U128,
+    /// Early-bird bonus paid on top of `amount`.
+    pub early_bird_bonus: U128,
+    pub referrer: Option<AccountId>,
+    /// Tokens paid to `referrer` on top of `amount`.
+    pub referral_bonus: U128,
+    /// Memo passed to the claimer's `ft_transfer`.
+    pub memo: Option<String>,
+}
+
+/// A claim reverted by the owner with `admin_unclaim`.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct UnclaimEvent {
+    pub account_id: AccountId,
+    /// Tokens the account had received according to its claim record.
+    pub amount: U128,
+}
+
+/// Leftover tokens burned by `finalize_campaign`.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BurnEvent {
+    pub amount: U128,
+    /// Burn address the tokens were sent to, `None` if they were burned by the token contract.
+    pub burn_address: Option<AccountId>,
+}
+
+/// A claim reverted after its promise chain failed.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimFailedEvent {
+    pub account_id: AccountId,
+    pub amount: U128,
+    /// Step of the chain that failed.
+    pub status: ClaimStatus,
+}
+
+/// The token contract replaced by `set_token_contract`.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenChangeEvent {
+    pub old_token_contract: AccountId,
+    pub new_token_contract: AccountId,
+}
+
+/// A leg of a basket leaf paid by `claim_basket`.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BasketClaimEvent {
+    pub account_id: AccountId,
+    pub token_contract: AccountId,
+    pub amount: U128,
+}
+
+/// An account placed on legal hold by `place_legal_hold`.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LegalHoldEvent {
+    pub account_id: AccountId,
+}
+
+/// A legal hold lifted by `lift_legal_hold`.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LegalHoldLiftedEvent {
+    pub account_id: AccountId,
+    /// Tokens of the claim deferred by the hold, now released by `release_held_claim`.
+    pub deferred_amount: U128,
+}
+
+/// A withdrawal announced by `announce_withdrawal` or vetoed by `veto_withdrawal`.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WithdrawalEvent {
+    pub amount: U128,
+    /// Time from which `withdraw_token` can execute the withdrawal, in nanoseconds.
+    pub executable_at: U64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a AirdropEvent,
+}
+
+impl AirdropEvent {
+    /// Logs the event as `EVENT_JSON:{...}`.
+    pub fn emit(&self) {
+        let log = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_STANDARD_VERSION,
+            event: self,
+        };
+        env::log_str(&format!(
+            "EVENT_JSON:{}",
+            serde_json::to_string(&log).unwrap_or_else(|_| env::abort())
+        ));
+    }
+}
@@ -0,0 +1,87 @@
+use near_sdk::{env, log, near, require, AccountId, NearToken};
+
+use crate::{AirdropContract, AirdropContractExt, NO_DEPOSIT};
+
+/// Maximum number of accounts added or removed by one call.
+const MAX_FEE_EXEMPTION_BATCH: usize = 100;
+
+#[near]
+impl AirdropContract {
+    /// Exempts `account_ids` from the claim fee and the protocol fee (only callable by the
+    /// owner), e.g. the team, validators or partners. Returns the number of accounts added.
+    #[payable]
+    pub fn add_fee_exemptions(&mut self, account_ids: Vec<AccountId>) -> u32 {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("add_fee_exemptions");
+        require!(
+            account_ids.len() <= MAX_FEE_EXEMPTION_BATCH,
+            "Too many accounts"
+        );
+        let mut added = 0;
+        for account_id in account_ids {
+            if self.fee_exempt.insert(account_id) {
+                added += 1;
+            }
+        }
+        log!("{} accounts exempted from fees", added);
+        added
+    }
+
+    /// Removes the fee exemption of `account_ids` (only callable by the owner). Returns the
+    /// number of accounts removed.
+    #[payable]
+    pub fn remove_fee_exemptions(&mut self, account_ids: Vec<AccountId>) -> u32 {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("remove_fee_exemptions");
+        require!(
+            account_ids.len() <= MAX_FEE_EXEMPTION_BATCH,
+            "Too many accounts"
+        );
+        let mut removed = 0;
+        for account_id in account_ids {
+            if self.fee_exempt.remove(&account_id) {
+                removed += 1;
+            }
+        }
+        log!("{} fee exemptions removed", removed);
+        removed
+    }
+
+    /// Returns whether `account_id` is exempt from fees.
+    pub fn is_fee_exempt(&self, account_id: AccountId) -> bool {
+        self.fee_exempt.contains(&account_id)
+    }
+
+    /// Returns up to `limit` fee-exempt accounts starting at index `from_index`.
+    pub fn get_fee_exemptions(&self, from_index: u32, limit: u32) -> Vec<AccountId> {
+        self.fee_exempt
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the number of fee-exempt accounts.
+    pub fn get_fee_exemption_count(&self) -> u32 {
+        self.fee_exempt.len()
+    }
+}
+
+impl AirdropContract {
+    /// Claim fee the caller must attach, waived if they are exempt.
+    pub(crate) fn caller_claim_fee(&self) -> NearToken {
+        if self.fee_exempt.contains(&env::predecessor_account_id()) {
+            return NO_DEPOSIT;
+        }
+        self.claim_fee
+    }
+
+    /// Protocol fee rate of the tokens claimed for `account_id`, 0 if it is exempt.
+    pub(crate) fn fee_bps_of(&self, account_id: &AccountId) -> u16 {
+        if self.fee_exempt.contains(account_id) {
+            return 0;
+        }
+        self.fee_bps
+    }
+}
@@ -0,0 +1,137 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, log, near, AccountId};
+
+use crate::{AirdropContract, AirdropContractExt, ClaimOutcome, ClaimStatus, PendingClaim};
+
+/// Claim owed after its token transfer failed, see `set_ious_enabled`.
+#[near(serializers = [borsh])]
+#[derive(Clone, Debug)]
+pub(crate) struct Iou {
+    /// The claim, still reserved and marked claimed.
+    pub claim: PendingClaim,
+    /// Time of the last failed transfer of the claim.
+    pub recorded_at: U64,
+}
+
+#[near]
+impl AirdropContract {
+    /// Records an IOU instead of reverting claims whose token transfer failed, typically because
+    /// the contract is underfunded (only callable by the owner). IOUs are paid by `settle_ious`.
+    #[payable]
+    pub fn set_ious_enabled(&mut self, enabled: bool) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_ious_enabled");
+        self.ious_enabled = enabled;
+        log!("IOUs enabled: {}", enabled);
+    }
+
+    /// Sets the time after its last failed transfer from which `croncat_tick` reverts an
+    /// unsettled IOU, or keeps IOUs until settled with `None` (only callable by the owner).
+    #[payable]
+    pub fn set_iou_expiry(&mut self, iou_expiry: Option<U64>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_iou_expiry");
+        self.iou_expiry = iou_expiry;
+        log!("IOU expiry set to {:?}", iou_expiry.map(|expiry| expiry.0));
+    }
+
+    /// Retries the transfers of up to `limit` IOUs, e.g. once the contract has been topped up.
+    /// Callable by anyone. IOUs whose transfer fails again are recorded again. Returns the
+    /// number of transfers started.
+    pub fn settle_ious(&mut self, limit: u32) -> u32 {
+        let mut settled = 0;
+        while settled < limit {
+            let Some(iou) = self.ious.pop() else {
+                break;
+            };
+            if self.assert_claim_gas(&iou.claim).is_err() {
+                self.ious.push(iou);
+                break;
+            }
+            log!(
+                "Settling the IOU of {} tokens to @{}",
+                iou.claim.net_amount(),
+                iou.claim.account_id
+            );
+            self.internal_transfer(iou.claim);
+            settled += 1;
+        }
+        settled
+    }
+
+    /// Returns whether failed transfers are recorded as IOUs.
+    pub fn get_ious_enabled(&self) -> bool {
+        self.ious_enabled
+    }
+
+    /// Returns the time after which unsettled IOUs expire, if any.
+    pub fn get_iou_expiry(&self) -> Option<U64> {
+        self.iou_expiry
+    }
+
+    /// Returns up to `limit` outstanding IOUs from index `from_index`, with the tokens owed to
+    /// each account.
+    pub fn get_ious(&self, from_index: u32, limit: u32) -> Vec<(AccountId, U128)> {
+        self.ious
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|iou| (iou.claim.account_id.clone(), U128(iou.claim.net_amount())))
+            .collect()
+    }
+
+    /// Returns the number of outstanding IOUs.
+    pub fn get_iou_count(&self) -> u32 {
+        self.ious.len()
+    }
+}
+
+impl AirdropContract {
+    /// Keeps `claim` claimed and its tokens reserved, owed until `settle_ious` transfers them.
+    pub(crate) fn internal_record_iou(&mut self, claim: PendingClaim) -> ClaimOutcome {
+        log!(
+            "Recorded an IOU of {} tokens to @{}",
+            claim.net_amount(),
+            claim.account_id
+        );
+        self.health.transfer_failures += 1;
+        let outcome = ClaimOutcome {
+            status: ClaimStatus::Iou,
+            amount: U128(claim.net_amount()),
+            receipt_index: None,
+            claimed_at: None,
+        };
+        self.ious.push(Iou {
+            claim,
+            recorded_at: U64(env::block_timestamp()),
+        });
+        outcome
+    }
+
+    /// Reverts up to `limit` IOUs past the IOU expiry among the first `scan` outstanding ones,
+    /// like failed claims: the accounts can claim again and their reservations are released.
+    /// Returns the number of IOUs expired.
+    pub(crate) fn internal_expire_ious(&mut self, limit: u32, scan: u32) -> u32 {
+        let Some(iou_expiry) = self.iou_expiry else {
+            return 0;
+        };
+        let mut expired = 0;
+        let mut index = 0;
+        while expired < limit && index < scan.min(self.ious.len()) {
+            let recorded_at = self.ious[index].recorded_at.0;
+            if env::block_timestamp() < recorded_at.saturating_add(iou_expiry.0) {
+                index += 1;
+                continue;
+            }
+            let iou = self.ious.swap_remove(index);
+            log!(
+                "The IOU of {} tokens to @{} expired",
+                iou.claim.net_amount(),
+                iou.claim.account_id
+            );
+            self.internal_rollback(&iou.claim, ClaimStatus::Expired);
+            expired += 1;
+        }
+        expired
+    }
+}
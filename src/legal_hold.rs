@@ -0,0 +1,113 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, log, near, require, AccountId, Promise, PromiseOrValue};
+
+use crate::{
+    AirdropContract, AirdropContractExt, AirdropEvent, ClaimOutcome, ClaimStatus, LegalHoldEvent,
+    LegalHoldLiftedEvent, PendingClaim,
+};
+
+#[near]
+impl AirdropContract {
+    /// Sets the compliance officer allowed to place and lift legal holds besides the owner
+    /// (only callable by the owner).
+    #[payable]
+    pub fn set_compliance_officer(&mut self, compliance_id: Option<AccountId>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_compliance_officer");
+        self.compliance_id = compliance_id;
+        log!("Compliance officer set to {:?}", self.compliance_id);
+    }
+
+    /// Places `account_id` on legal hold (only callable by the owner or the compliance
+    /// officer). Its claims are accepted and recorded, their transfer deferred until the hold
+    /// is lifted.
+    #[payable]
+    pub fn place_legal_hold(&mut self, account_id: AccountId) {
+        self.assert_compliance("Only the owner or the compliance officer can place holds");
+        self.record_admin_action("place_legal_hold");
+        require!(
+            self.legal_holds.insert(account_id.clone()),
+            "The account is already on hold"
+        );
+        AirdropEvent::LegalHold(vec![LegalHoldEvent { account_id }]).emit();
+    }
+
+    /// Lifts the legal hold of `account_id` (only callable by the owner or the compliance
+    /// officer). Its deferred claim, if any, is then transferred by `release_held_claim`.
+    #[payable]
+    pub fn lift_legal_hold(&mut self, account_id: AccountId) {
+        self.assert_compliance("Only the owner or the compliance officer can lift holds");
+        self.record_admin_action("lift_legal_hold");
+        require!(
+            self.legal_holds.remove(&account_id),
+            "The account is not on hold"
+        );
+        let deferred_amount = self
+            .held_claims
+            .get(&account_id)
+            .map_or(U128(0), |claim| U128(claim.net_amount()));
+        AirdropEvent::LegalHoldLifted(vec![LegalHoldLiftedEvent {
+            account_id,
+            deferred_amount,
+        }])
+        .emit();
+    }
+
+    /// Transfers the claim deferred by a legal hold once the hold is lifted. Callable by anyone.
+    pub fn release_held_claim(&mut self, account_id: AccountId) -> Promise {
+        require!(
+            !self.legal_holds.contains(&account_id),
+            "The account is on hold"
+        );
+        let claim = self
+            .held_claims
+            .remove(&account_id)
+            .unwrap_or_else(|| env::panic_str("No held claim"));
+        if self.assert_claim_gas(&claim).is_err() {
+            env::panic_str("Not enough gas to transfer the held claim");
+        }
+        self.internal_check_then_transfer(claim)
+    }
+
+    /// Returns the compliance officer, if any.
+    pub fn get_compliance_officer(&self) -> Option<AccountId> {
+        self.compliance_id.clone()
+    }
+
+    /// Returns whether `account_id` is on legal hold.
+    pub fn is_on_legal_hold(&self, account_id: AccountId) -> bool {
+        self.legal_holds.contains(&account_id)
+    }
+
+    /// Returns the claim of `account_id` deferred by a legal hold, if any.
+    pub fn get_held_claim(&self, account_id: AccountId) -> Option<PendingClaim> {
+        self.held_claims.get(&account_id).cloned()
+    }
+}
+
+impl AirdropContract {
+    fn assert_compliance(&self, message: &str) {
+        if self.compliance_id.as_ref() != Some(&env::predecessor_account_id()) {
+            self.assert_owner(message);
+        }
+    }
+
+    /// Transfers the reserved `claim`, or defers it while its account is on legal hold.
+    pub(crate) fn internal_transfer_unless_held(
+        &mut self,
+        claim: PendingClaim,
+    ) -> PromiseOrValue<ClaimOutcome> {
+        if !self.legal_holds.contains(&claim.account_id) {
+            return self.internal_check_then_transfer(claim).into();
+        }
+        log!("Transfer to @{} deferred by a legal hold", claim.account_id);
+        let amount = U128(claim.net_amount());
+        self.held_claims.insert(claim.account_id.clone(), claim);
+        PromiseOrValue::Value(ClaimOutcome {
+            status: ClaimStatus::Held,
+            amount,
+            receipt_index: None,
+            claimed_at: None,
+        })
+    }
+}
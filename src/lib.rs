@@ -1,12 +1,886 @@
-use near_sdk::json_types::U128;
+// The claim methods take their options as separate JSON arguments, also in their generated
+// cross-contract bindings
+#![allow(clippy::too_many_arguments)]
+
+use near_sdk::base64::Engine;
+use near_sdk::json_types::{Base58CryptoHash, Base64VecU8, U128, U64};
+use near_sdk::store::{IterableSet, LookupMap, LookupSet, Vector};
 use near_sdk::{
-    assert_one_yocto, env, log, near, require, serde_json, AccountId, Gas, NearToken,
-    PanicOnDefault, Promise,
+    assert_one_yocto, env, log, near, require, serde_json, AccountId, BorshStorageKey, CryptoHash,
+    Gas, NearToken, PanicOnDefault, Promise, PromiseOrValue, PromiseResult, PublicKey,
 };
 use serde_json::json;
-const CURRENT_STATE_VERSION: u32 = 1;
+
+use crate::iou::Iou;
+use crate::liquid_staking::GAS_FOR_LIQUID_STAKING;
+use crate::lockup::GAS_FOR_LOCKUP;
+use crate::nft_badge::GAS_FOR_BADGE;
+use crate::social::GAS_FOR_SOCIAL_POST;
+use crate::wnear::GAS_FOR_UNWRAP;
+
+mod admin_log;
+mod basket;
+mod claim_keys;
+mod council;
+mod croncat;
+mod email;
+mod errors;
+mod events;
+mod fee_exemptions;
+mod iou;
+mod legal_hold;
+mod liquid_staking;
+mod locks;
+mod lockup;
+pub mod merkle;
+mod nft_badge;
+mod partial_fill;
+mod pro_rata;
+mod raffle;
+mod relayer;
+mod root_commit;
+mod rounds;
+mod social;
+mod split;
+mod streaks;
+mod streaming;
+mod swap;
+mod terms;
+#[cfg(any(test, all(feature = "test-utils", not(target_arch = "wasm32"))))]
+pub mod test_utils;
+mod tiers;
+#[cfg(feature = "wasm-bindgen")]
+mod wasm;
+mod web4;
+mod weight_classes;
+mod withdrawal;
+mod wnear;
+
+pub use crate::admin_log::AdminAction;
+pub use crate::basket::{encode_basket_leaf, MAX_BASKET_LEGS};
+pub use crate::council::{Council, CouncilAction, CouncilProposal};
+pub use crate::email::email_attestation_message;
+pub use crate::errors::ClaimError;
+pub use crate::events::{
+    AirdropEvent, BasketClaimEvent, BurnEvent, ClaimEvent, ClaimFailedEvent, LegalHoldEvent,
+    LegalHoldLiftedEvent, TokenChangeEvent, UnclaimEvent, WithdrawalEvent,
+};
+pub use crate::locks::{LockBoost, LockedBalance};
+pub use crate::lockup::LockupFactory;
+pub use crate::merkle::{HashAlgorithm, IncrementalMerkleTree, LeafFormat, MerkleTree};
+pub use crate::nft_badge::NftBadge;
+pub use crate::pro_rata::ProRata;
+pub use crate::raffle::Raffle;
+pub use crate::relayer::{
+    claim_authorization_message, receiver_authorization_message, ClaimAuthorization,
+    ReceiverAuthorization, RelayerQuota,
+};
+pub use crate::root_commit::RootCommitment;
+pub use crate::rounds::{ClaimedStorage, Round};
+pub use crate::social::SocialPosts;
+pub use crate::split::MAX_SPLIT_RECEIVERS;
+pub use crate::streaks::{Streak, StreakBonus};
+pub use crate::swap::SwapRoute;
+pub use crate::terms::TermsAcceptance;
+pub use crate::web4::{Web4Request, Web4Response};
+pub use crate::weight_classes::WeightClass;
+pub use crate::withdrawal::PendingWithdrawal;
+
+const CURRENT_STATE_VERSION: u32 = 2;
+// Storage key of the code staged by `stage_code`
+const STAGED_CODE_KEY: &[u8] = b"STAGED_CODE";
 const NO_DEPOSIT: NearToken = NearToken::from_near(0);
 const OUTER_UPGRADE_GAS: Gas = Gas::from_tgas(20);
+// Gas needed to schedule one storage_deposit/ft_transfer chain in `distribute`
+const GAS_PER_DISTRIBUTION: Gas = Gas::from_tgas(55);
+// Gas kept aside for the `distribute` call itself
+const DISTRIBUTION_GAS_RESERVE: Gas = Gas::from_tgas(10);
+// Basis points in 100%
+const BPS_DENOMINATOR: u128 = 10_000;
+// Upper bound for the protocol fee (10%)
+const MAX_FEE_BPS: u16 = 1_000;
+// Upper bound for the referral bonus (20%)
+const MAX_REFERRAL_BPS: u16 = 2_000;
+// Deposit attached to each storage_deposit on the token contract
+const STORAGE_DEPOSIT_AMOUNT: NearToken = NearToken::from_yoctonear(1_250_000_000_000_000_000_000);
+const GAS_FOR_STORAGE_DEPOSIT: Gas = Gas::from_tgas(10);
+const GAS_FOR_STORAGE_VIEW: Gas = Gas::from_tgas(5);
+// Static gas of `on_storage_balance_of_then_transfer` on top of the transfer chain,
+// covering the storage_deposit calls it may schedule
+const GAS_FOR_REGISTRATION_CALLBACK: Gas = Gas::from_tgas(30);
+const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(20);
+// Gas of each eligibility check of a claim
+const GAS_FOR_ELIGIBILITY_CHECK: Gas = Gas::from_tgas(10);
+// Static gas of `on_eligibility_then_transfer` on top of the registration and transfer chain
+const GAS_FOR_ELIGIBILITY_CALLBACK: Gas = Gas::from_tgas(10);
+// Gas of the price query of a USD-denominated claim
+const GAS_FOR_PRICE_QUERY: Gas = Gas::from_tgas(10);
+// Static gas of `on_price_then_claim`, the rest of the prepaid gas is forwarded to it
+const GAS_FOR_PRICE_CALLBACK: Gas = Gas::from_tgas(20);
+// Gas of an ft_transfer_call, including the receiver's `ft_on_transfer` and the token's resolution
+const GAS_FOR_FT_TRANSFER_CALL: Gas = Gas::from_tgas(80);
+// Static gas of `on_storage_deposit_then_transfer` with a single ft_transfer
+const GAS_FOR_TRANSFER_CALLBACK: Gas = Gas::from_tgas(40);
+// Static gas of `on_unclaimed_burned`
+const GAS_FOR_BURN_CALLBACK: Gas = Gas::from_tgas(5);
+// NEAR kept on top of the storage staking when `finalize_campaign` sends the rest to the treasury
+const FINALIZATION_NEAR_RESERVE: NearToken = NearToken::from_near(1);
+// Worst-case storage of one claim with 64-character account ids: claimed set entry, claim
+// record and claim index entry, with 40 bytes of overhead per storage record
+const STORAGE_BYTES_PER_CLAIM: u64 = 415;
+// Delay between proposing and setting a new token contract, in nanoseconds (1 day)
+const TOKEN_CHANGE_TIMELOCK: u64 = 86_400_000_000_000;
+// Default minimum interval between Merkle root updates, in nanoseconds (1 hour)
+const DEFAULT_ROOT_UPDATE_COOLDOWN: u64 = 3_600_000_000_000;
+// Gas kept aside for the claim call itself on top of its promise chain
+const CLAIM_GAS_RESERVE: Gas = Gas::from_tgas(15);
+// Gas attached to each action of a generated DAO proposal
+const PROPOSAL_ACTION_GAS: Gas = Gas::from_tgas(150);
+// Maximum number of accounts per `import_claimed` call
+const MAX_IMPORT_BATCH: usize = 200;
+
+/// Maximum number of siblings in the Merkle proof of a single leaf.
+const MAX_PROOF_LENGTH: usize = 64;
+/// Length in bytes of the hashes of the Merkle tree.
+const HASH_LENGTH: usize = 32;
+
+/// Prefixes of the persistent collections.
+#[near(serializers = [borsh])]
+#[derive(BorshStorageKey)]
+enum StorageKey {
+    ReferralStats,
+    ClaimRecords,
+    ClaimIndex,
+    RaffleEntrants,
+    RaffleEntered,
+    RaffleWinners,
+    ProRataWeights,
+    Escrow,
+    BasketLegs,
+    ClaimedEmails,
+    ClaimKeys,
+    EscrowQueue,
+    Relayers,
+    CouncilProposals,
+    AdminLog,
+    Rounds,
+    RoundClaimed,
+    NonceClaimed,
+    HashedClaimed,
+    Ious,
+    Shortfalls,
+    TierStarts,
+    TermsAccepted,
+    TermsAcceptors,
+    LegalHolds,
+    HeldClaims,
+    FeeExempt,
+    Locks,
+    Streaks,
+    LeafRecords,
+}
+
+/// Unit of the leaf amounts of the current airdrop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Denomination {
+    Tokens,
+    UsdCents,
+    Weight,
+    WeightClass,
+}
+
+/// Rewards earned by a referrer through leaves naming them.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReferralStats {
+    /// Number of successful claims naming this referrer.
+    pub referrals: u32,
+    /// Total bonus tokens received.
+    pub rewards: U128,
+}
+
+/// Condition a claimer must meet, checked with a cross-contract view call before the transfer.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EligibilityGate {
+    /// Holds at least one token of a NEP-171 collection.
+    NftHolder { collection: AccountId },
+    /// Holds a valid proof-of-personhood SBT of an i-am-human registry.
+    Human { registry: AccountId },
+    /// Is verified by a compliance (KYC) registry.
+    Verified { registry: AccountId },
+    /// Holds at least `min_balance` of another NEP-141 token.
+    TokenHolder { token: AccountId, min_balance: U128 },
+    /// Has an active delegation with one of the allowlisted staking pools.
+    Staker { pools: Vec<AccountId> },
+}
+
+impl EligibilityGate {
+    /// Number of view calls of `check`.
+    fn checks(&self) -> u64 {
+        match self {
+            EligibilityGate::Staker { pools } => pools.len() as u64,
+            _ => 1,
+        }
+    }
+
+    /// View calls checking the gate for `account_id`, the gate passes if any of them does.
+    fn check(&self, account_id: &AccountId) -> Promise {
+        match self {
+            EligibilityGate::NftHolder { collection } => Promise::new(collection.clone())
+                .function_call(
+                    "nft_tokens_for_owner".to_string(),
+                    json!({ "account_id": account_id, "limit": 1 })
+                        .to_string()
+                        .into_bytes(),
+                    NO_DEPOSIT,
+                    GAS_FOR_ELIGIBILITY_CHECK,
+                ),
+            EligibilityGate::Human { registry } => Promise::new(registry.clone()).function_call(
+                "is_human".to_string(),
+                json!({ "account": account_id }).to_string().into_bytes(),
+                NO_DEPOSIT,
+                GAS_FOR_ELIGIBILITY_CHECK,
+            ),
+            EligibilityGate::Verified { registry } => Promise::new(registry.clone()).function_call(
+                "is_verified".to_string(),
+                json!({ "account_id": account_id }).to_string().into_bytes(),
+                NO_DEPOSIT,
+                GAS_FOR_ELIGIBILITY_CHECK,
+            ),
+            EligibilityGate::TokenHolder { token, .. } => Promise::new(token.clone())
+                .function_call(
+                    "ft_balance_of".to_string(),
+                    json!({ "account_id": account_id }).to_string().into_bytes(),
+                    NO_DEPOSIT,
+                    GAS_FOR_ELIGIBILITY_CHECK,
+                ),
+            EligibilityGate::Staker { pools } => pools
+                .iter()
+                .map(|pool| {
+                    Promise::new(pool.clone()).function_call(
+                        "get_account_staked_balance".to_string(),
+                        json!({ "account_id": account_id }).to_string().into_bytes(),
+                        NO_DEPOSIT,
+                        GAS_FOR_ELIGIBILITY_CHECK,
+                    )
+                })
+                .reduce(|checks, check| checks.and(check))
+                .unwrap_or_else(|| env::abort()),
+        }
+    }
+
+    /// Whether a result of `check` lets the account claim.
+    fn passed(&self, result: &[u8]) -> bool {
+        match self {
+            EligibilityGate::NftHolder { .. } => {
+                serde_json::from_slice::<Vec<serde_json::Value>>(result)
+                    .is_ok_and(|tokens| !tokens.is_empty())
+            }
+            // `is_human` lists the SBTs proving personhood per issuer, empty if there are none
+            EligibilityGate::Human { .. } => {
+                serde_json::from_slice::<Vec<serde_json::Value>>(result)
+                    .is_ok_and(|proofs| !proofs.is_empty())
+            }
+            EligibilityGate::Verified { .. } => {
+                serde_json::from_slice::<bool>(result).unwrap_or(false)
+            }
+            EligibilityGate::TokenHolder { min_balance, .. } => {
+                serde_json::from_slice::<U128>(result)
+                    .is_ok_and(|balance| balance.0 >= min_balance.0)
+            }
+            EligibilityGate::Staker { .. } => {
+                serde_json::from_slice::<U128>(result).is_ok_and(|staked| staked.0 > 0)
+            }
+        }
+    }
+}
+
+/// Price oracle settings of USD-denominated airdrops, whose leaf amounts are in USD cents.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UsdPricing {
+    /// Oracle implementing `get_price_data`, e.g. `priceoracle.near`.
+    pub oracle_id: AccountId,
+    /// Asset id of the airdropped token in the oracle.
+    pub asset_id: String,
+    /// Maximum age of the price, in nanoseconds.
+    pub max_price_age: U64,
+}
+
+/// Response of `get_price_data` of the price oracle.
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct PriceData {
+    /// Time of the prices, in nanoseconds since the Unix epoch.
+    pub timestamp: U64,
+    pub prices: Vec<AssetOptionalPrice>,
+}
+
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct AssetOptionalPrice {
+    pub asset_id: String,
+    pub price: Option<Price>,
+}
+
+/// USD price of the smallest token unit, `multiplier / 10^decimals`.
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct Price {
+    pub multiplier: U128,
+    pub decimals: u8,
+}
+
+impl Price {
+    /// Token amount worth `cents` USD cents, rounded down.
+    pub fn tokens_for_cents(&self, cents: u128) -> Option<u128> {
+        if self.multiplier.0 == 0 {
+            return None;
+        }
+        10u128
+            .checked_pow(self.decimals as u32)?
+            .checked_mul(cents)
+            .map(|scaled| scaled / self.multiplier.0 / 100)
+    }
+}
+
+/// How a claim resolved, see `ClaimOutcome`.
+#[near(serializers = [json])]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClaimStatus {
+    /// The tokens were transferred.
+    Success,
+    /// The claim is held in escrow until its dispute window ends.
+    Escrowed,
+    /// The claimed account could not be registered with the token contract.
+    StorageFailed,
+    /// The token transfer failed.
+    TransferFailed,
+    /// The account does not meet the eligibility gates.
+    Ineligible,
+    /// The USD amount could not be converted at an acceptable price.
+    PricingFailed,
+    /// The account had already claimed, returned instead of an error with idempotent claims.
+    AlreadyClaimed,
+    /// The Merkle root changed while the claim was in flight.
+    RootChanged,
+    /// The token transfer failed and the claim is owed until `settle_ious` pays it.
+    Iou,
+    /// The claim was recorded and its transfer deferred by a legal hold of the account.
+    Held,
+    /// The IOU of the claim was not settled before the IOU expiry and was reverted.
+    Expired,
+    /// Some legs of a split claim failed; their tokens are owed to the claimed account, see
+    /// `claim_shortfall`.
+    PartiallyPaid,
+}
+
+/// Value a claim resolves to. Failed claims are reverted: the account can claim again and
+/// its deposit is refunded.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClaimOutcome {
+    pub status: ClaimStatus,
+    /// Tokens transferred to the claimed account, or held in escrow or owed to it.
+    pub amount: U128,
+    /// Index of the claim record of a successful claim, see `get_claims`.
+    pub receipt_index: Option<U64>,
+    /// Time of the earlier claim of an `AlreadyClaimed` outcome, in nanoseconds.
+    pub claimed_at: Option<U64>,
+}
+
+impl ClaimOutcome {
+    /// Outcome of a claim that transferred nothing.
+    fn failed(status: ClaimStatus) -> Self {
+        Self {
+            status,
+            amount: U128(0),
+            receipt_index: None,
+            claimed_at: None,
+        }
+    }
+}
+
+/// A claim held in escrow until `releases_at`, during which the owner or guardian can cancel it.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug)]
+pub struct EscrowedClaim {
+    pub claim: PendingClaim,
+    /// Time from which the claim can be released, in nanoseconds since the Unix epoch.
+    pub releases_at: U64,
+}
+
+/// Description of the airdrop for wallets and claim UIs.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CampaignMetadata {
+    pub title: String,
+    pub description: Option<String>,
+    pub icon_url: Option<String>,
+    /// Terms claimers agree to.
+    pub terms_url: Option<String>,
+    pub token_symbol: String,
+}
+
+/// Period over which `ClaimRateLimit` counts claims.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RatePeriod {
+    Block,
+    Epoch,
+}
+
+/// Maximum number of claims started per block or epoch.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClaimRateLimit {
+    pub max_claims: u32,
+    pub period: RatePeriod,
+}
+
+/// Storage used by the contract, see `get_storage_report`.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageReport {
+    /// Bytes stored by the contract account.
+    pub storage_usage: U64,
+    /// Bytes of the claimed set, part of the contract state.
+    pub claimed_set_bytes: U64,
+    /// NEAR locked for `storage_usage`.
+    pub locked: NearToken,
+    /// Balance of the account not locked for storage.
+    pub available: NearToken,
+    /// Upper bound of the NEAR locked by one more claim.
+    pub cost_per_claim: NearToken,
+}
+
+/// Failure counters of the claim chain, see `get_health`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Health {
+    /// Claims reverted because the claimed account could not be registered.
+    pub storage_deposit_failures: u64,
+    /// Claims reverted because the token transfer failed.
+    pub transfer_failures: u64,
+    /// All reverted claims, including those failing the eligibility gates.
+    pub rolled_back_claims: u64,
+    /// Time of the last reverted claim, in nanoseconds since the Unix epoch.
+    pub last_failure_at: Option<U64>,
+}
+
+/// Token accounting of the contract, see `get_accounting`.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Accounting {
+    /// Tokens received through `ft_transfer_call` of the token contract.
+    pub funded: U128,
+    /// Tokens paid out by successful claims, bonuses and fees included.
+    pub distributed: U128,
+    /// Tokens reserved by claims in flight or in escrow.
+    pub reserved: U128,
+    /// Tokens of locked claims held by the contract until they are unlocked.
+    pub locked: U128,
+    /// Tokens withdrawn, swept or burned by the owner.
+    pub withdrawn: U128,
+}
+
+/// How `finalize_campaign` burns the leftover tokens instead of sending them to the treasury.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UnclaimedBurn {
+    /// Transfers them to a burn address, e.g. an account without access keys.
+    Address { account_id: AccountId },
+    /// Calls `burn` of the token contract.
+    TokenBurn,
+}
+
+/// Fields of the NEP-148 metadata of the token contract checked by `verify_token`.
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct FtMetadata {
+    pub spec: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+/// Token contract change proposed by the owner, see `set_token_contract`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenChange {
+    pub token_contract: AccountId,
+    /// Time from which the change can be applied, in nanoseconds since the Unix epoch.
+    pub executable_at: U64,
+}
+
+/// Totals declared by the owner with a Merkle root, bounding the claims against it.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RootTotals {
+    /// Sum of the leaf amounts of the tree, in tokens.
+    pub total_amount: U128,
+    /// Number of leaves of the tree.
+    pub leaf_count: u32,
+}
+
+/// Token contract settings stored by `verify_token`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenInfo {
+    pub symbol: String,
+    pub decimals: u8,
+    /// Minimum storage deposit of the token contract, attached to the registrations it pays for.
+    pub storage_deposit: NearToken,
+}
+
+/// NEP-145 storage balance bounds of the token contract.
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct StorageBalanceBounds {
+    pub min: U128,
+    pub max: Option<U128>,
+}
+
+/// Receipt of a successful claim. Contracts cannot read the hash of the transaction,
+/// `block_height` locates it in an explorer instead.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClaimRecord {
+    /// Position of the claim in the order of successful claims.
+    pub index: U64,
+    /// Tokens received by the claimed account.
+    pub amount: U128,
+    /// Block time of the successful transfer, in nanoseconds since the Unix epoch.
+    pub timestamp: U64,
+    /// Height of the block the transfer completed in.
+    pub block_height: U64,
+    /// Referrer of the claimed leaf, paid `referral_bonus`.
+    pub referrer: Option<AccountId>,
+    pub referral_bonus: U128,
+    /// Token ID of the NFT badge minted for the claim, once minted.
+    pub badge_token_id: Option<String>,
+    /// Round of the claimed leaf.
+    pub round: u32,
+    /// Nonce of the claimed leaf, if it has one.
+    pub nonce: Option<U64>,
+}
+
+/// Linear decay of the claimable share of each leaf, from 100% at `start_at`
+/// down to `floor_bps` at `end_at`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecaySchedule {
+    /// Start of the decay, in nanoseconds since the Unix epoch.
+    pub start_at: U64,
+    /// End of the decay, in nanoseconds since the Unix epoch.
+    pub end_at: U64,
+    /// Claimable share from `end_at` on, in basis points.
+    pub floor_bps: u16,
+}
+
+impl DecaySchedule {
+    /// Claimable share of a leaf at `timestamp`, in basis points.
+    pub fn claimable_bps(&self, timestamp: u64) -> u16 {
+        let (start_at, end_at) = (self.start_at.0, self.end_at.0);
+        if timestamp <= start_at {
+            return BPS_DENOMINATOR as u16;
+        }
+        if timestamp >= end_at {
+            return self.floor_bps;
+        }
+        let decay_bps = u128::from(BPS_DENOMINATOR as u16 - self.floor_bps);
+        let decayed = decay_bps * u128::from(timestamp - start_at) / u128::from(end_at - start_at);
+        BPS_DENOMINATOR as u16 - decayed as u16
+    }
+}
+
+/// Optional fields of a leaf, in addition to the account and amount.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LeafExtras {
+    /// Referrer paid a bonus on top of the claim.
+    pub referrer: Option<AccountId>,
+    /// Time after which the leaf can no longer be claimed, in nanoseconds since the Unix epoch.
+    pub expires_at: Option<U64>,
+    /// Distinguishes several leaves of the same account, each claimed once.
+    pub nonce: Option<U64>,
+    /// Cohort of the leaf, claimable from the start time of the tier.
+    pub tier: Option<u8>,
+    /// Streams the claimed tokens over this many seconds instead of paying a lump sum.
+    pub stream_duration: Option<U64>,
+    /// Pays the claimed tokens into the lockup of the account, locked for this many
+    /// nanoseconds.
+    pub lockup_duration: Option<U64>,
+}
+
+impl LeafExtras {
+    /// Encodes a leaf as `account:amount[:nonce][:referrer][#tier][~stream_duration]`, followed by
+    /// `[^lockup_duration][@expires_at]`.
+    pub fn encode_leaf(&self, account_id: &AccountId, amount: u128) -> String {
+        let mut leaf = format!("{}:{}", account_id, amount);
+        if let Some(nonce) = self.nonce {
+            leaf.push_str(&format!(":{}", nonce.0));
+        }
+        if let Some(referrer) = &self.referrer {
+            leaf.push_str(&format!(":{}", referrer));
+        }
+        if let Some(tier) = self.tier {
+            leaf.push_str(&format!("#{}", tier));
+        }
+        if let Some(stream_duration) = self.stream_duration {
+            leaf.push_str(&format!("~{}", stream_duration.0));
+        }
+        if let Some(lockup_duration) = self.lockup_duration {
+            leaf.push_str(&format!("^{}", lockup_duration.0));
+        }
+        if let Some(expires_at) = self.expires_at {
+            leaf.push_str(&format!("@{}", expires_at.0));
+        }
+        leaf
+    }
+}
+
+/// Domain every leaf is prefixed with before hashing, so that proofs of a tree shared with
+/// another deployment (e.g. on testnet) cannot be replayed against this contract.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LeafDomain {
+    /// Campaign of the tree, distinguishing several trees of the same contract.
+    pub campaign_id: Option<String>,
+}
+
+impl LeafDomain {
+    /// Prefixes `leaf` with the domain of `contract_id`, as `contract_id[/campaign_id]|leaf`.
+    pub fn prefix_leaf(&self, contract_id: &AccountId, leaf: &str) -> String {
+        match &self.campaign_id {
+            Some(campaign_id) => format!("{}/{}|{}", contract_id, campaign_id, leaf),
+            None => format!("{}|{}", contract_id, leaf),
+        }
+    }
+}
+
+/// Normalizes an account ID the way the off-chain tree generator does, by trimming
+/// whitespace and lowercasing it. Returns `None` if the result is not a valid account ID.
+pub fn normalize_account_id(account_id: &str) -> Option<AccountId> {
+    account_id.trim().to_lowercase().parse().ok()
+}
+
+/// Whether `account_id` is a NEAR implicit account, i.e. 64 lowercase hex characters
+/// derived from an ed25519 public key.
+pub fn is_implicit_account(account_id: &AccountId) -> bool {
+    let account_id = account_id.as_str();
+    account_id.len() == 64
+        && account_id
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+}
+
+/// A leaf as encoded and hashed by the contract, see `compute_leaf`.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComputedLeaf {
+    /// Leaf string, e.g. `account:amount`.
+    pub leaf: String,
+    /// Hex-encoded hash of the leaf under the current hash algorithm and leaf format.
+    pub hash: String,
+}
+
+/// Bonus on top of the leaf amount for claims made before `ends_at`,
+/// paid out of a reserved bonus pool.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EarlyBirdBonus {
+    /// Bonus in basis points of the claimed amount.
+    pub bonus_bps: u16,
+    /// End of the early-bird window, in nanoseconds since the Unix epoch.
+    pub ends_at: U64,
+}
+
+/// Contract receiving the claimed tokens through `ft_transfer_call` on behalf of the claimed account.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransferCall {
+    pub receiver_id: AccountId,
+    /// `msg` passed to `ft_on_transfer` of `receiver_id`.
+    pub msg: String,
+}
+
+/// Pays claims out with `ft_transfer_call` to the claimed account, for receivers that are contracts.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransferCallPayout {
+    /// `msg` used when the claimer does not pass one.
+    pub msg: String,
+    /// Messages claimers may pass instead of `msg`.
+    pub allowed_msgs: Vec<String>,
+}
+
+/// A claim in flight through the storage_deposit/ft_transfer callbacks.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug)]
+pub struct PendingClaim {
+    /// Account of the leaf, marked as claimed.
+    pub account_id: AccountId,
+    pub amount: U128,
+    /// Account that attached `deposit`, refunded if the claim fails.
+    pub claimer: AccountId,
+    pub deposit: U128,
+    /// Part of `deposit` paying for the registration of `account_id` with the token contract.
+    pub storage_deposit: U128,
+    /// Part of `deposit` forwarded to the treasury once the claim succeeds.
+    pub fee: U128,
+    /// Tokens deducted from `amount` and transferred to the treasury.
+    pub protocol_fee: U128,
+    /// Part of `amount` lost to the decay schedule and returned to the treasury.
+    pub forfeited: U128,
+    /// Early-bird bonus paid on top of `amount`, reserved from the bonus pool.
+    pub early_bird_bonus: U128,
+    /// Referrer encoded in the leaf, paid `referral_bonus` on top of `amount`.
+    pub referrer: Option<AccountId>,
+    pub referral_bonus: U128,
+    /// Memo passed to the claimer's `ft_transfer`.
+    pub memo: Option<String>,
+    /// Sends the claimed tokens with `ft_transfer_call` instead of `ft_transfer` to `account_id`.
+    pub transfer_call: Option<TransferCall>,
+    /// Account receiving the tokens instead of `account_id`, designated by it.
+    pub receiver_id: Option<AccountId>,
+    /// Part of `deposit` paying for the near.social post of the claim once it succeeds.
+    pub social_deposit: U128,
+    /// Part of `deposit` paying for the NFT badge minted once the claim succeeds.
+    pub badge_deposit: U128,
+    /// Part of `deposit` paying for the storage of the claim record, charged as measured once
+    /// the claim succeeds and the excess refunded.
+    pub record_deposit: U128,
+    /// Part of the leaf amount left unpaid by a partial fill, owed once the claim starts.
+    pub shortfall: U128,
+    /// Pays the shortfall of an earlier partial fill, see `claim_shortfall`. The account stays
+    /// claimed if it fails.
+    pub pays_shortfall: bool,
+    /// Nonce of the claimed leaf, claimed separately from the other leaves of the account.
+    pub nonce: Option<U64>,
+    /// Version of the Merkle root the claim was verified against, see `get_root_version`. The
+    /// claim is reverted if the root changes before the transfer.
+    pub root_version: Option<u32>,
+    /// Unwraps the claimed wNEAR and sends liquid staking tokens instead, see
+    /// `claim_and_liquid_stake`.
+    pub liquid_stake: bool,
+    /// Unwraps the claimed wNEAR and sends native NEAR instead, see `set_unwrap_wnear`.
+    pub unwrap_wnear: bool,
+    /// Streams the tokens over this many seconds through the streaming contract, see
+    /// `set_streaming_contract`.
+    pub stream_duration: Option<U64>,
+    /// Lockup duration of the leaf, whose tokens are paid into the lockup of the claimed
+    /// account, see `claim_into_lockup`.
+    pub lockup_duration: Option<U64>,
+    /// Part of `deposit` paying for the creation of the lockup once the claim succeeds.
+    pub lockup_deposit: U128,
+    /// Locks the tokens in the contract for this many months, see `claim_locked`.
+    pub lock_months: Option<u16>,
+    /// Bonus of the lock period on top of the claim, paid out of the lock bonus pool.
+    pub lock_bonus: U128,
+    /// Bonus of the account's streak of consecutive rounds, paid out of the streak bonus pool.
+    pub streak_bonus: U128,
+    /// Pushed by `distribute` rather than claimed: earns no bonus and is not gated.
+    pub pushed: bool,
+    /// Claim key paying the claim rather than a leaf, see `claim_with_key`: the account is not
+    /// marked as claimed and the tokens do not count against the root totals.
+    pub claim_key: Option<PublicKey>,
+    /// Pays a raffle prize rather than a leaf, see `claim_raffle_prize`: like key claims, the
+    /// account is not marked as claimed and the tokens do not count against the root totals.
+    pub raffle_prize: bool,
+    /// Receivers the leaf amount is distributed to instead of `account_id`, see `claim_split`.
+    pub split: Vec<(AccountId, U128)>,
+}
+
+impl PendingClaim {
+    /// A claim paid for by the current caller with the attached deposit.
+    pub fn new(account_id: AccountId, amount: U128, fee: NearToken) -> Self {
+        Self {
+            account_id,
+            amount,
+            claimer: env::predecessor_account_id(),
+            deposit: U128(env::attached_deposit().as_yoctonear()),
+            storage_deposit: U128(0),
+            fee: U128(fee.as_yoctonear()),
+            protocol_fee: U128(0),
+            forfeited: U128(0),
+            early_bird_bonus: U128(0),
+            referrer: None,
+            referral_bonus: U128(0),
+            memo: None,
+            transfer_call: None,
+            receiver_id: None,
+            social_deposit: U128(0),
+            badge_deposit: U128(0),
+            record_deposit: U128(0),
+            shortfall: U128(0),
+            pays_shortfall: false,
+            nonce: None,
+            root_version: None,
+            liquid_stake: false,
+            unwrap_wnear: false,
+            stream_duration: None,
+            lockup_duration: None,
+            lockup_deposit: U128(0),
+            lock_months: None,
+            lock_bonus: U128(0),
+            streak_bonus: U128(0),
+            pushed: false,
+            claim_key: None,
+            raffle_prize: false,
+            split: Vec::new(),
+        }
+    }
+
+    /// Whether the claim pays a leaf, rather than a claim key or a raffle prize.
+    pub fn pays_leaf(&self) -> bool {
+        self.claim_key.is_none() && !self.raffle_prize
+    }
+
+    /// Whether the claim marks a leaf as claimed.
+    pub fn claims_leaf(&self) -> bool {
+        !self.pays_shortfall && self.pays_leaf()
+    }
+
+    /// Account receiving the tokens.
+    pub fn receiver_id(&self) -> &AccountId {
+        self.receiver_id.as_ref().unwrap_or(&self.account_id)
+    }
+
+    /// Whether the claimed wNEAR is unwrapped before it is paid out. Transfer calls, streams,
+    /// lockups and locked claims receive wNEAR.
+    pub fn unwraps(&self) -> bool {
+        self.liquid_stake
+            || (self.unwrap_wnear
+                && self.transfer_call.is_none()
+                && self.stream_duration.is_none()
+                && self.lockup_duration.is_none()
+                && self.lock_months.is_none())
+    }
+
+    /// Tokens transferred to the claimed account.
+    pub fn net_amount(&self) -> u128 {
+        self.amount.0 - self.forfeited.0 - self.protocol_fee.0
+            + self.early_bird_bonus.0
+            + self.lock_bonus.0
+            + self.streak_bonus.0
+    }
+
+    /// Tokens transferred to the treasury.
+    pub fn treasury_amount(&self) -> u128 {
+        self.protocol_fee.0 + self.forfeited.0
+    }
+
+    /// Tokens leaving the contract for this claim, bonuses included.
+    pub fn total_amount(&self) -> Option<u128> {
+        self.amount
+            .0
+            .checked_add(self.early_bird_bonus.0)?
+            .checked_add(self.lock_bonus.0)?
+            .checked_add(self.streak_bonus.0)?
+            .checked_add(self.referral_bonus.0)
+    }
+}
 
 /// Contract to manage airdrops using a Merkle Tree
 #[derive(PanicOnDefault)]
@@ -20,194 +894,2303 @@ pub struct AirdropContract {
     merkle_root: String,
     // Mapping to keep track of claimed accounts
     claimed: std::collections::HashSet<AccountId>,
+    // Hash function the Merkle tree was built with
+    hash_algorithm: HashAlgorithm,
+    // Leaf/node hashing scheme of the current Merkle root
+    leaf_format: LeafFormat,
+    // Whether admin methods require 1 yoctoNEAR (disable for DAO owners using function-call proposals)
+    admin_deposit_required: bool,
+    // Hash of the code stored under `STAGED_CODE_KEY`, awaiting deployment
+    staged_code_hash: Option<CryptoHash>,
+    // Account receiving claim fees
+    treasury_id: AccountId,
+    // NEAR fee attached by claimers on top of the 1 yoctoNEAR
+    claim_fee: NearToken,
+    // Share of each claimed amount sent to the treasury, in basis points
+    fee_bps: u16,
+    // Bonus paid to the referrer of a leaf, in basis points of the claimed amount
+    referral_bps: u16,
+    // Referral rewards per referrer
+    referral_stats: LookupMap<AccountId, ReferralStats>,
+    // Optional decay of the claimable amounts over time
+    decay_schedule: Option<DecaySchedule>,
+    // Optional bonus for early claimers
+    early_bird_bonus: Option<EarlyBirdBonus>,
+    // Tokens still reserved for early-bird bonuses
+    bonus_pool: u128,
+    // Receipt of each successful claim, by claim index
+    claim_records: LookupMap<u64, ClaimRecord>,
+    // Claim index of the receipt of each claimed leaf, by round, account and nonce
+    leaf_records: LookupMap<(u32, AccountId, Option<u64>), u64>,
+    // Claimed accounts in the order their claims succeeded
+    claim_index: Vector<AccountId>,
+    // Storage deposit claimers attach to pay for their own token registration,
+    // `None` if the contract pays for it
+    claimer_storage_deposit: Option<NearToken>,
+    // Staking/farming contract claimed tokens can be staked into
+    staking_contract: Option<AccountId>,
+    // Pays claims out with ft_transfer_call instead of ft_transfer
+    transfer_call_payout: Option<TransferCallPayout>,
+    // Conditions claimers must meet on top of the Merkle proof
+    eligibility_gates: Vec<EligibilityGate>,
+    // Price oracle settings if leaf amounts are in USD cents
+    usd_pricing: Option<UsdPricing>,
+    // Current raffle, if any
+    raffle: Option<Raffle>,
+    // Entrants of the current raffle, the winners first once drawn
+    raffle_entrants: Vector<AccountId>,
+    raffle_entered: LookupSet<AccountId>,
+    // Prize of each raffle winner
+    raffle_winners: LookupMap<AccountId, U128>,
+    // Pro-rata distribution of a fixed pool, if any
+    pro_rata: Option<ProRata>,
+    // Weight registered by each account for the pro-rata distribution
+    pro_rata_weights: LookupMap<AccountId, U128>,
+    // Time claims are held in escrow before the transfer, in nanoseconds
+    escrow_period: Option<U64>,
+    // Account allowed to cancel escrowed claims besides the owner
+    guardian_id: Option<AccountId>,
+    // Claims held in escrow
+    escrow: LookupMap<AccountId, EscrowedClaim>,
+    // Description of the airdrop for wallets
+    campaign_metadata: Option<CampaignMetadata>,
+    // Time from which claims are rejected, in nanoseconds since the Unix epoch
+    claim_deadline: Option<U64>,
+    // Whether `finalize_campaign` has closed the airdrop for good
+    finalized: bool,
+    // Burns the leftover tokens at finalization instead of sending them to the treasury
+    unclaimed_burn: Option<UnclaimedBurn>,
+    // Optional cap on the number of claims per block or epoch
+    claim_rate_limit: Option<ClaimRateLimit>,
+    // Block or epoch height of the current rate limit window, and the claims started in it
+    rate_window: u64,
+    rate_window_claims: u32,
+    // Hard cap on the tokens distributed by claims, bonuses included
+    distribution_cap: Option<U128>,
+    // Tokens distributed or reserved by claims so far, bonuses included
+    distributed: u128,
+    // Part of `distributed` reserved by claims in flight or in escrow
+    reserved: u128,
+    // Part of `distributed` held by locked claims until they are unlocked
+    locked: u128,
+    // Tokens received through ft_transfer_call of the token contract
+    funded: u128,
+    // Tokens withdrawn, swept or burned by the owner
+    withdrawn: u128,
+    // Largest amount a single account can claim, whatever the Merkle tree allows
+    max_claim_amount: Option<U128>,
+    // Whether repeated claims resolve to an `AlreadyClaimed` outcome instead of failing
+    idempotent_claims: bool,
+    // Failure counters of the claim chain
+    health: Health,
+    // Metadata and storage deposit of the token contract, once verified
+    token_info: Option<TokenInfo>,
+    // Token contract change awaiting its timelock
+    token_change: Option<TokenChange>,
+    // Tokens of each account's basket leaf already paid or in flight
+    basket_legs: LookupMap<AccountId, Vec<AccountId>>,
+    // NEAR sent to implicit accounts claimed on their behalf to create them,
+    // `None` to leave them uncreated
+    implicit_account_funding: Option<NearToken>,
+    // ed25519 key signing the mappings of email hashes to accounts
+    attestor_key: Option<PublicKey>,
+    // Account each email hash was claimed by
+    claimed_emails: LookupMap<String, AccountId>,
+    // Tokens claimable with each unused claim key
+    claim_keys: LookupMap<PublicKey, U128>,
+    // Croncat manager contract allowed to call `croncat_tick`
+    croncat_manager: Option<AccountId>,
+    // Escrowed accounts in the order their claims were escrowed
+    escrow_queue: Vector<AccountId>,
+    // Position in `escrow_queue` of the next claim `croncat_tick` releases
+    escrow_cursor: u32,
+    // Accepts claims without the 1 yoctoNEAR and signed authorizations, for relayers
+    relayed_claims: bool,
+    // Daily claim quota and counter of each registered relayer
+    relayers: LookupMap<AccountId, RelayerQuota>,
+    // near.social posts of claims, opted into by claimers
+    social_posts: Option<SocialPosts>,
+    // NFT series minted to each successful claimer
+    nft_badge: Option<NftBadge>,
+    // Members confirming root updates, withdrawals and upgrades, if any
+    council: Option<Council>,
+    // Pending council proposals by ID
+    council_proposals: LookupMap<u32, CouncilProposal>,
+    // ID of the next council proposal
+    next_council_proposal: u32,
+    // Append-only audit log of administrative calls
+    admin_log: Vector<AdminAction>,
+    // Minimum interval between Merkle root updates, in nanoseconds
+    root_update_cooldown: U64,
+    // Earliest time of the next Merkle root update, 0 before the first one
+    next_root_update_at: U64,
+    // Outstanding commitments to future Merkle roots
+    root_commitments: Vec<RootCommitment>,
+    // Totals declared with the current root, none for the root set at initialization
+    root_totals: Option<RootTotals>,
+    // Tokens claimed or reserved against the current root, before fees and bonuses
+    root_claimed: u128,
+    // Number of Merkle root updates, binding in-flight claims to the root they were verified against
+    root_version: u32,
+    // Current round of a recurring airdrop, whose accounts claim once per round
+    round: u32,
+    // Root and stats of each round
+    rounds: LookupMap<u32, Round>,
+    // Accounts claimed in rounds after the first, which uses `claimed`
+    round_claimed: LookupSet<(u32, AccountId)>,
+    // Leaves with a nonce claimed in each round, by account and nonce
+    nonce_claimed: LookupSet<(u32, AccountId, u64)>,
+    // Domain leaves are prefixed with before hashing, none for plain leaves
+    leaf_domain: Option<LeafDomain>,
+    // Accounts imported by `import_claimed` from a legacy deployment
+    imported_claimed: u64,
+    // Merkle tree of the accounts of successful claims, in claim order
+    claimed_snapshot: IncrementalMerkleTree,
+    // Whether claimers pay for the storage of their claim record
+    claimer_pays_records: bool,
+    // Whether failed transfers are recorded as IOUs instead of reverting the claim
+    ious_enabled: bool,
+    // Claims owed after their transfer failed
+    ious: Vector<Iou>,
+    // Time after which `croncat_tick` reverts an unsettled IOU, `None` to keep IOUs
+    iou_expiry: Option<U64>,
+    // Whether claims larger than the remaining pool are partially filled
+    partial_fills: bool,
+    // Leaf amounts owed after a partial fill
+    shortfalls: LookupMap<AccountId, U128>,
+    // Sum of `shortfalls`
+    shortfall_total: u128,
+    // Time from which the leaves of each tier can be claimed
+    tier_starts: LookupMap<u8, U64>,
+    // Hash of the terms of service claimers must accept
+    terms_hash: Option<Base58CryptoHash>,
+    // Latest terms accepted by each account, and the accounts in order of first acceptance
+    terms_accepted: LookupMap<AccountId, TermsAcceptance>,
+    terms_acceptors: Vector<AccountId>,
+    // Account allowed to place and lift legal holds besides the owner
+    compliance_id: Option<AccountId>,
+    // Accounts on legal hold, and their claims deferred until the hold is lifted
+    legal_holds: LookupSet<AccountId>,
+    held_claims: LookupMap<AccountId, PendingClaim>,
+    // Delay between the announcement and the execution of withdrawals, in nanoseconds
+    withdrawal_delay: Option<U64>,
+    pending_withdrawal: Option<PendingWithdrawal>,
+    // Accounts exempt from the claim fee and the protocol fee
+    fee_exempt: IterableSet<AccountId>,
+    // Exchange and output token of `claim_and_swap`
+    swap_route: Option<SwapRoute>,
+    // Liquid staking contract of `claim_and_liquid_stake`
+    liquid_staking_contract: Option<AccountId>,
+    // Pays wNEAR claims out in native NEAR
+    unwrap_wnear: bool,
+    // Roketo streaming contract paying out leaves with a stream duration
+    streaming_contract: Option<AccountId>,
+    // Factory of the lockups receiving leaves with a lockup duration
+    lockup_factory: Option<LockupFactory>,
+    // Lock periods of `claim_locked` and the pool paying their bonuses
+    lock_boosts: Vec<LockBoost>,
+    lock_bonus_pool: u128,
+    // Tokens of locked claims held until `unlock`
+    locks: LookupMap<AccountId, LockedBalance>,
+    // Bonus of claims in consecutive rounds and the pool paying it
+    streak_bonus: Option<StreakBonus>,
+    streak_bonus_pool: u128,
+    // Consecutive rounds claimed by each account
+    streaks: LookupMap<AccountId, Streak>,
+    // Token amounts of the weight classes of `claim_weighted`
+    weight_classes: Vec<WeightClass>,
+    // How claimed accounts are recorded
+    claimed_storage: ClaimedStorage,
+    // sha256 of the accounts claimed in each round with `ClaimedStorage::Hashed`
+    hashed_claimed: LookupSet<(u32, CryptoHash)>,
+}
+
+/// State layout of version 1, before the Merkle hashing scheme became configurable.
+#[near(serializers = [borsh])]
+struct AirdropContractV1 {
+    owner_id: AccountId,
+    token_contract: AccountId,
+    merkle_root: String,
+    claimed: std::collections::HashSet<AccountId>,
 }
 
 #[near]
 impl AirdropContract {
     /// Initializes the contract with the given owner and NEP-141 token contract address.
+    /// - `merkle_root`: Root of the airdrop list, as hex (optionally `0x`-prefixed), base58 or base64.
+    /// - `hash_algorithm`: Hash function of the Merkle tree, defaults to keccak256.
+    /// - `leaf_format`: Leaf/node hashing scheme of the Merkle root, defaults to `V1`.
     #[init]
-    pub fn new(owner_id: AccountId, token_contract: AccountId, merkle_root: String) -> Self {
+    pub fn new(
+        owner_id: AccountId,
+        token_contract: AccountId,
+        merkle_root: String,
+        hash_algorithm: Option<HashAlgorithm>,
+        leaf_format: Option<LeafFormat>,
+    ) -> Self {
         assert!(!env::state_exists(), "The contract is already initialized.");
-        Self {
-            owner_id,
-            token_contract,
-            merkle_root,
-            claimed: std::collections::HashSet::new(),
-        }
+        let mut contract =
+            Self::internal_new(owner_id, token_contract, Self::normalize_root(&merkle_root));
+        contract.hash_algorithm = hash_algorithm.unwrap_or_default();
+        contract.leaf_format = leaf_format.unwrap_or_default();
+        contract
     }
 
-    /// Updates the Merkle root (only callable by the owner).
-    /// - `merkle_root`: The new Merkle root representing the airdrop list.
+    /// Updates the Merkle root (only callable by the owner, through the council if one is set),
+    /// at most once per root update cooldown.
+    /// - `merkle_root`: The new Merkle root representing the airdrop list, as hex (optionally
+    ///   `0x`-prefixed), base58 or base64. It is stored as lowercase hex.
+    /// - `total_amount`: Sum of the leaf amounts, which claims against the root cannot exceed.
+    /// - `leaf_count`: Number of leaves of the tree.
+    /// - `leaf_format`: Hashing scheme of the new root, keeps the current one if omitted.
     #[payable]
-    pub fn update_merkle_root(&mut self, merkle_root: String) {
-        assert_one_yocto();
-        assert_eq!(
-            self.owner_id,
-            env::predecessor_account_id(),
-            "Only the owner can update the Merkle root."
+    pub fn update_merkle_root(
+        &mut self,
+        merkle_root: String,
+        total_amount: U128,
+        leaf_count: u32,
+        leaf_format: Option<LeafFormat>,
+    ) {
+        self.assert_owner("Only the owner can update the Merkle root.");
+        self.record_admin_action("update_merkle_root");
+        self.assert_no_council();
+        self.internal_update_merkle_root(
+            &merkle_root,
+            RootTotals {
+                total_amount,
+                leaf_count,
+            },
+            leaf_format,
         );
-        self.merkle_root = merkle_root;
-        env::log_str(&format!("Merkle root updated to {}", self.merkle_root));
     }
 
+    /// Sets the minimum interval between Merkle root updates, in nanoseconds (only callable by
+    /// the owner). Once the root has been updated, a lower cooldown only applies after a full
+    /// period of the current one, so it cannot be used to swap roots in quick succession.
     #[payable]
-    pub fn update_owner(&mut self, new_owner: AccountId) -> bool {
-        assert_one_yocto();
-        require!(
-            env::predecessor_account_id() == self.owner_id,
-            "Owner's method"
+    pub fn set_root_update_cooldown(&mut self, cooldown: U64) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_root_update_cooldown");
+        if cooldown.0 < self.root_update_cooldown.0 && self.next_root_update_at.0 > 0 {
+            let delayed = env::block_timestamp().saturating_add(self.root_update_cooldown.0);
+            self.next_root_update_at = U64(self.next_root_update_at.0.max(delayed));
+        }
+        self.root_update_cooldown = cooldown;
+        log!(
+            "Root update cooldown set to {} ns, next update from {}",
+            cooldown.0,
+            self.next_root_update_at.0
         );
+    }
+
+    #[payable]
+    pub fn update_owner(&mut self, new_owner: AccountId) -> bool {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("update_owner");
         require!(!new_owner.as_str().is_empty(), "New owner cannot be empty");
         log!("Owner updated from {} to {}", self.owner_id, new_owner);
         self.owner_id = new_owner;
         true
     }
 
-    /// Allows users to claim their airdrop if they are eligible.
-    /// - `amount`: The amount of tokens the user claims.
-    /// - `merkle_proof`: The Merkle proof validating the user's claim.
+    /// Enables or disables the 1 yoctoNEAR requirement on admin methods (only callable by the owner).
+    /// DAO owners whose proposals cannot attach a deposit should disable it.
     #[payable]
-    pub fn claim_airdrop(&mut self, amount: U128, merkle_proof: Vec<String>) -> Promise {
-        assert_one_yocto();
-        let account_id = env::predecessor_account_id();
-
-        // Ensure the user has not already claimed
-        assert!(
-            !self.claimed.contains(&account_id),
-            "You have already claimed your airdrop."
-        );
-
-        // Verify the Merkle proof
-        let leaf = format!("{}:{}", account_id, amount.0);
-        assert!(
-            Self::verify_merkle_proof(leaf, &self.merkle_root, &merkle_proof),
-            "Merkle proof verification failed."
-        );
-
-        // Mark the account as claimed
-        self.claimed.insert(account_id.clone());
+    pub fn set_admin_deposit_required(&mut self, required: bool) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_admin_deposit_required");
+        self.admin_deposit_required = required;
+        log!("Admin deposit requirement set to {}", required);
+    }
 
-        // Always call storage_deposit first, regardless of registration status
+    /// Checks that the token contract implements NEP-141 metadata and NEP-145 storage, and
+    /// stores its decimals and minimum storage deposit (only callable by the owner). Meant to
+    /// run right after `new`, so a wrong token address surfaces before the first claim.
+    #[payable]
+    pub fn verify_token(&mut self) -> Promise {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("verify_token");
         Promise::new(self.token_contract.clone())
             .function_call(
-                "storage_deposit".to_string(),
-                near_sdk::serde_json::json!({
-                    "account_id": account_id,
-                    "registration_only": true
-                })
-                .to_string()
-                .into_bytes(),
-                NearToken::from_yoctonear(1_250_000_000_000_000_000_000),
-                Gas::from_gas(10_000_000_000_000),
+                "ft_metadata".to_string(),
+                vec![],
+                NO_DEPOSIT,
+                GAS_FOR_STORAGE_VIEW,
             )
-            // Chain to transfer tokens after storage_deposit
+            .and(Promise::new(self.token_contract.clone()).function_call(
+                "storage_balance_bounds".to_string(),
+                vec![],
+                NO_DEPOSIT,
+                GAS_FOR_STORAGE_VIEW,
+            ))
             .then(
                 Self::ext(env::current_account_id())
-                    .with_static_gas(Gas::from_gas(40_000_000_000_000))
-                    .on_storage_deposit_then_transfer(account_id, amount),
+                    .with_static_gas(GAS_FOR_STORAGE_VIEW)
+                    .on_token_verified(),
             )
     }
 
-    /// Callback: After storage_deposit, attempt to transfer the airdrop tokens.
+    /// Callback: Stores the token settings queried by `verify_token`.
     #[private]
-    pub fn on_storage_deposit_then_transfer(
+    pub fn on_token_verified(
         &mut self,
-        account_id: AccountId,
-        amount: U128,
-        #[callback_result] call_result: Result<Option<serde_json::Value>, near_sdk::PromiseError>,
-    ) -> Promise {
-        // If storage_deposit failed, revert and do not transfer tokens
-        if call_result.is_err() {
-            self.claimed.remove(&account_id);
-            return Promise::new(env::current_account_id());
-        }
+        #[callback_result] metadata: Result<FtMetadata, near_sdk::PromiseError>,
+        #[callback_result] bounds: Result<StorageBalanceBounds, near_sdk::PromiseError>,
+    ) -> TokenInfo {
+        let metadata = metadata.unwrap_or_else(|_| env::panic_str("Failed to get ft_metadata"));
+        require!(
+            metadata.spec.starts_with("ft-"),
+            "The token contract is not a NEP-141 token"
+        );
+        let bounds = bounds.unwrap_or_else(|_| env::panic_str("Failed to get storage bounds"));
+        let token_info = TokenInfo {
+            symbol: metadata.symbol,
+            decimals: metadata.decimals,
+            storage_deposit: NearToken::from_yoctonear(bounds.min.0),
+        };
+        log!("Token verified: {:?}", token_info);
+        self.token_info = Some(token_info.clone());
+        token_info
+    }
+
+    /// Proposes a new token contract, or withdraws the proposal with `None` (only callable by
+    /// the owner). It can be set with `set_token_contract` once the timelock has passed.
+    #[payable]
+    pub fn propose_token_contract(&mut self, token_contract: Option<AccountId>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("propose_token_contract");
+        self.token_change = token_contract.map(|token_contract| TokenChange {
+            token_contract,
+            executable_at: U64(env::block_timestamp().saturating_add(TOKEN_CHANGE_TIMELOCK)),
+        });
+        log!("Token contract change set to {:?}", self.token_change);
+    }
+
+    /// Replaces the token contract with the proposed one once its timelock has passed (only
+    /// callable by the owner). No claim may be in flight or in escrow, and the balance of the
+    /// current token must have been swept.
+    #[payable]
+    pub fn set_token_contract(&mut self) -> Promise {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_token_contract");
+        let token_change = self
+            .token_change
+            .as_ref()
+            .unwrap_or_else(|| env::panic_str("No token contract change proposed"));
+        require!(
+            env::block_timestamp() >= token_change.executable_at.0,
+            "The timelock has not passed"
+        );
+        require!(self.reserved == 0, "Claims are in flight or in escrow");
         Promise::new(self.token_contract.clone())
             .function_call(
-                "ft_transfer".to_string(),
-                serde_json::json!({
-                    "receiver_id": account_id.clone(),
-                    "amount": amount,
-                })
-                .to_string()
-                .into_bytes(),
-                NearToken::from_yoctonear(1),
-                Gas::from_gas(20_000_000_000_000),
+                "ft_balance_of".to_string(),
+                json!({ "account_id": env::current_account_id() })
+                    .to_string()
+                    .into_bytes(),
+                NO_DEPOSIT,
+                GAS_FOR_STORAGE_VIEW,
             )
             .then(
                 Self::ext(env::current_account_id())
-                    .with_static_gas(Gas::from_gas(5_000_000_000_000))
-                    .on_ft_transfer_then_claimed(account_id, amount),
+                    .with_static_gas(GAS_FOR_STORAGE_VIEW)
+                    .on_balance_then_set_token(),
             )
     }
 
-    /// Callback: After ft_transfer, only then mark the account as claimed.
+    /// Callback: Sets the proposed token contract if the balance of the current one is swept.
     #[private]
-    pub fn on_ft_transfer_then_claimed(
+    pub fn on_balance_then_set_token(
         &mut self,
-        account_id: AccountId,
-        amount: U128,
-        #[callback_result] call_result: Result<(), near_sdk::PromiseError>,
-    ) -> bool {
-        if call_result.is_err() {
-            self.claimed.remove(&account_id);
-            return false;
-        }
-        env::log_str(&format!(
-            "Account @{} claimed {} tokens from @{}.",
-            account_id, amount.0, self.token_contract
-        ));
-        true
+        #[callback_result] call_result: Result<U128, near_sdk::PromiseError>,
+    ) {
+        let balance = call_result.unwrap_or_else(|_| env::panic_str("Failed to get token balance"));
+        require!(balance.0 == 0, "Sweep the current token balance first");
+        require!(self.reserved == 0, "Claims are in flight or in escrow");
+        let token_change = self
+            .token_change
+            .take()
+            .unwrap_or_else(|| env::panic_str("No token contract change proposed"));
+        AirdropEvent::TokenChange(vec![TokenChangeEvent {
+            old_token_contract: self.token_contract.clone(),
+            new_token_contract: token_change.token_contract.clone(),
+        }])
+        .emit();
+        self.token_contract = token_change.token_contract;
+        self.token_info = None;
     }
 
-    /// Verifies a Merkle proof.
-    /// - `leaf`: The leaf node (e.g., "account_id + amount").
-    /// - `root`: The root of the Merkle tree.
-    /// - `proof`: The Merkle proof (an array of sibling hashes).
-    /// Returns `true` if the proof is valid, `false` otherwise.
-    pub fn verify_merkle_proof(leaf: String, root: &String, proof: &Vec<String>) -> bool {
-        let mut hash = env::keccak256(leaf.as_bytes());
-        for sibling in proof {
-            let sibling_hash = hex::decode(sibling).expect("Invalid hex in Merkle proof.");
-            if hash < sibling_hash {
-                hash = env::keccak256(&[hash.as_slice(), sibling_hash.as_slice()].concat());
-            } else {
-                hash = env::keccak256(&[sibling_hash.as_slice(), hash.as_slice()].concat());
-            }
+    /// Sets the account receiving fees, withdrawals, swept tokens and recovered NEAR (only
+    /// callable by the owner, through the council if one is set), so the owner key does not
+    /// need to hold funds.
+    #[payable]
+    pub fn set_treasury(&mut self, treasury_id: AccountId) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_treasury");
+        self.assert_no_council();
+        self.internal_set_treasury(treasury_id);
+    }
+
+    /// Prefixes every leaf with the account ID of this contract and an optional campaign ID
+    /// before hashing, or hashes plain leaves with `None` (only callable by the owner, while no
+    /// claim is in flight or in escrow). The current root must have been built the same way.
+    #[payable]
+    pub fn set_leaf_domain(&mut self, leaf_domain: Option<LeafDomain>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_leaf_domain");
+        require!(self.reserved == 0, "Claims are in flight or in escrow");
+        if let Some(campaign_id) = leaf_domain
+            .as_ref()
+            .and_then(|domain| domain.campaign_id.as_ref())
+        {
+            require!(
+                !campaign_id.is_empty() && !campaign_id.contains(['/', '|']),
+                "Invalid campaign ID"
+            );
         }
-        hex::encode(hash) == *root
+        self.leaf_domain = leaf_domain;
+        log!("Leaf domain set to {:?}", self.leaf_domain);
     }
 
-    /// Returns the current Merkle root.
-    pub fn get_merkle_root(&self) -> String {
-        self.merkle_root.clone()
+    /// Sets the description of the airdrop shown by wallets (only callable by the owner).
+    #[payable]
+    pub fn set_campaign_metadata(&mut self, campaign_metadata: Option<CampaignMetadata>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_campaign_metadata");
+        self.campaign_metadata = campaign_metadata;
+        log!("Campaign metadata updated");
     }
 
-    /// Checks if an account has already claimed their airdrop.
-    pub fn has_claimed(&self, account_id: AccountId) -> bool {
-        self.claimed.contains(&account_id)
+    /// Sets or clears the time from which claims are rejected (only callable by the owner,
+    /// through the council if one is set).
+    #[payable]
+    pub fn set_claim_deadline(&mut self, claim_deadline: Option<U64>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_claim_deadline");
+        self.assert_no_council();
+        self.internal_set_claim_deadline(claim_deadline);
     }
 
-    #[private]
-    #[init(ignore_state)]
-    #[allow(unused_variables)]
-    pub fn migrate(from_version: u32) -> Self {
-        env::state_read().unwrap_or_else(|| env::panic_str("ERR_FAILED_TO_READ_STATE"))
+    /// Burns the leftover tokens at finalization instead of sending them to the treasury,
+    /// or returns them again with `None` (only callable by the owner, through the council if
+    /// one is set).
+    #[payable]
+    pub fn set_unclaimed_burn(&mut self, unclaimed_burn: Option<UnclaimedBurn>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_unclaimed_burn");
+        self.assert_no_council();
+        self.internal_set_unclaimed_burn(unclaimed_burn);
     }
 
-    pub fn update_contract(&self) {
-        // Ensure only owner can call
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.owner_id,
-            "Only the owner can upgrade"
+    /// Sets the NEAR fee claimers attach on top of the 1 yoctoNEAR (only callable by the owner).
+    #[payable]
+    pub fn set_claim_fee(&mut self, claim_fee: NearToken) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_claim_fee");
+        self.claim_fee = claim_fee;
+        log!("Claim fee set to {} yoctoNEAR", claim_fee.as_yoctonear());
+    }
+
+    /// Sends `implicit_account_funding` from the contract balance to implicit accounts receiving
+    /// a claim they did not sign themselves, creating them before the storage_deposit, or
+    /// disables it with `None` (only callable by the owner).
+    #[payable]
+    pub fn set_implicit_account_funding(&mut self, implicit_account_funding: Option<NearToken>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_implicit_account_funding");
+        self.implicit_account_funding = implicit_account_funding;
+        log!(
+            "Implicit account funding set to {:?}",
+            self.implicit_account_funding
         );
+    }
 
-        // Receive the code directly from the input to avoid the
-        // GAS overhead of deserializing parameters
-        let code = env::input().unwrap_or_else(|| env::panic_str("ERR_NO_INPUT"));
-        // Deploy the contract code.
+    /// Makes claimers pay for the storage of their claim record (only callable by the owner).
+    /// Claimers then attach `get_record_deposit` on top of the claim deposit, and the excess of
+    /// the measured storage cost is refunded once the claim succeeds.
+    #[payable]
+    pub fn set_claimer_pays_records(&mut self, enabled: bool) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_claimer_pays_records");
+        self.claimer_pays_records = enabled;
+        log!("Claim record storage paid by claimers: {}", enabled);
+    }
+
+    /// Makes claimers pay for their own registration with the token contract (only callable
+    /// by the owner). Enabling queries `storage_balance_bounds` of the token contract, and
+    /// claimers must then attach its minimum on top of the 1 yoctoNEAR and the claim fee.
+    #[payable]
+    pub fn set_claimer_pays_storage(&mut self, enabled: bool) -> PromiseOrValue<()> {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_claimer_pays_storage");
+        if !enabled {
+            self.claimer_storage_deposit = None;
+            log!("Storage deposits paid by the contract");
+            return PromiseOrValue::Value(());
+        }
+        Promise::new(self.token_contract.clone())
+            .function_call(
+                "storage_balance_bounds".to_string(),
+                vec![],
+                NO_DEPOSIT,
+                GAS_FOR_STORAGE_VIEW,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_STORAGE_VIEW)
+                    .on_storage_balance_bounds(),
+            )
+            .into()
+    }
+
+    /// Callback: Stores the storage deposit claimers have to attach.
+    #[private]
+    pub fn on_storage_balance_bounds(
+        &mut self,
+        #[callback_result] call_result: Result<StorageBalanceBounds, near_sdk::PromiseError>,
+    ) {
+        let bounds = call_result.unwrap_or_else(|_| env::panic_str("Failed to get storage bounds"));
+        self.claimer_storage_deposit = Some(NearToken::from_yoctonear(bounds.min.0));
+        log!(
+            "Storage deposits paid by claimers: {} yoctoNEAR",
+            bounds.min.0
+        );
+    }
+
+    /// Makes claims pay out with `ft_transfer_call` instead of `ft_transfer` (only callable by
+    /// the owner), or back with `None`.
+    #[payable]
+    pub fn set_transfer_call_payout(&mut self, transfer_call_payout: Option<TransferCallPayout>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_transfer_call_payout");
+        log!(
+            "Payout with ft_transfer_call {}",
+            if transfer_call_payout.is_some() {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+        self.transfer_call_payout = transfer_call_payout;
+    }
+
+    /// Makes leaf amounts denominated in USD cents, converted at the oracle price when claimed
+    /// with `claim_airdrop_usd` (only callable by the owner), or back in tokens with `None`.
+    #[payable]
+    pub fn set_usd_pricing(&mut self, usd_pricing: Option<UsdPricing>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_usd_pricing");
+        self.usd_pricing = usd_pricing;
+        log!("USD pricing set to {:?}", self.usd_pricing);
+    }
+
+    /// Sets the conditions claimers must meet on top of the Merkle proof (only callable by the
+    /// owner). They are checked before the transfer; push distributions are not gated.
+    #[payable]
+    pub fn set_eligibility_gates(&mut self, eligibility_gates: Vec<EligibilityGate>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_eligibility_gates");
+        require!(
+            eligibility_gates.iter().all(|gate| gate.checks() > 0),
+            "A staker gate needs at least one staking pool"
+        );
+        self.eligibility_gates = eligibility_gates;
+        log!("{} eligibility gates set", self.eligibility_gates.len());
+    }
+
+    /// Caps the tokens distributed by claims, bonuses included, whatever the Merkle tree
+    /// allows (only callable by the owner), or removes the cap with `None`.
+    #[payable]
+    pub fn set_distribution_cap(&mut self, distribution_cap: Option<U128>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_distribution_cap");
+        self.distribution_cap = distribution_cap;
+        log!("Distribution cap set to {:?}", self.distribution_cap);
+    }
+
+    /// Sets or clears the largest amount a single account can claim (only callable by the owner).
+    /// Larger claims are rejected even if their proof verifies.
+    #[payable]
+    pub fn set_max_claim_amount(&mut self, max_claim_amount: Option<U128>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_max_claim_amount");
+        self.max_claim_amount = max_claim_amount;
+        log!("Maximum claim amount set to {:?}", self.max_claim_amount);
+    }
+
+    /// Makes repeated claims of `claim_airdrop`, `claim_and_stake` and `claim_airdrop_multi`
+    /// resolve to an `AlreadyClaimed` outcome with the deposit refunded, instead of failing
+    /// (only callable by the owner). Wallets retrying on timeouts then show no failure.
+    #[payable]
+    pub fn set_idempotent_claims(&mut self, enabled: bool) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_idempotent_claims");
+        self.idempotent_claims = enabled;
+        log!("Idempotent claims set to {}", enabled);
+    }
+
+    /// Caps the number of claims started per block or epoch, or removes the cap with `None`
+    /// (only callable by the owner). Push distributions are not limited.
+    #[payable]
+    pub fn set_claim_rate_limit(&mut self, claim_rate_limit: Option<ClaimRateLimit>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_claim_rate_limit");
+        if let Some(limit) = &claim_rate_limit {
+            require!(limit.max_claims > 0, "The limit must allow claims");
+        }
+        self.claim_rate_limit = claim_rate_limit;
+        log!("Claim rate limit set to {:?}", self.claim_rate_limit);
+    }
+
+    /// Holds claims in escrow for `escrow_period` nanoseconds before the transfer, or transfers
+    /// them right away with `None` (only callable by the owner).
+    #[payable]
+    pub fn set_escrow_period(&mut self, escrow_period: Option<U64>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_escrow_period");
+        self.escrow_period = escrow_period;
+        log!("Escrow period set to {:?}", self.escrow_period);
+    }
+
+    /// Sets the account allowed to cancel escrowed claims besides the owner
+    /// (only callable by the owner).
+    #[payable]
+    pub fn set_guardian(&mut self, guardian_id: Option<AccountId>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_guardian");
+        self.guardian_id = guardian_id;
+        log!("Guardian set to {:?}", self.guardian_id);
+    }
+
+    /// Cancels an escrowed claim during its dispute window (only callable by the owner or the
+    /// guardian). The account stays marked as claimed, its deposit is refunded and any shortfall
+    /// of a partial fill is dropped.
+    #[payable]
+    pub fn cancel_escrowed_claim(&mut self, account_id: AccountId) {
+        if self.guardian_id.as_ref() != Some(&env::predecessor_account_id()) {
+            self.assert_owner("Only the owner or the guardian can cancel claims");
+        }
+        self.record_admin_action("cancel_escrowed_claim");
+        let escrowed = self
+            .escrow
+            .remove(&account_id)
+            .unwrap_or_else(|| env::panic_str("No escrowed claim"));
+        require!(
+            env::block_timestamp() < escrowed.releases_at.0,
+            "The dispute window has ended"
+        );
+        self.internal_release_reservation(&escrowed.claim);
+        self.internal_delete_claim_key(&escrowed.claim);
+        self.internal_remove_shortfall(&account_id);
+        log!("Escrowed claim of @{} cancelled", account_id);
+        self.internal_refund(&escrowed.claim);
+    }
+
+    /// Removes the leaf of `account_id` with `nonce` from the claimed leaves of `round`, the
+    /// current round by default, so it can be claimed again (only callable by the owner), for
+    /// payouts that failed without the contract noticing. Its claim record and referral stats
+    /// are reverted and, in the current round, an escrowed or held claim of the leaf is
+    /// cancelled and any shortfall of the account dropped.
+    #[payable]
+    pub fn admin_unclaim(&mut self, account_id: AccountId, round: Option<u32>, nonce: Option<U64>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("admin_unclaim");
+        let round = round.unwrap_or(self.round);
+        require!(
+            self.internal_unmark_leaf_claimed_in_round(&account_id, nonce, round),
+            "The account has not claimed"
+        );
+        if round == self.round {
+            if self
+                .escrow
+                .get(&account_id)
+                .is_some_and(|escrowed| escrowed.claim.nonce == nonce)
+            {
+                let escrowed = self
+                    .escrow
+                    .remove(&account_id)
+                    .unwrap_or_else(|| env::abort());
+                self.internal_release_reservation(&escrowed.claim);
+                self.internal_refund(&escrowed.claim);
+            }
+            if self
+                .held_claims
+                .get(&account_id)
+                .is_some_and(|claim| claim.nonce == nonce)
+            {
+                let claim = self
+                    .held_claims
+                    .remove(&account_id)
+                    .unwrap_or_else(|| env::abort());
+                self.internal_release_reservation(&claim);
+                self.internal_refund(&claim);
+            }
+            self.internal_remove_shortfall(&account_id);
+        }
+        let record = self
+            .leaf_records
+            .remove(&(round, account_id.clone(), nonce.map(|nonce| nonce.0)))
+            .and_then(|index| self.claim_records.remove(&index));
+        if let Some(record) = &record {
+            let stats = record
+                .referrer
+                .as_ref()
+                .and_then(|referrer| self.referral_stats.get_mut(referrer));
+            if let Some(stats) = stats {
+                stats.referrals = stats.referrals.saturating_sub(1);
+                stats.rewards = U128(stats.rewards.0.saturating_sub(record.referral_bonus.0));
+            }
+        }
+        AirdropEvent::Unclaim(vec![UnclaimEvent {
+            account_id,
+            amount: record.map_or(U128(0), |record| record.amount),
+        }])
+        .emit();
+    }
+
+    /// Marks `account_ids` as claimed without transferring anything (only callable by the owner),
+    /// for accounts of the tree that received their tokens through another channel.
+    #[payable]
+    pub fn admin_mark_claimed(&mut self, account_ids: Vec<AccountId>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("admin_mark_claimed");
+        let marked = account_ids
+            .into_iter()
+            .filter(|account_id| self.internal_mark_claimed(account_id.clone()))
+            .count();
+        log!("Marked {} accounts as claimed", marked);
+    }
+
+    /// Seeds the claimed set of `round`, the current round by default, with the accounts that
+    /// claimed from a legacy deployment, at most `MAX_IMPORT_BATCH` per call (only callable by
+    /// the owner, before the first claim). Returns the number of accounts not already imported.
+    #[payable]
+    pub fn import_claimed(&mut self, account_ids: Vec<AccountId>, round: Option<u32>) -> u32 {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("import_claimed");
+        require!(
+            !self.claims_started(),
+            "Claims have started, the claimed set can no longer be imported"
+        );
+        if account_ids.len() > MAX_IMPORT_BATCH {
+            env::panic_str(&format!("At most {} accounts per import", MAX_IMPORT_BATCH));
+        }
+        let round = round.unwrap_or(self.round);
+        let imported = account_ids
+            .into_iter()
+            .filter(|account_id| self.internal_mark_claimed_in_round(account_id.clone(), round))
+            .count() as u32;
+        self.imported_claimed += imported as u64;
+        log!(
+            "Imported {} claimed accounts, {} in total",
+            imported,
+            self.imported_claimed
+        );
+        imported
+    }
+
+    /// Transfers an escrowed claim once its dispute window has ended, or defers it while the
+    /// account is on legal hold. Callable by anyone.
+    pub fn release_escrowed_claim(
+        &mut self,
+        account_id: AccountId,
+    ) -> PromiseOrValue<ClaimOutcome> {
+        let escrowed = self
+            .escrow
+            .remove(&account_id)
+            .unwrap_or_else(|| env::panic_str("No escrowed claim"));
+        require!(
+            env::block_timestamp() >= escrowed.releases_at.0,
+            "The dispute window has not ended"
+        );
+        self.internal_transfer_unless_held(escrowed.claim)
+    }
+
+    /// Sets the staking/farming contract used by `claim_and_stake` (only callable by the owner).
+    #[payable]
+    pub fn set_staking_contract(&mut self, staking_contract: Option<AccountId>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_staking_contract");
+        self.staking_contract = staking_contract;
+        log!("Staking contract set to {:?}", self.staking_contract);
+    }
+
+    /// Sets the share of claimed tokens sent to the treasury, in basis points,
+    /// capped at `MAX_FEE_BPS` (only callable by the owner).
+    #[payable]
+    pub fn set_fee_bps(&mut self, fee_bps: u16) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_fee_bps");
+        require!(fee_bps <= MAX_FEE_BPS, "Fee exceeds the maximum");
+        self.fee_bps = fee_bps;
+        log!("Protocol fee set to {} bps", fee_bps);
+    }
+
+    /// Sets the bonus paid to referrers named in leaves, in basis points of the claimed amount,
+    /// capped at `MAX_REFERRAL_BPS` (only callable by the owner).
+    #[payable]
+    pub fn set_referral_bps(&mut self, referral_bps: u16) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_referral_bps");
+        require!(
+            referral_bps <= MAX_REFERRAL_BPS,
+            "Referral bonus exceeds the maximum"
+        );
+        self.referral_bps = referral_bps;
+        log!("Referral bonus set to {} bps", referral_bps);
+    }
+
+    /// Sets or clears the decay of claimable amounts over time (only callable by the owner).
+    /// The decayed part of each claim is sent to the treasury.
+    #[payable]
+    pub fn set_decay_schedule(&mut self, decay_schedule: Option<DecaySchedule>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_decay_schedule");
+        if let Some(schedule) = &decay_schedule {
+            require!(
+                schedule.start_at.0 < schedule.end_at.0,
+                "Decay must end after it starts"
+            );
+            require!(
+                u128::from(schedule.floor_bps) <= BPS_DENOMINATOR,
+                "Floor exceeds 100%"
+            );
+        }
+        self.decay_schedule = decay_schedule;
+        log!("Decay schedule updated");
+    }
+
+    /// Sets or clears the early-bird bonus and the pool reserved for it (only callable by the owner).
+    /// Bonuses stop once the pool is exhausted.
+    #[payable]
+    pub fn set_early_bird_bonus(
+        &mut self,
+        early_bird_bonus: Option<EarlyBirdBonus>,
+        bonus_pool: U128,
+    ) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_early_bird_bonus");
+        self.early_bird_bonus = early_bird_bonus;
+        self.bonus_pool = bonus_pool.0;
+        log!("Early-bird bonus updated, bonus pool {}", bonus_pool.0);
+    }
+
+    /// Allows users to claim their airdrop if they are eligible.
+    /// - `amount`: The amount of tokens the user claims.
+    /// - `merkle_proof`: The Merkle proof validating the user's claim.
+    /// - `extras`: The optional leaf fields (referrer, expiry) encoded in the user's leaf.
+    /// - `memo`: Memo of the token transfer, e.g. for crediting custodial accounts.
+    /// - `msg`: `msg` of the `ft_transfer_call` when claims pay out with it, must be allowlisted.
+    /// - `authorization`: With relayed claims, the signature of an implicit account claiming its
+    ///   leaf through the caller, instead of the caller's own leaf.
+    /// - `post_to_social`: Announces the claim on near.social once it succeeds, for the extra
+    ///   deposit of `get_social_posts`.
+    #[payable]
+    #[handle_result]
+    pub fn claim_airdrop(
+        &mut self,
+        amount: U128,
+        merkle_proof: Vec<String>,
+        extras: Option<LeafExtras>,
+        memo: Option<String>,
+        msg: Option<String>,
+        authorization: Option<ClaimAuthorization>,
+        post_to_social: Option<bool>,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        let social_deposit = self.internal_social_deposit(post_to_social)?;
+        self.assert_claim_deposit_with(social_deposit)?;
+        self.assert_denomination(Denomination::Tokens)?;
+        let extras = extras.unwrap_or_default();
+        let account_id = self.internal_claim_beneficiary(authorization, amount, extras.nonce)?;
+        if let Some(outcome) = self.internal_repeated_claim(&account_id, extras.nonce) {
+            return Ok(PromiseOrValue::Value(outcome));
+        }
+        self.internal_count_relayed_claim()?;
+        let transfer_call = match (&self.transfer_call_payout, msg) {
+            (None, None) => None,
+            (Some(payout), None) => Some(payout.msg.clone()),
+            (Some(payout), Some(msg)) if payout.allowed_msgs.contains(&msg) => Some(msg),
+            (_, Some(_)) => return Err(ClaimError::MsgNotAllowed),
+        }
+        .map(|msg| TransferCall {
+            receiver_id: account_id.clone(),
+            msg,
+        });
+        self.internal_verify_leaf(&account_id, amount, &merkle_proof, &extras)?;
+        self.internal_start_claim(PendingClaim {
+            referrer: extras.referrer,
+            memo,
+            transfer_call,
+            social_deposit: U128(social_deposit.as_yoctonear()),
+            nonce: extras.nonce,
+            stream_duration: extras.stream_duration,
+            lockup_duration: extras.lockup_duration,
+            ..self.internal_new_claim(account_id, amount)
+        })
+    }
+
+    /// Claims the leaf of `account_id` and sends the tokens to `receiver_id`, e.g. a
+    /// hardware-wallet account. Called by `account_id` itself, or by anyone with an
+    /// `authorization` signed by the key of the implicit account `account_id`.
+    #[payable]
+    #[handle_result]
+    pub fn claim_to(
+        &mut self,
+        account_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        merkle_proof: Vec<String>,
+        extras: Option<LeafExtras>,
+        authorization: Option<ReceiverAuthorization>,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_claim_deposit()?;
+        self.assert_denomination(Denomination::Tokens)?;
+        let extras = extras.unwrap_or_default();
+        if env::predecessor_account_id() != account_id {
+            let authorization = authorization.ok_or(ClaimError::InvalidAuthorization)?;
+            let message = receiver_authorization_message(
+                &env::current_account_id(),
+                &account_id,
+                &receiver_id,
+                amount,
+                self.round,
+                extras.nonce,
+            );
+            if !relayer::verify_account_signature(
+                &account_id,
+                &authorization.public_key,
+                &authorization.signature.0,
+                &message,
+            ) {
+                return Err(ClaimError::InvalidAuthorization);
+            }
+        }
+        if let Some(outcome) = self.internal_repeated_claim(&account_id, extras.nonce) {
+            return Ok(PromiseOrValue::Value(outcome));
+        }
+        self.internal_count_relayed_claim()?;
+        self.internal_verify_leaf(&account_id, amount, &merkle_proof, &extras)?;
+
+        log!("Claim of @{} sent to @{}", account_id, receiver_id);
+        self.internal_start_claim(PendingClaim {
+            referrer: extras.referrer,
+            receiver_id: Some(receiver_id),
+            nonce: extras.nonce,
+            stream_duration: extras.stream_duration,
+            lockup_duration: extras.lockup_duration,
+            ..self.internal_new_claim(account_id, amount)
+        })
+    }
+
+    /// Claims like `claim_airdrop`, but stakes the tokens into pool `pool_id` of the configured
+    /// staking contract with the caller as beneficiary, using `ft_transfer_call`. Tokens the
+    /// staking contract does not accept are sent to the caller.
+    #[payable]
+    #[handle_result]
+    pub fn claim_and_stake(
+        &mut self,
+        amount: U128,
+        merkle_proof: Vec<String>,
+        extras: Option<LeafExtras>,
+        pool_id: String,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_claim_deposit()?;
+        self.assert_denomination(Denomination::Tokens)?;
+        let staking_contract = self
+            .staking_contract
+            .clone()
+            .ok_or(ClaimError::StakingUnavailable)?;
+        let account_id = env::predecessor_account_id();
+        let extras = extras.unwrap_or_default();
+        if let Some(outcome) = self.internal_repeated_claim(&account_id, extras.nonce) {
+            return Ok(PromiseOrValue::Value(outcome));
+        }
+        self.internal_count_relayed_claim()?;
+        self.internal_verify_leaf(&account_id, amount, &merkle_proof, &extras)?;
+
+        let msg = json!({
+            "pool_id": pool_id,
+            "beneficiary_id": account_id,
+        })
+        .to_string();
+        self.internal_start_claim(PendingClaim {
+            referrer: extras.referrer,
+            transfer_call: Some(TransferCall {
+                receiver_id: staking_contract,
+                msg,
+            }),
+            nonce: extras.nonce,
+            stream_duration: extras.stream_duration,
+            lockup_duration: extras.lockup_duration,
+            ..self.internal_new_claim(account_id, amount)
+        })
+    }
+
+    /// Claims a USD-denominated leaf: `amount_cents` is converted to tokens at the oracle price.
+    /// - `min_amount`: Fails the claim if the price yields fewer tokens, guarding against slippage.
+    #[payable]
+    #[handle_result]
+    pub fn claim_airdrop_usd(
+        &mut self,
+        amount_cents: U128,
+        merkle_proof: Vec<String>,
+        extras: Option<LeafExtras>,
+        min_amount: Option<U128>,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_claim_deposit()?;
+        self.internal_count_relayed_claim()?;
+        self.assert_denomination(Denomination::UsdCents)?;
+        let usd_pricing = self
+            .usd_pricing
+            .clone()
+            .ok_or(ClaimError::WrongDenomination)?;
+        let account_id = env::predecessor_account_id();
+        let extras = extras.unwrap_or_default();
+        self.internal_verify_leaf(&account_id, amount_cents, &merkle_proof, &extras)?;
+
+        // Blocks other claims of the leaf while the price is queried
+        self.internal_mark_leaf_claimed(account_id.clone(), extras.nonce);
+        let claim = PendingClaim {
+            referrer: extras.referrer,
+            nonce: extras.nonce,
+            stream_duration: extras.stream_duration,
+            lockup_duration: extras.lockup_duration,
+            root_version: Some(self.root_version),
+            ..self.internal_new_claim(account_id, amount_cents)
+        };
+        Ok(Promise::new(usd_pricing.oracle_id)
+            .function_call(
+                "get_price_data".to_string(),
+                json!({ "asset_ids": [usd_pricing.asset_id] })
+                    .to_string()
+                    .into_bytes(),
+                NO_DEPOSIT,
+                GAS_FOR_PRICE_QUERY,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_PRICE_CALLBACK)
+                    .on_price_then_claim(claim, min_amount),
+            )
+            .into())
+    }
+
+    /// Allows users with several allocations in the tree to claim all of them at once.
+    /// - `entries`: The amounts of each allocation, in the leaf order expected by the multiproof.
+    /// - `multiproof`: The sibling hashes needed to rebuild the root from all leaves.
+    /// - `flags`: For each hashing step, whether the second operand is taken from the
+    ///   leaves/computed hashes (`true`) or from the multiproof (`false`).
+    #[payable]
+    #[handle_result]
+    pub fn claim_airdrop_multi(
+        &mut self,
+        entries: Vec<U128>,
+        multiproof: Vec<String>,
+        flags: Vec<bool>,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_claim_deposit()?;
+        self.assert_denomination(Denomination::Tokens)?;
+        let account_id = env::predecessor_account_id();
+        if let Some(outcome) = self.internal_repeated_claim(&account_id, None) {
+            return Ok(PromiseOrValue::Value(outcome));
+        }
+        self.internal_count_relayed_claim()?;
+
+        // Ensure the user has not already claimed
+        if self.is_claimed(&account_id) {
+            return Err(ClaimError::AlreadyClaimed);
+        }
+        if entries.is_empty() {
+            return Err(ClaimError::NoEntries);
+        }
+        if entries.iter().any(|amount| amount.0 == 0) {
+            return Err(ClaimError::ZeroAmount);
+        }
+
+        // Verify all leaves against the root with a single multiproof
+        let leaves = entries
+            .iter()
+            .map(|amount| {
+                self.domain_leaf(LeafExtras::default().encode_leaf(&account_id, amount.0))
+            })
+            .collect();
+        if !Self::verify_merkle_multiproof_with(
+            self.hash_algorithm,
+            self.leaf_format,
+            leaves,
+            &self.merkle_root,
+            &multiproof,
+            &flags,
+        )? {
+            return Err(ClaimError::InvalidProof);
+        }
+
+        let total = entries
+            .iter()
+            .try_fold(0u128, |total, amount| total.checked_add(amount.0))
+            .ok_or(ClaimError::AmountOverflow)?;
+        self.internal_start_claim(self.internal_new_claim(account_id, U128(total)))
+    }
+
+    /// Only owner can call, through the council if one is set. Pushes tokens directly to
+    /// `recipients` without requiring them to claim.
+    /// Recipients are processed in order until the prepaid gas runs out; the remaining ones
+    /// should be sent in a follow-up call. Accounts that already claimed, or whose claim is in
+    /// escrow or held, are skipped. Pushed tokens go through the escrow and legal holds like
+    /// claims, but earn no bonus. With a withdrawal delay, the total of `recipients` must have
+    /// been announced, see `announce_withdrawal`, and the tokens pushed are deducted from the
+    /// announcement. Returns the number of entries processed.
+    #[payable]
+    pub fn distribute(&mut self, recipients: Vec<(AccountId, U128)>) -> u32 {
+        self.assert_owner("Only the owner can distribute tokens");
+        self.record_admin_action("distribute");
+        self.assert_no_council();
+        self.internal_distribute(recipients)
+    }
+
+    /// Callback: After storage_deposit, attempt to transfer the airdrop tokens.
+    #[private]
+    pub fn on_storage_deposit_then_transfer(
+        &mut self,
+        claim: PendingClaim,
+        #[callback_result] call_result: Result<Option<serde_json::Value>, near_sdk::PromiseError>,
+    ) -> PromiseOrValue<ClaimOutcome> {
+        let mut claim = claim;
+        // If storage_deposit failed, revert, refund the claimer and do not transfer tokens
+        if call_result.is_err() {
+            self.internal_rollback(&claim, ClaimStatus::StorageFailed);
+            return PromiseOrValue::Value(ClaimOutcome::failed(ClaimStatus::StorageFailed));
+        }
+        // The storage deposit is spent and no longer refunded
+        claim.deposit = U128(claim.deposit.0 - claim.storage_deposit.0);
+        claim.storage_deposit = U128(0);
+        if self.internal_check_root_version(&claim) {
+            return PromiseOrValue::Value(ClaimOutcome::failed(ClaimStatus::RootChanged));
+        }
+        self.internal_transfer(claim)
+    }
+
+    /// Callback: In claimer-paid storage mode, registers the claimed account with the storage
+    /// deposit attached by the claimer, or refunds it if the account is already registered.
+    #[private]
+    pub fn on_storage_balance_of_then_transfer(
+        &mut self,
+        claim: PendingClaim,
+        #[callback_result] call_result: Result<Option<serde_json::Value>, near_sdk::PromiseError>,
+    ) -> PromiseOrValue<ClaimOutcome> {
+        let mut claim = claim;
+        let registered = match call_result {
+            Ok(balance) => balance.is_some(),
+            Err(_) => {
+                self.internal_rollback(&claim, ClaimStatus::StorageFailed);
+                return PromiseOrValue::Value(ClaimOutcome::failed(ClaimStatus::StorageFailed));
+            }
+        };
+        if self.internal_check_root_version(&claim) {
+            return PromiseOrValue::Value(ClaimOutcome::failed(ClaimStatus::RootChanged));
+        }
+        let mut storage_deposits = Vec::new();
+        if registered {
+            Promise::new(claim.claimer.clone())
+                .transfer(NearToken::from_yoctonear(claim.storage_deposit.0));
+            claim.deposit = U128(claim.deposit.0 - claim.storage_deposit.0);
+            claim.storage_deposit = U128(0);
+        } else {
+            storage_deposits.push((
+                claim.receiver_id().clone(),
+                NearToken::from_yoctonear(claim.storage_deposit.0),
+            ));
+        }
+        if let Some(referrer) = &claim.referrer {
+            storage_deposits.push((referrer.clone(), self.token_storage_deposit()));
+        }
+        match Self::storage_deposits(&self.token_contract, storage_deposits) {
+            Some(storage_deposit) => storage_deposit
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(Self::transfer_gas(&claim))
+                        .on_storage_deposit_then_transfer(claim),
+                )
+                .into(),
+            None => self.internal_transfer(claim),
+        }
+    }
+
+    /// Callback: Converts the USD cents of a claim to tokens at the oracle price, then starts
+    /// the claim, or reverts it if the price is missing, stale or below the claimer's minimum.
+    #[private]
+    pub fn on_price_then_claim(
+        &mut self,
+        claim: PendingClaim,
+        min_amount: Option<U128>,
+        #[callback_result] call_result: Result<PriceData, near_sdk::PromiseError>,
+    ) -> PromiseOrValue<ClaimOutcome> {
+        self.internal_unmark_leaf_claimed(&claim.account_id, claim.nonce);
+        if claim.root_version != Some(self.root_version) {
+            log!(
+                "Merkle root changed since the claim of @{} was verified",
+                claim.account_id
+            );
+            self.internal_refund(&claim);
+            return PromiseOrValue::Value(ClaimOutcome::failed(ClaimStatus::RootChanged));
+        }
+        let priced = self.internal_price_claim(claim.clone(), min_amount, call_result.ok());
+        match priced.and_then(|claim| self.internal_start_claim(claim)) {
+            Ok(result) => result,
+            Err(err) => {
+                log!("Claim of @{} failed: {}", claim.account_id, err);
+                self.internal_refund(&claim);
+                PromiseOrValue::Value(ClaimOutcome::failed(ClaimStatus::PricingFailed))
+            }
+        }
+    }
+
+    /// Callback: After the eligibility checks, registers the claimed account and transfers
+    /// the tokens, or reverts the claim if a gate is not met.
+    #[private]
+    pub fn on_eligibility_then_transfer(
+        &mut self,
+        claim: PendingClaim,
+    ) -> PromiseOrValue<ClaimOutcome> {
+        // The results of the checks of each gate follow those of the previous gates
+        let mut index = 0;
+        let eligible = self.eligibility_gates.iter().all(|gate| {
+            let mut checks = index..index + gate.checks();
+            index = checks.end;
+            checks.any(|check| match env::promise_result(check) {
+                PromiseResult::Successful(result) => gate.passed(&result),
+                PromiseResult::Failed => false,
+            })
+        });
+        if !eligible {
+            log!("@{} does not meet the eligibility gates", claim.account_id);
+            self.internal_rollback(&claim, ClaimStatus::Ineligible);
+            return PromiseOrValue::Value(ClaimOutcome::failed(ClaimStatus::Ineligible));
+        }
+        self.internal_register_then_transfer(claim).into()
+    }
+
+    /// Callback: After ft_transfer, only then mark the account as claimed.
+    #[private]
+    pub fn on_ft_transfer_then_claimed(
+        &mut self,
+        claim: PendingClaim,
+        #[callback_result] call_result: Result<(), near_sdk::PromiseError>,
+    ) -> ClaimOutcome {
+        if call_result.is_err() {
+            if self.ious_enabled {
+                return self.internal_record_iou(claim);
+            }
+            self.internal_rollback(&claim, ClaimStatus::TransferFailed);
+            return ClaimOutcome::failed(ClaimStatus::TransferFailed);
+        }
+        self.internal_complete_claim(claim)
+    }
+
+    /// Callback: After ft_transfer_call, sends the tokens the receiver did not use to the
+    /// claimed account and marks the claim as successful.
+    #[private]
+    pub fn on_ft_transfer_call_then_claimed(
+        &mut self,
+        claim: PendingClaim,
+        #[callback_result] call_result: Result<U128, near_sdk::PromiseError>,
+    ) -> ClaimOutcome {
+        let used = match call_result {
+            Ok(used) => used.0,
+            Err(_) => {
+                self.internal_rollback(&claim, ClaimStatus::TransferFailed);
+                return ClaimOutcome::failed(ClaimStatus::TransferFailed);
+            }
+        };
+        // A stream that was not created must not pay the tokens out as a lump sum
+        if used == 0 && claim.stream_duration.is_some() {
+            self.internal_rollback(&claim, ClaimStatus::TransferFailed);
+            return ClaimOutcome::failed(ClaimStatus::TransferFailed);
+        }
+        let unused = claim.net_amount().saturating_sub(used);
+        if unused > 0 {
+            log!(
+                "Returning {} unused tokens to @{}",
+                unused,
+                claim.receiver_id()
+            );
+            Promise::new(self.token_contract.clone()).function_call(
+                "ft_transfer".to_string(),
+                Self::ft_transfer_args(claim.receiver_id(), unused, claim.memo.as_deref()),
+                NearToken::from_yoctonear(1),
+                GAS_FOR_FT_TRANSFER,
+            );
+        }
+        self.internal_complete_claim(claim)
+    }
+
+    /// Returns the current Merkle root.
+    pub fn get_merkle_root(&self) -> String {
+        self.merkle_root.clone()
+    }
+
+    /// Returns the totals declared with the current Merkle root, if any.
+    pub fn get_root_totals(&self) -> Option<RootTotals> {
+        self.root_totals.clone()
+    }
+
+    /// Returns the tokens claimed or reserved against the current Merkle root, before fees
+    /// and bonuses.
+    pub fn get_root_claimed(&self) -> U128 {
+        U128(self.root_claimed)
+    }
+
+    /// Returns the number of Merkle root updates, which claims in flight are bound to.
+    pub fn get_root_version(&self) -> u32 {
+        self.root_version
+    }
+
+    /// Returns the hash algorithm used to verify Merkle proofs.
+    pub fn get_hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// Returns the leaf/node hashing scheme of the current Merkle root.
+    pub fn get_leaf_format(&self) -> LeafFormat {
+        self.leaf_format
+    }
+
+    /// Returns the hex root of the Merkle tree of the accounts of successful claims, whose leaf
+    /// `i` is the account of claim `i`, see `get_claims`.
+    pub fn get_claimed_snapshot_root(&self) -> String {
+        hex::encode(self.claimed_snapshot.root(self.hash_algorithm))
+    }
+
+    /// Returns the number of leaves of the claimed snapshot.
+    pub fn get_claimed_snapshot_size(&self) -> U64 {
+        U64(self.claimed_snapshot.len())
+    }
+
+    /// Returns the number of claimed accounts imported from a legacy deployment.
+    pub fn get_imported_claimed(&self) -> U64 {
+        U64(self.imported_claimed)
+    }
+
+    /// Returns the domain leaves are prefixed with before hashing, if any.
+    pub fn get_leaf_domain(&self) -> Option<LeafDomain> {
+        self.leaf_domain.clone()
+    }
+
+    /// Returns the leaf the contract expects for `account_id` and `amount`, and its hash, so
+    /// tree builders can check their encoding. The account ID is normalized first.
+    pub fn compute_leaf(
+        &self,
+        account_id: String,
+        amount: U128,
+        extras: Option<LeafExtras>,
+    ) -> ComputedLeaf {
+        let account_id = normalize_account_id(&account_id)
+            .unwrap_or_else(|| env::panic_str("Invalid account ID."));
+        let leaf = self.domain_leaf(
+            extras
+                .unwrap_or_default()
+                .encode_leaf(&account_id, amount.0),
+        );
+        let hash = self
+            .leaf_format
+            .hash_leaf(self.hash_algorithm, leaf.as_bytes());
+        ComputedLeaf {
+            leaf,
+            hash: hex::encode(hash),
+        }
+    }
+
+    /// Returns the proposed token contract change, if any.
+    pub fn get_token_change(&self) -> Option<TokenChange> {
+        self.token_change.clone()
+    }
+
+    /// Returns the minimum interval between Merkle root updates, in nanoseconds.
+    pub fn get_root_update_cooldown(&self) -> U64 {
+        self.root_update_cooldown
+    }
+
+    /// Returns the earliest time of the next Merkle root update, in nanoseconds since the Unix
+    /// epoch.
+    pub fn get_next_root_update_at(&self) -> U64 {
+        self.next_root_update_at
+    }
+
+    /// Returns the metadata and storage deposit of the token contract, once verified.
+    pub fn get_token_info(&self) -> Option<TokenInfo> {
+        self.token_info.clone()
+    }
+
+    /// Returns the account receiving fees.
+    pub fn get_treasury(&self) -> AccountId {
+        self.treasury_id.clone()
+    }
+
+    /// Returns the description of the airdrop, if set.
+    pub fn get_campaign_metadata(&self) -> Option<CampaignMetadata> {
+        self.campaign_metadata.clone()
+    }
+
+    /// Query the failure counters of the claim chain, for alerting when the token contract
+    /// starts rejecting registrations or transfers.
+    pub fn get_health(&self) -> Health {
+        self.health.clone()
+    }
+
+    /// Query the storage used by the contract, to budget the account balance of large airdrops.
+    pub fn get_storage_report(&self) -> StorageReport {
+        // Borsh length prefix of the set, then of each account id
+        let claimed_set_bytes = self.claimed.iter().fold(4u64, |bytes, account_id| {
+            bytes + 4 + account_id.len() as u64
+        });
+        let storage_usage = env::storage_usage();
+        let locked = env::storage_byte_cost().saturating_mul(storage_usage.into());
+        StorageReport {
+            storage_usage: U64(storage_usage),
+            claimed_set_bytes: U64(claimed_set_bytes),
+            locked,
+            available: env::account_balance().saturating_sub(locked),
+            cost_per_claim: env::storage_byte_cost().saturating_mul(STORAGE_BYTES_PER_CLAIM.into()),
+        }
+    }
+
+    /// Query the token accounting of the contract. Tokens sent with a plain `ft_transfer`
+    /// are not counted as funded.
+    pub fn get_accounting(&self) -> Accounting {
+        Accounting {
+            funded: U128(self.funded),
+            distributed: U128(
+                self.distributed
+                    .saturating_sub(self.reserved)
+                    .saturating_sub(self.locked),
+            ),
+            reserved: U128(self.reserved),
+            locked: U128(self.locked),
+            withdrawn: U128(self.withdrawn),
+        }
+    }
+
+    /// Query the cap on the tokens distributed by claims
+    pub fn get_distribution_cap(&self) -> Option<U128> {
+        self.distribution_cap
+    }
+
+    /// Query the tokens distributed or reserved by claims so far, bonuses included
+    pub fn get_distributed(&self) -> U128 {
+        U128(self.distributed)
+    }
+
+    /// Query whether repeated claims resolve to an `AlreadyClaimed` outcome
+    pub fn get_idempotent_claims(&self) -> bool {
+        self.idempotent_claims
+    }
+
+    /// Query the largest amount a single account can claim
+    pub fn get_max_claim_amount(&self) -> Option<U128> {
+        self.max_claim_amount
+    }
+
+    /// Query the cap on the number of claims per block or epoch
+    pub fn get_claim_rate_limit(&self) -> Option<ClaimRateLimit> {
+        self.claim_rate_limit.clone()
+    }
+
+    /// Query the time from which claims are rejected
+    pub fn get_claim_deadline(&self) -> Option<U64> {
+        self.claim_deadline
+    }
+
+    /// Query how the leftover tokens are burned at finalization
+    pub fn get_unclaimed_burn(&self) -> Option<UnclaimedBurn> {
+        self.unclaimed_burn.clone()
+    }
+
+    /// Query whether the airdrop is finalized
+    pub fn is_finalized(&self) -> bool {
+        self.finalized
+    }
+
+    /// Returns the NEAR fee claimers must attach on top of the 1 yoctoNEAR.
+    pub fn get_claim_fee(&self) -> NearToken {
+        self.claim_fee
+    }
+
+    /// Returns the storage deposit claimers must attach, `None` if the contract pays for it.
+    pub fn get_claimer_storage_deposit(&self) -> Option<NearToken> {
+        self.claimer_storage_deposit
+    }
+
+    /// Returns the deposit claimers attach for the storage of their claim record, zero if the
+    /// contract pays for it.
+    pub fn get_record_deposit(&self) -> NearToken {
+        self.record_deposit()
+    }
+
+    /// Returns the NEAR sent to create implicit accounts, `None` if disabled.
+    pub fn get_implicit_account_funding(&self) -> Option<NearToken> {
+        self.implicit_account_funding
+    }
+
+    /// Returns the `ft_transfer_call` payout settings, `None` if claims use `ft_transfer`.
+    pub fn get_transfer_call_payout(&self) -> Option<TransferCallPayout> {
+        self.transfer_call_payout.clone()
+    }
+
+    /// Returns the price oracle settings, `None` if leaf amounts are in tokens.
+    pub fn get_usd_pricing(&self) -> Option<UsdPricing> {
+        self.usd_pricing.clone()
+    }
+
+    /// Returns the escrowed claim of `account_id`, if any.
+    pub fn get_escrowed_claim(&self, account_id: AccountId) -> Option<EscrowedClaim> {
+        self.escrow.get(&account_id).cloned()
+    }
+
+    /// Returns the escrow period of claims in nanoseconds, `None` if claims are not escrowed.
+    pub fn get_escrow_period(&self) -> Option<U64> {
+        self.escrow_period
+    }
+
+    /// Returns the conditions claimers must meet on top of the Merkle proof.
+    pub fn get_eligibility_gates(&self) -> Vec<EligibilityGate> {
+        self.eligibility_gates.clone()
+    }
+
+    /// Returns the staking/farming contract used by `claim_and_stake`, if any.
+    pub fn get_staking_contract(&self) -> Option<AccountId> {
+        self.staking_contract.clone()
+    }
+
+    /// Returns the share of claimed tokens sent to the treasury, in basis points.
+    pub fn get_fee_bps(&self) -> u16 {
+        self.fee_bps
+    }
+
+    /// Returns the referral bonus in basis points of the claimed amount.
+    pub fn get_referral_bps(&self) -> u16 {
+        self.referral_bps
+    }
+
+    /// Returns the referral rewards earned by `account_id`.
+    pub fn get_referral_stats(&self, account_id: AccountId) -> ReferralStats {
+        self.referral_stats
+            .get(&account_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the receipt of the claim of the leaf of `account_id` with `nonce` in `round`,
+    /// the current round by default, if it has been claimed.
+    pub fn get_claim_record(
+        &self,
+        account_id: AccountId,
+        round: Option<u32>,
+        nonce: Option<U64>,
+    ) -> Option<ClaimRecord> {
+        self.leaf_record(&account_id, round.unwrap_or(self.round), nonce)
+            .cloned()
+    }
+
+    /// Returns up to `limit` successful claims as `(index, account_id, amount, timestamp)`,
+    /// starting at claim index `from_index`. Indexes are assigned in claim order and never
+    /// change, so indexers can resume from the last index they have seen.
+    pub fn get_claims(&self, from_index: u64, limit: u64) -> Vec<(U64, AccountId, U128, U64)> {
+        let from_index = from_index.min(self.claim_index.len() as u64);
+        let to_index = from_index
+            .saturating_add(limit)
+            .min(self.claim_index.len() as u64);
+        (from_index..to_index)
+            .filter_map(|index| {
+                let account_id = self.claim_index.get(index as u32)?;
+                let record = self.claim_records.get(&index)?;
+                Some((
+                    U64(index),
+                    account_id.clone(),
+                    record.amount,
+                    record.timestamp,
+                ))
+            })
+            .collect()
+    }
+
+    /// Returns the decay schedule of claimable amounts, if any.
+    pub fn get_decay_schedule(&self) -> Option<DecaySchedule> {
+        self.decay_schedule.clone()
+    }
+
+    /// Returns the amount `amount` would pay out if claimed now, before fees.
+    pub fn get_claimable_amount(&self, amount: U128) -> U128 {
+        U128(self.internal_decayed_amount(amount.0))
+    }
+
+    /// Returns the early-bird bonus, if any.
+    pub fn get_early_bird_bonus(&self) -> Option<EarlyBirdBonus> {
+        self.early_bird_bonus.clone()
+    }
+
+    /// Returns the tokens still reserved for early-bird bonuses.
+    pub fn get_bonus_pool(&self) -> U128 {
+        U128(self.bonus_pool)
+    }
+
+    /// Returns whether admin methods require 1 yoctoNEAR.
+    pub fn get_admin_deposit_required(&self) -> bool {
+        self.admin_deposit_required
+    }
+
+    /// Builds the `add_proposal` arguments a Sputnik DAO owner needs to call admin methods
+    /// of this contract, e.g. `[("update_merkle_root", {"merkle_root": "..."})]`.
+    pub fn get_proposal_payloads(
+        &self,
+        description: String,
+        actions: Vec<(String, serde_json::Value)>,
+    ) -> serde_json::Value {
+        let deposit = if self.admin_deposit_required {
+            U128(1)
+        } else {
+            U128(0)
+        };
+        let actions: Vec<serde_json::Value> = actions
+            .into_iter()
+            .map(|(method_name, args)| {
+                json!({
+                    "method_name": method_name,
+                    "args": Base64VecU8::from(args.to_string().into_bytes()),
+                    "deposit": deposit,
+                    "gas": U64(PROPOSAL_ACTION_GAS.as_gas()),
+                })
+            })
+            .collect();
+        json!({
+            "proposal": {
+                "description": description,
+                "kind": {
+                    "FunctionCall": {
+                        "receiver_id": env::current_account_id(),
+                        "actions": actions,
+                    }
+                }
+            }
+        })
+    }
+
+    /// Checks if an account has already claimed their airdrop in the current round.
+    pub fn has_claimed(&self, account_id: AccountId) -> bool {
+        self.is_claimed(&account_id)
+    }
+
+    /// Migrates the state written by a previous version of the contract.
+    /// - `from_version`: The state version of the code being replaced.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate(from_version: u32) -> Self {
+        match from_version {
+            1 => {
+                let old: AirdropContractV1 =
+                    env::state_read().unwrap_or_else(|| env::panic_str("ERR_FAILED_TO_READ_STATE"));
+                let mut contract =
+                    Self::internal_new(old.owner_id, old.token_contract, old.merkle_root);
+                contract.claimed = old.claimed;
+                contract
+            }
+            CURRENT_STATE_VERSION => {
+                env::state_read().unwrap_or_else(|| env::panic_str("ERR_FAILED_TO_READ_STATE"))
+            }
+            _ => env::panic_str("ERR_UNKNOWN_STATE_VERSION"),
+        }
+    }
+
+    pub fn update_contract(&mut self) {
+        // Ensure only owner can call
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only the owner can upgrade"
+        );
+        self.assert_no_council();
+        self.record_admin_action("update_contract");
+
+        // Receive the code directly from the input to avoid the
+        // GAS overhead of deserializing parameters
+        let code = env::input().unwrap_or_else(|| env::panic_str("ERR_NO_INPUT"));
+        Self::internal_deploy_and_migrate(&code);
+    }
+
+    /// Stores new contract code for a later `deploy_staged_code` (only callable by the owner).
+    /// - `code`: The WASM binary, borsh-serialized to avoid JSON overhead.
+    #[payable]
+    pub fn stage_code(&mut self, #[serializer(borsh)] code: Vec<u8>) {
+        self.assert_owner("Only the owner can upgrade");
+        self.record_admin_action("stage_code");
+        require!(!code.is_empty(), "ERR_NO_INPUT");
+        let code_hash: CryptoHash = env::sha256_array(&code);
+        env::storage_write(STAGED_CODE_KEY, &code);
+        self.staged_code_hash = Some(code_hash);
+        log!(
+            "Staged code with hash {}",
+            String::from(&Base58CryptoHash::from(code_hash))
+        );
+    }
+
+    /// Deploys the staged code and migrates the state (only callable by the owner, through the
+    /// council if one is set).
+    /// - `code_hash`: Must match the sha256 of the staged code, as a confirmation.
+    #[payable]
+    pub fn deploy_staged_code(&mut self, code_hash: Base58CryptoHash) {
+        self.assert_owner("Only the owner can upgrade");
+        self.record_admin_action("deploy_staged_code");
+        self.assert_no_council();
+        self.internal_deploy_staged_code(code_hash);
+    }
+
+    /// Returns the sha256 hash of the staged code, if any.
+    pub fn get_staged_code_hash(&self) -> Option<Base58CryptoHash> {
+        self.staged_code_hash.map(Base58CryptoHash::from)
+    }
+
+    /// Query owner
+    pub fn owner(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+    /// Only owner can call, through the council if one is set. Transfer `amount` of given token to the treasury.
+    /// With a withdrawal delay, the withdrawal must have been announced, see `announce_withdrawal`.
+    #[payable]
+    pub fn withdraw_token(&mut self, amount: U128) -> Promise {
+        // Ensure only owner can call
+        self.assert_owner("Only the owner can withdraw tokens");
+        self.record_admin_action("withdraw_token");
+        self.assert_no_council();
+        self.assert_announced_withdrawal(amount);
+        self.internal_withdraw_token(amount)
+    }
+
+    /// Finalizes the airdrop once the claim deadline has passed (only callable by the owner,
+    /// through the council if one is set).
+    /// Claims are closed for good and each call deletes up to `batch_size` claim records to
+    /// release their storage staking. Once none are left, the claimed set is cleared, the
+    /// remaining tokens are swept to the treasury or burned and, with `send_recovered_near`, the
+    /// NEAR not needed for storage is sent to the treasury. Returns whether the finalization
+    /// is complete.
+    #[payable]
+    pub fn finalize_campaign(
+        &mut self,
+        batch_size: u32,
+        send_recovered_near: bool,
+    ) -> PromiseOrValue<bool> {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("finalize_campaign");
+        self.assert_no_council();
+        self.internal_finalize(batch_size, send_recovered_near)
+    }
+
+    /// Callback: Transfers the token balance of the contract not owed to claimers, up to
+    /// `max_amount`, to the treasury, or burns it.
+    #[private]
+    pub fn on_balance_then_sweep(
+        &mut self,
+        max_amount: U128,
+        #[callback_result] call_result: Result<U128, near_sdk::PromiseError>,
+    ) -> bool {
+        let balance = call_result.unwrap_or_else(|_| env::panic_str("Failed to get token balance"));
+        // Tokens still owed to claimers stay in the contract
+        let balance = U128(
+            balance
+                .0
+                .saturating_sub(self.owed_tokens())
+                .min(max_amount.0),
+        );
+        if balance.0 == 0 {
+            return true;
+        }
+        self.internal_count_withdrawal(balance.0);
+        let token = Promise::new(self.token_contract.clone());
+        let (transfer, burn_address) = match &self.unclaimed_burn {
+            None => {
+                log!("Sweeping {} tokens to @{}", balance.0, self.treasury_id);
+                token.function_call(
+                    "ft_transfer".to_string(),
+                    Self::ft_transfer_args(&self.treasury_id, balance.0, None),
+                    NearToken::from_yoctonear(1),
+                    GAS_FOR_FT_TRANSFER,
+                );
+                return true;
+            }
+            Some(UnclaimedBurn::Address { account_id }) => (
+                token.function_call(
+                    "ft_transfer".to_string(),
+                    Self::ft_transfer_args(account_id, balance.0, None),
+                    NearToken::from_yoctonear(1),
+                    GAS_FOR_FT_TRANSFER,
+                ),
+                Some(account_id.clone()),
+            ),
+            Some(UnclaimedBurn::TokenBurn) => (
+                token.function_call(
+                    "burn".to_string(),
+                    json!({ "amount": balance }).to_string().into_bytes(),
+                    NearToken::from_yoctonear(1),
+                    GAS_FOR_FT_TRANSFER,
+                ),
+                None,
+            ),
+        };
+        transfer.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_BURN_CALLBACK)
+                .on_unclaimed_burned(balance, burn_address),
+        );
+        true
+    }
+
+    /// Callback: Emits the burn event once the leftover tokens are burned.
+    #[private]
+    pub fn on_unclaimed_burned(
+        &mut self,
+        amount: U128,
+        burn_address: Option<AccountId>,
+        #[callback_result] call_result: Result<(), near_sdk::PromiseError>,
+    ) -> bool {
+        if call_result.is_err() {
+            log!("Failed to burn {} tokens", amount.0);
+            return false;
+        }
+        AirdropEvent::Burn(vec![BurnEvent {
+            amount,
+            burn_address,
+        }])
+        .emit();
+        true
+    }
+
+    #[private]
+    #[handle_result]
+    pub fn on_check_balance_then_withdraw(
+        &mut self,
+        token_contract: AccountId,
+        to: AccountId,
+        amount: U128,
+        #[callback_result] call_result: Result<Option<U128>, near_sdk::PromiseError>,
+    ) -> Result<Promise, ClaimError> {
+        let balance = match call_result {
+            Ok(Some(b)) => b.0,
+            _ => return Err(ClaimError::BalanceUnavailable),
+        };
+        // Tokens still owed to claimers stay in the contract
+        if amount.0 > balance.saturating_sub(self.owed_tokens()) {
+            return Err(ClaimError::Underfunded);
+        }
+        self.internal_take_announced_withdrawal(amount);
+        self.internal_count_withdrawal(amount.0);
+
+        Ok(Promise::new(token_contract).function_call(
+            "ft_transfer".to_string(),
+            serde_json::json!({
+                "receiver_id": to,
+                "amount": amount,
+            })
+            .to_string()
+            .into_bytes(),
+            NearToken::from_yoctonear(1),
+            Gas::from_gas(10_000_000_000_000),
+        ))
+    }
+
+    /// Counts tokens sent with `ft_transfer_call` of the token contract as funding.
+    /// Tokens of other contracts are refunded.
+    #[allow(unused_variables)]
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        if env::predecessor_account_id() != self.token_contract {
+            return PromiseOrValue::Value(amount);
+        }
+        self.funded = self
+            .funded
+            .checked_add(amount.0)
+            .unwrap_or_else(|| env::panic_str("Accounting overflow"));
+        log!("@{} funded {} tokens", sender_id, amount.0);
+        PromiseOrValue::Value(U128(0))
+    }
+
+    /// Checks the token accounting against the token balance of the contract, failing if
+    /// the balance cannot cover the reserved and locked claims or is lower than the funded
+    /// tokens not yet paid out or withdrawn. Callable by anyone, e.g. monitoring.
+    pub fn assert_invariants(&self) -> Promise {
+        require!(
+            self.reserved.saturating_add(self.locked) <= self.distributed,
+            "Reserved tokens exceed the distributed tokens"
+        );
+        Promise::new(self.token_contract.clone())
+            .function_call(
+                "ft_balance_of".to_string(),
+                json!({ "account_id": env::current_account_id() })
+                    .to_string()
+                    .into_bytes(),
+                NO_DEPOSIT,
+                GAS_FOR_STORAGE_VIEW,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_STORAGE_VIEW)
+                    .on_balance_then_check_invariants(),
+            )
+    }
+
+    /// Callback: Compares the token balance with the accounting.
+    #[private]
+    pub fn on_balance_then_check_invariants(
+        &self,
+        #[callback_result] call_result: Result<U128, near_sdk::PromiseError>,
+    ) -> Accounting {
+        let balance = call_result
+            .unwrap_or_else(|_| env::panic_str("Failed to get token balance"))
+            .0;
+        let accounting = self.get_accounting();
+        let expected = self
+            .funded
+            .saturating_sub(accounting.distributed.0)
+            .saturating_sub(self.withdrawn)
+            .max(self.reserved.saturating_add(self.locked));
+        if balance < expected {
+            env::panic_str(&format!(
+                "Token balance {} is below the expected {}",
+                balance, expected
+            ));
+        }
+        accounting
+    }
+}
+
+impl AirdropContract {
+    /// Verifies a Merkle proof.
+    /// - `leaf`: The leaf node (e.g., "account_id + amount").
+    /// - `root`: The root of the Merkle tree.
+    /// - `proof`: The Merkle proof (an array of sibling hashes).
+    ///
+    /// Returns `true` if the proof is valid, `false` otherwise.
+    pub fn verify_merkle_proof(
+        leaf: String,
+        root: &str,
+        proof: &[String],
+    ) -> Result<bool, ClaimError> {
+        Self::verify_merkle_proof_with(HashAlgorithm::Keccak256, LeafFormat::V1, leaf, root, proof)
+    }
+
+    /// Same as `verify_merkle_proof`, but with an explicit hash algorithm and leaf format.
+    pub fn verify_merkle_proof_with(
+        hash_algorithm: HashAlgorithm,
+        leaf_format: LeafFormat,
+        leaf: String,
+        root: &str,
+        proof: &[String],
+    ) -> Result<bool, ClaimError> {
+        if proof.len() > MAX_PROOF_LENGTH {
+            return Err(ClaimError::ProofTooLong);
+        }
+        let proof = proof
+            .iter()
+            .map(|sibling| Self::decode_hash(sibling))
+            .collect::<Result<Vec<_>, _>>()?;
+        let hash = merkle::process_proof(hash_algorithm, leaf_format, leaf.as_bytes(), &proof);
+        Ok(hash == Self::decode_hash(root)?)
+    }
+
+    /// Verifies a Merkle multiproof for several leaves at once.
+    /// - `leaves`: The leaf nodes, in the order they appear in the tree.
+    /// - `root`: The root of the Merkle tree.
+    /// - `proof`: The sibling hashes not derivable from the leaves themselves.
+    /// - `flags`: For each hashing step, `true` to pair with the next leaf/computed hash,
+    ///   `false` to pair with the next proof element.
+    ///
+    /// Returns `true` if the multiproof is valid, `false` otherwise.
+    pub fn verify_merkle_multiproof_with(
+        hash_algorithm: HashAlgorithm,
+        leaf_format: LeafFormat,
+        leaves: Vec<String>,
+        root: &str,
+        proof: &[String],
+        flags: &[bool],
+    ) -> Result<bool, ClaimError> {
+        if proof.len() > MAX_PROOF_LENGTH.saturating_mul(leaves.len()) {
+            return Err(ClaimError::ProofTooLong);
+        }
+        let proof = proof
+            .iter()
+            .map(|sibling| Self::decode_hash(sibling))
+            .collect::<Result<Vec<_>, _>>()?;
+        match merkle::process_multiproof(hash_algorithm, leaf_format, &leaves, proof, flags) {
+            Some(hash) => Ok(hash == Self::decode_hash(root)?),
+            None => Ok(false),
+        }
+    }
+
+    /// Sets the treasury, see `set_treasury`.
+    fn internal_set_treasury(&mut self, treasury_id: AccountId) {
+        log!(
+            "Treasury updated from {} to {}",
+            self.treasury_id,
+            treasury_id
+        );
+        self.treasury_id = treasury_id;
+    }
+
+    /// Sets how the leftover tokens are burned, see `set_unclaimed_burn`.
+    fn internal_set_unclaimed_burn(&mut self, unclaimed_burn: Option<UnclaimedBurn>) {
+        self.unclaimed_burn = unclaimed_burn;
+        log!("Unclaimed burn set to {:?}", self.unclaimed_burn);
+    }
+
+    /// Sets the claim deadline, see `set_claim_deadline`.
+    fn internal_set_claim_deadline(&mut self, claim_deadline: Option<U64>) {
+        require!(!self.finalized, "The airdrop is finalized");
+        self.claim_deadline = claim_deadline;
+        log!("Claim deadline set to {:?}", self.claim_deadline);
+    }
+
+    /// Pushes tokens to `recipients`, see `distribute`.
+    fn internal_distribute(&mut self, recipients: Vec<(AccountId, U128)>) -> u32 {
+        let total = recipients
+            .iter()
+            .try_fold(0u128, |total, (_, amount)| total.checked_add(amount.0))
+            .unwrap_or_else(|| env::panic_str("Accounting overflow"));
+        self.assert_announced_withdrawal(U128(total));
+        let mut processed = 0;
+        for (account_id, amount) in recipients {
+            let remaining_gas = env::prepaid_gas().saturating_sub(env::used_gas());
+            if remaining_gas < GAS_PER_DISTRIBUTION.saturating_add(DISTRIBUTION_GAS_RESERVE) {
+                break;
+            }
+            processed += 1;
+            if self.is_claimed(&account_id)
+                || self.escrow.contains_key(&account_id)
+                || self.held_claims.contains_key(&account_id)
+            {
+                log!("Skipping @{}: already claimed", account_id);
+                continue;
+            }
+            // The single yoctoNEAR attached by the owner covers the whole batch
+            let claim = PendingClaim {
+                deposit: U128(0),
+                pushed: true,
+                ..PendingClaim::new(account_id.clone(), amount, NO_DEPOSIT)
+            };
+            match self.internal_reserve_claim(claim) {
+                Ok(claim) => {
+                    self.internal_take_announced_withdrawal(claim.amount);
+                    self.internal_escrow_or_transfer(claim);
+                }
+                Err(err) => log!("Skipping @{}: {}", account_id, err),
+            }
+        }
+        log!("Distributed to {} recipients", processed);
+        processed
+    }
+
+    /// Finalizes the airdrop, see `finalize_campaign`.
+    fn internal_finalize(
+        &mut self,
+        batch_size: u32,
+        send_recovered_near: bool,
+    ) -> PromiseOrValue<bool> {
+        require!(
+            self.claim_deadline
+                .is_some_and(|deadline| env::block_timestamp() >= deadline.0),
+            "The claim deadline has not passed"
+        );
+        self.finalized = true;
+        for _ in 0..batch_size {
+            match self.claim_index.pop() {
+                Some(account_id) => {
+                    let index = self.claim_index.len() as u64;
+                    if let Some(record) = self.claim_records.remove(&index) {
+                        self.leaf_records.remove(&(
+                            record.round,
+                            account_id,
+                            record.nonce.map(|nonce| nonce.0),
+                        ));
+                    }
+                }
+                None => break,
+            }
+        }
+        if !self.claim_index.is_empty() {
+            log!("{} claim records left to delete", self.claim_index.len());
+            return PromiseOrValue::Value(false);
+        }
+        self.claimed.clear();
+
+        if send_recovered_near {
+            let storage_staking = env::storage_byte_cost()
+                .saturating_mul(env::storage_usage().into())
+                .saturating_add(FINALIZATION_NEAR_RESERVE);
+            let recovered = env::account_balance().saturating_sub(storage_staking);
+            if !recovered.is_zero() {
+                log!(
+                    "Sending {} yoctoNEAR to the treasury",
+                    recovered.as_yoctonear()
+                );
+                Promise::new(self.treasury_id.clone()).transfer(recovered);
+            }
+        }
+        log!("Airdrop finalized");
+        let Some(allowance) = self.internal_take_sweep_allowance() else {
+            log!("The unclaimed tokens are swept once a withdrawal is announced and executable");
+            return PromiseOrValue::Value(true);
+        };
+        Promise::new(self.token_contract.clone())
+            .function_call(
+                "ft_balance_of".to_string(),
+                json!({ "account_id": env::current_account_id() })
+                    .to_string()
+                    .into_bytes(),
+                NO_DEPOSIT,
+                GAS_FOR_STORAGE_VIEW,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_FT_TRANSFER.saturating_add(GAS_FOR_BURN_CALLBACK))
+                    .on_balance_then_sweep(U128(allowance)),
+            )
+            .into()
+    }
+
+    /// Builds the initial state with default settings.
+    fn internal_new(owner_id: AccountId, token_contract: AccountId, merkle_root: String) -> Self {
+        let mut contract = Self {
+            treasury_id: owner_id.clone(),
+            owner_id,
+            token_contract,
+            merkle_root,
+            claimed: std::collections::HashSet::new(),
+            hash_algorithm: HashAlgorithm::default(),
+            leaf_format: LeafFormat::default(),
+            admin_deposit_required: true,
+            staged_code_hash: None,
+            claim_fee: NO_DEPOSIT,
+            fee_bps: 0,
+            referral_bps: 0,
+            referral_stats: LookupMap::new(StorageKey::ReferralStats),
+            decay_schedule: None,
+            early_bird_bonus: None,
+            bonus_pool: 0,
+            claim_records: LookupMap::new(StorageKey::ClaimRecords),
+            leaf_records: LookupMap::new(StorageKey::LeafRecords),
+            claim_index: Vector::new(StorageKey::ClaimIndex),
+            claimer_storage_deposit: None,
+            staking_contract: None,
+            transfer_call_payout: None,
+            eligibility_gates: Vec::new(),
+            usd_pricing: None,
+            raffle: None,
+            raffle_entrants: Vector::new(StorageKey::RaffleEntrants),
+            raffle_entered: LookupSet::new(StorageKey::RaffleEntered),
+            raffle_winners: LookupMap::new(StorageKey::RaffleWinners),
+            pro_rata: None,
+            pro_rata_weights: LookupMap::new(StorageKey::ProRataWeights),
+            escrow_period: None,
+            guardian_id: None,
+            escrow: LookupMap::new(StorageKey::Escrow),
+            campaign_metadata: None,
+            claim_deadline: None,
+            finalized: false,
+            unclaimed_burn: None,
+            claim_rate_limit: None,
+            rate_window: 0,
+            rate_window_claims: 0,
+            distribution_cap: None,
+            distributed: 0,
+            reserved: 0,
+            locked: 0,
+            funded: 0,
+            withdrawn: 0,
+            max_claim_amount: None,
+            idempotent_claims: false,
+            health: Health::default(),
+            token_info: None,
+            token_change: None,
+            basket_legs: LookupMap::new(StorageKey::BasketLegs),
+            implicit_account_funding: None,
+            attestor_key: None,
+            claimed_emails: LookupMap::new(StorageKey::ClaimedEmails),
+            claim_keys: LookupMap::new(StorageKey::ClaimKeys),
+            croncat_manager: None,
+            escrow_queue: Vector::new(StorageKey::EscrowQueue),
+            escrow_cursor: 0,
+            relayed_claims: false,
+            relayers: LookupMap::new(StorageKey::Relayers),
+            social_posts: None,
+            nft_badge: None,
+            council: None,
+            council_proposals: LookupMap::new(StorageKey::CouncilProposals),
+            next_council_proposal: 0,
+            admin_log: Vector::new(StorageKey::AdminLog),
+            root_update_cooldown: U64(DEFAULT_ROOT_UPDATE_COOLDOWN),
+            next_root_update_at: U64(0),
+            root_commitments: Vec::new(),
+            root_totals: None,
+            root_claimed: 0,
+            root_version: 0,
+            round: 0,
+            rounds: LookupMap::new(StorageKey::Rounds),
+            round_claimed: LookupSet::new(StorageKey::RoundClaimed),
+            nonce_claimed: LookupSet::new(StorageKey::NonceClaimed),
+            leaf_domain: None,
+            imported_claimed: 0,
+            claimed_snapshot: IncrementalMerkleTree::default(),
+            claimer_pays_records: false,
+            ious_enabled: false,
+            ious: Vector::new(StorageKey::Ious),
+            iou_expiry: None,
+            partial_fills: false,
+            shortfalls: LookupMap::new(StorageKey::Shortfalls),
+            shortfall_total: 0,
+            tier_starts: LookupMap::new(StorageKey::TierStarts),
+            terms_hash: None,
+            terms_accepted: LookupMap::new(StorageKey::TermsAccepted),
+            terms_acceptors: Vector::new(StorageKey::TermsAcceptors),
+            compliance_id: None,
+            legal_holds: LookupSet::new(StorageKey::LegalHolds),
+            held_claims: LookupMap::new(StorageKey::HeldClaims),
+            withdrawal_delay: None,
+            pending_withdrawal: None,
+            fee_exempt: IterableSet::new(StorageKey::FeeExempt),
+            swap_route: None,
+            liquid_staking_contract: None,
+            unwrap_wnear: false,
+            streaming_contract: None,
+            lockup_factory: None,
+            lock_boosts: Vec::new(),
+            lock_bonus_pool: 0,
+            locks: LookupMap::new(StorageKey::Locks),
+            streak_bonus: None,
+            streak_bonus_pool: 0,
+            streaks: LookupMap::new(StorageKey::Streaks),
+            weight_classes: Vec::new(),
+            claimed_storage: ClaimedStorage::default(),
+            hashed_claimed: LookupSet::new(StorageKey::HashedClaimed),
+        };
+        contract.rounds.insert(
+            0,
+            Round {
+                merkle_root: contract.merkle_root.clone(),
+                opened_at: U64(env::block_timestamp()),
+                closed_at: None,
+                claims: 0,
+                claimed_amount: U128(0),
+            },
+        );
+        contract
+    }
+
+    /// Deploys `code` to this account and calls `migrate` in the same batch,
+    /// so the upgrade fails as a whole if the migration fails.
+    fn internal_deploy_and_migrate(code: &[u8]) {
+        // Deploy the contract code.
         let promise_id = env::promise_batch_create(&env::current_account_id());
-        env::promise_batch_action_deploy_contract(promise_id, &code);
+        env::promise_batch_action_deploy_contract(promise_id, code);
         // Call promise to migrate the state.
         // Batched together to fail upgrade if migration fails.
         env::promise_batch_action_function_call(
@@ -221,130 +3204,4748 @@ impl AirdropContract {
                 .saturating_sub(env::used_gas())
                 .saturating_sub(OUTER_UPGRADE_GAS),
         );
-        env::promise_return(promise_id);
+        env::promise_return(promise_id);
+    }
+
+    /// Deposit a claim must attach besides the 1 yoctoNEAR: the claim fee, the storage deposit
+    /// of the claimer and the storage of its NFT badge.
+    fn claim_deposit(&self) -> NearToken {
+        self.claim_fee
+            .saturating_add(self.claimer_storage_deposit.unwrap_or(NO_DEPOSIT))
+            .saturating_add(self.badge_deposit())
+            .saturating_add(self.record_deposit())
+    }
+
+    /// Deposit claimers attach for the storage of their claim record, if they pay for it.
+    fn record_deposit(&self) -> NearToken {
+        if !self.claimer_pays_records {
+            return NO_DEPOSIT;
+        }
+        env::storage_byte_cost().saturating_mul(STORAGE_BYTES_PER_CLAIM.into())
+    }
+
+    /// Storage deposit of the NFT badge minted for each claim, if any.
+    fn badge_deposit(&self) -> NearToken {
+        self.nft_badge
+            .as_ref()
+            .map_or(NO_DEPOSIT, |nft_badge| nft_badge.deposit)
+    }
+
+    /// Updates the Merkle root, normalized to lowercase hex, with its declared totals and
+    /// optionally the leaf format.
+    fn internal_update_merkle_root(
+        &mut self,
+        merkle_root: &str,
+        root_totals: RootTotals,
+        leaf_format: Option<LeafFormat>,
+    ) {
+        require!(
+            root_totals.total_amount.0 > 0 && root_totals.leaf_count > 0,
+            "The declared totals must be positive"
+        );
+        let now = env::block_timestamp();
+        if now < self.next_root_update_at.0 {
+            env::panic_str(&format!(
+                "Root updates are on cooldown until {}",
+                self.next_root_update_at.0
+            ));
+        }
+        self.next_root_update_at = U64(now.saturating_add(self.root_update_cooldown.0));
+        self.merkle_root = Self::normalize_root(merkle_root);
+        if let Some(leaf_format) = leaf_format {
+            self.leaf_format = leaf_format;
+        }
+        env::log_str(&format!(
+            "Merkle root updated to {} with {} tokens over {} leaves",
+            self.merkle_root, root_totals.total_amount.0, root_totals.leaf_count
+        ));
+        self.root_totals = Some(root_totals);
+        self.root_claimed = 0;
+        self.root_version += 1;
+        if let Some(round) = self.rounds.get_mut(&self.round) {
+            round.merkle_root = self.merkle_root.clone();
+        }
+    }
+
+    /// Deploys the staged code matching `code_hash` and migrates the state.
+    fn internal_deploy_staged_code(&mut self, code_hash: Base58CryptoHash) {
+        let staged_code_hash = self
+            .staged_code_hash
+            .take()
+            .unwrap_or_else(|| env::panic_str("No staged code"));
+        require!(
+            CryptoHash::from(code_hash) == staged_code_hash,
+            "Code hash does not match the staged code"
+        );
+        let code =
+            env::storage_read(STAGED_CODE_KEY).unwrap_or_else(|| env::panic_str("No staged code"));
+        env::storage_remove(STAGED_CODE_KEY);
+        Self::internal_deploy_and_migrate(&code);
+    }
+
+    /// Withdraws `amount` tokens to the treasury, once the balance is checked.
+    fn internal_withdraw_token(&self, amount: U128) -> Promise {
+        Promise::new(self.token_contract.clone())
+            .function_call(
+                "ft_balance_of".to_string(),
+                serde_json::json!({
+                    "account_id": env::current_account_id()
+                })
+                .to_string()
+                .into_bytes(),
+                NearToken::from_near(0),
+                Gas::from_gas(10_000_000_000_000),
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_gas(30_000_000_000_000))
+                    .on_check_balance_then_withdraw(
+                        self.token_contract.clone(),
+                        self.treasury_id.clone(),
+                        amount,
+                    ),
+            )
+    }
+
+    /// Ensures the claimer attached exactly 1 yoctoNEAR plus the claim fee. With relayed claims,
+    /// the 1 yoctoNEAR is optional.
+    fn assert_claim_deposit(&self) -> Result<(), ClaimError> {
+        self.assert_claim_deposit_with(NO_DEPOSIT)
+    }
+
+    /// Ensures the attached deposit covers the claim and `extra`, like `assert_claim_deposit`.
+    fn assert_claim_deposit_with(&self, extra: NearToken) -> Result<(), ClaimError> {
+        let expected = self
+            .claim_deposit()
+            .saturating_sub(self.claim_fee)
+            .saturating_add(self.caller_claim_fee())
+            .saturating_add(extra);
+        let attached = env::attached_deposit();
+        if attached != expected.saturating_add(NearToken::from_yoctonear(1))
+            && !(self.relayed_claims && attached == expected)
+        {
+            return Err(ClaimError::InvalidDeposit);
+        }
+        Ok(())
+    }
+
+    /// Prefixes `leaf` with the leaf domain, if set.
+    pub(crate) fn domain_leaf(&self, leaf: String) -> String {
+        match &self.leaf_domain {
+            Some(leaf_domain) => leaf_domain.prefix_leaf(&env::current_account_id(), &leaf),
+            None => leaf,
+        }
+    }
+
+    /// Ensures the claim deadline has not passed and the airdrop is not finalized.
+    fn assert_claims_open(&self) -> Result<(), ClaimError> {
+        let deadline_passed = self
+            .claim_deadline
+            .is_some_and(|deadline| env::block_timestamp() >= deadline.0);
+        if self.finalized || deadline_passed {
+            return Err(ClaimError::CampaignClosed);
+        }
+        self.assert_round_open()
+    }
+
+    /// Decodes a 32-byte hash of a Merkle root or proof, encoded as hex (optionally
+    /// `0x`-prefixed), base58 or base64.
+    fn decode_hash(hash: &str) -> Result<Vec<u8>, ClaimError> {
+        let hash = hash.trim();
+        let unprefixed = hash
+            .strip_prefix("0x")
+            .or_else(|| hash.strip_prefix("0X"))
+            .unwrap_or(hash);
+        // A 32-byte hash is 64 hex characters, while base58 and base64 use at most 44.
+        let decoded = if unprefixed.len() == HASH_LENGTH * 2 {
+            hex::decode(unprefixed).ok()
+        } else {
+            near_sdk::bs58::decode(hash)
+                .into_vec()
+                .ok()
+                .filter(|bytes| bytes.len() == HASH_LENGTH)
+                .or_else(|| {
+                    near_sdk::base64::engine::general_purpose::STANDARD
+                        .decode(hash)
+                        .ok()
+                })
+        };
+        decoded
+            .filter(|bytes| bytes.len() == HASH_LENGTH)
+            .ok_or(ClaimError::InvalidProofEncoding)
+    }
+
+    /// Normalizes a Merkle root to lowercase hex.
+    fn normalize_root(root: &str) -> String {
+        match Self::decode_hash(root) {
+            Ok(root) => hex::encode(root),
+            Err(_) => env::panic_str("Invalid Merkle root, expected a 32-byte hash."),
+        }
+    }
+
+    /// Ensures the caller is the owner and, unless disabled for DAO owners, attached 1 yoctoNEAR.
+    fn assert_owner(&self, message: &str) {
+        if self.admin_deposit_required {
+            assert_one_yocto();
+        }
+        require!(env::predecessor_account_id() == self.owner_id, message);
+    }
+
+    /// Marks the account as claimed and starts the storage_deposit/ft_transfer chain,
+    /// or holds the claim in escrow if an escrow period is set.
+    fn internal_start_claim(
+        &mut self,
+        claim: PendingClaim,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_terms_accepted(&claim.account_id)?;
+        self.assert_streamable(&claim)?;
+        self.assert_lockup(&claim)?;
+        if self.held_claims.contains_key(&claim.account_id) {
+            return Err(ClaimError::AlreadyClaimed);
+        }
+        if self.escrow_period.is_none() {
+            self.assert_claim_gas(&claim)?;
+        }
+        // Fallible checks come first: in callbacks an error does not revert the state
+        self.assert_claim_rate()?;
+        let claim = self.internal_reserve_claim(claim)?;
+        self.internal_record_claim_rate();
+        Ok(self.internal_escrow_or_transfer(claim))
+    }
+
+    /// Holds the reserved `claim` in escrow during the dispute window, if any, or transfers it.
+    fn internal_escrow_or_transfer(&mut self, claim: PendingClaim) -> PromiseOrValue<ClaimOutcome> {
+        if let Some(escrow_period) = self.escrow_period {
+            let releases_at = U64(env::block_timestamp().saturating_add(escrow_period.0));
+            log!(
+                "Claim of @{} held in escrow until {}",
+                claim.account_id,
+                releases_at.0
+            );
+            let amount = U128(claim.net_amount());
+            self.escrow_queue.push(claim.account_id.clone());
+            self.escrow.insert(
+                claim.account_id.clone(),
+                EscrowedClaim { claim, releases_at },
+            );
+            return PromiseOrValue::Value(ClaimOutcome {
+                status: ClaimStatus::Escrowed,
+                amount,
+                receipt_index: None,
+                claimed_at: None,
+            });
+        }
+        self.internal_transfer_unless_held(claim)
+    }
+
+    /// With idempotent claims, the outcome of a repeated claim by `account_id`, whose deposit
+    /// is refunded.
+    fn internal_repeated_claim(
+        &self,
+        account_id: &AccountId,
+        nonce: Option<U64>,
+    ) -> Option<ClaimOutcome> {
+        if !self.idempotent_claims || !self.is_leaf_claimed(account_id, nonce) {
+            return None;
+        }
+        Promise::new(env::predecessor_account_id()).transfer(env::attached_deposit());
+        let record = self.leaf_record(account_id, self.round, nonce);
+        Some(ClaimOutcome {
+            status: ClaimStatus::AlreadyClaimed,
+            amount: record.map_or(U128(0), |record| record.amount),
+            receipt_index: record.map(|record| record.index),
+            claimed_at: record.map(|record| record.timestamp),
+        })
+    }
+
+    /// Receipt of the claim of the leaf of `account_id` with `nonce` in `round`.
+    fn leaf_record(
+        &self,
+        account_id: &AccountId,
+        round: u32,
+        nonce: Option<U64>,
+    ) -> Option<&ClaimRecord> {
+        let index =
+            self.leaf_records
+                .get(&(round, account_id.clone(), nonce.map(|nonce| nonce.0)))?;
+        self.claim_records.get(index)
+    }
+
+    /// Ensures the prepaid gas covers the whole storage_deposit/ft_transfer chain of `claim`,
+    /// so an underfunded call fails before the account is marked as claimed.
+    fn assert_claim_gas(&self, claim: &PendingClaim) -> Result<(), ClaimError> {
+        let registrations = if claim.referrer.is_some() { 2 } else { 1 };
+        let mut required = Self::transfer_gas(claim)
+            .saturating_add(GAS_FOR_STORAGE_DEPOSIT.saturating_mul(registrations))
+            .saturating_add(CLAIM_GAS_RESERVE);
+        // The treasury leg is only known once the fees are computed
+        if self.fee_bps_of(&claim.account_id) > 0 || self.decay_schedule.is_some() {
+            required = required.saturating_add(GAS_FOR_FT_TRANSFER);
+        }
+        if claim.storage_deposit.0 > 0 {
+            required = required
+                .saturating_add(GAS_FOR_STORAGE_VIEW)
+                .saturating_add(GAS_FOR_REGISTRATION_CALLBACK);
+        }
+        if !self.eligibility_gates.is_empty() {
+            let checks: u64 = self
+                .eligibility_gates
+                .iter()
+                .map(EligibilityGate::checks)
+                .sum();
+            required = required
+                .saturating_add(GAS_FOR_ELIGIBILITY_CHECK.saturating_mul(checks))
+                .saturating_add(GAS_FOR_ELIGIBILITY_CALLBACK)
+                .saturating_add(GAS_FOR_REGISTRATION_CALLBACK);
+        }
+        if env::prepaid_gas().saturating_sub(env::used_gas()) < required {
+            return Err(ClaimError::InsufficientGas);
+        }
+        Ok(())
+    }
+
+    /// Counts a claim against the rate limit of the current block or epoch.
+    fn internal_count_claim(&mut self) -> Result<(), ClaimError> {
+        self.assert_claim_rate()?;
+        self.internal_record_claim_rate();
+        Ok(())
+    }
+
+    /// Ensures the rate limit of the current block or epoch allows another claim.
+    fn assert_claim_rate(&self) -> Result<(), ClaimError> {
+        match self.claim_rate_window() {
+            Some((window, max_claims))
+                if window == self.rate_window && self.rate_window_claims >= max_claims =>
+            {
+                Err(ClaimError::RateLimited)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts a claim allowed by `assert_claim_rate`.
+    fn internal_record_claim_rate(&mut self) {
+        let Some((window, _)) = self.claim_rate_window() else {
+            return;
+        };
+        if window != self.rate_window {
+            self.rate_window = window;
+            self.rate_window_claims = 0;
+        }
+        self.rate_window_claims += 1;
+    }
+
+    /// Current block or epoch of the rate limit and its number of claims, if any.
+    fn claim_rate_window(&self) -> Option<(u64, u32)> {
+        let limit = self.claim_rate_limit.as_ref()?;
+        let window = match limit.period {
+            RatePeriod::Block => env::block_height(),
+            RatePeriod::Epoch => env::epoch_height(),
+        };
+        Some((window, limit.max_claims))
+    }
+
+    /// Checks the eligibility gates, then registers the claimed account and transfers the tokens.
+    fn internal_check_then_transfer(&self, claim: PendingClaim) -> Promise {
+        if self.eligibility_gates.is_empty() || claim.pushed {
+            return self.internal_register_then_transfer(claim);
+        }
+
+        // All gates are checked in parallel, their results are read in order by the callback
+        let checks = self
+            .eligibility_gates
+            .iter()
+            .map(|gate| gate.check(&claim.account_id))
+            .reduce(|checks, check| checks.and(check))
+            .unwrap_or_else(|| env::abort());
+        let registration_gas = Self::transfer_gas(&claim)
+            .saturating_add(GAS_FOR_REGISTRATION_CALLBACK)
+            .saturating_add(GAS_FOR_ELIGIBILITY_CALLBACK);
+        checks.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(registration_gas)
+                .on_eligibility_then_transfer(claim),
+        )
+    }
+
+    /// Computes the fees and bonuses of the claim, reserves them and marks the account as claimed.
+    fn internal_reserve_claim(
+        &mut self,
+        mut claim: PendingClaim,
+    ) -> Result<PendingClaim, ClaimError> {
+        self.assert_claims_open()?;
+        if self
+            .max_claim_amount
+            .is_some_and(|max| claim.amount.0 > max.0)
+        {
+            return Err(ClaimError::AmountTooLarge);
+        }
+        self.internal_partial_fill(&mut claim)?;
+        let payout = self.internal_decayed_amount(claim.amount.0);
+        claim.forfeited = U128(claim.amount.0 - payout);
+        claim.protocol_fee = U128(apply_bps(payout, self.fee_bps_of(&claim.account_id)));
+        if claim.referrer.is_some() {
+            claim.referral_bonus = U128(apply_bps(payout, self.referral_bps));
+        }
+        if let Some(months) = claim.lock_months {
+            claim.lock_bonus = U128(self.internal_take_lock_bonus(months, payout)?);
+        }
+        // Pushed distributions are not claims and earn no bonus
+        if !claim.pushed {
+            if let Some(early_bird) = self
+                .early_bird_bonus
+                .as_ref()
+                .filter(|bonus| env::block_timestamp() < bonus.ends_at.0)
+            {
+                let bonus = apply_bps(payout, early_bird.bonus_bps).min(self.bonus_pool);
+                self.bonus_pool -= bonus;
+                claim.early_bird_bonus = U128(bonus);
+            }
+            claim.streak_bonus = U128(self.internal_take_streak_bonus(&claim.account_id, payout));
+        }
+        if let Err(err) = self.internal_reserve_amounts(&claim) {
+            self.internal_restore_bonuses(&claim);
+            return Err(err);
+        }
+        if claim.pays_leaf() {
+            claim.root_version.get_or_insert(self.root_version);
+        }
+
+        // Mark the account as claimed
+        if claim.claims_leaf() {
+            self.internal_mark_leaf_claimed(claim.account_id.clone(), claim.nonce);
+        }
+        self.internal_record_shortfall(&claim);
+        Ok(claim)
+    }
+
+    /// Reserves the tokens of `claim` against the distribution cap and the root totals. Leaves
+    /// the state untouched on failure.
+    fn internal_reserve_amounts(&mut self, claim: &PendingClaim) -> Result<(), ClaimError> {
+        if claim.net_amount() == 0 {
+            return Err(ClaimError::CampaignClosed);
+        }
+        let total_amount = claim.total_amount().ok_or(ClaimError::AmountOverflow)?;
+        let distributed = self
+            .distributed
+            .checked_add(total_amount)
+            .ok_or(ClaimError::AmountOverflow)?;
+        let reserved = self
+            .reserved
+            .checked_add(total_amount)
+            .ok_or(ClaimError::AmountOverflow)?;
+        if self.distribution_cap.is_some_and(|cap| distributed > cap.0) {
+            return Err(ClaimError::CapExceeded);
+        }
+        if claim.pays_leaf() {
+            self.internal_count_root_claim(claim.amount.0)?;
+        }
+        self.distributed = distributed;
+        self.reserved = reserved;
+        Ok(())
+    }
+
+    /// Returns the bonuses taken by `claim` to their pools.
+    fn internal_restore_bonuses(&mut self, claim: &PendingClaim) {
+        self.bonus_pool += claim.early_bird_bonus.0;
+        self.lock_bonus_pool += claim.lock_bonus.0;
+        self.streak_bonus_pool += claim.streak_bonus.0;
+    }
+
+    /// Registers the claimed account with the token contract, then transfers the tokens, or
+    /// pays the legs of a split claim.
+    fn internal_register_then_transfer(&self, claim: PendingClaim) -> Promise {
+        if !claim.split.is_empty() {
+            return self.internal_split_legs(claim);
+        }
+        // Implicit accounts that signed the claim already exist, others are created first
+        let funding = self
+            .implicit_account_funding
+            .filter(|_| {
+                *claim.receiver_id() != claim.claimer && is_implicit_account(claim.receiver_id())
+            })
+            .map(|amount| Promise::new(claim.receiver_id().clone()).transfer(amount));
+        let registration = self.internal_registration(claim);
+        match funding {
+            Some(funding) => funding.then(registration),
+            None => registration,
+        }
+    }
+
+    /// Registers the claimed account with the token contract, then transfers the claim.
+    fn internal_registration(&self, claim: PendingClaim) -> Promise {
+        let transfer_gas = Self::transfer_gas(&claim);
+
+        // The claimer pays for its own registration, which is only needed if it is not registered yet
+        if claim.storage_deposit.0 > 0 {
+            return Promise::new(self.token_contract.clone())
+                .function_call(
+                    "storage_balance_of".to_string(),
+                    json!({ "account_id": claim.receiver_id() })
+                        .to_string()
+                        .into_bytes(),
+                    NO_DEPOSIT,
+                    GAS_FOR_STORAGE_VIEW,
+                )
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(transfer_gas.saturating_add(GAS_FOR_REGISTRATION_CALLBACK))
+                        .on_storage_balance_of_then_transfer(claim),
+                );
+        }
+
+        // Always call storage_deposit first, regardless of registration status
+        let storage_deposit = self.token_storage_deposit();
+        let mut storage_deposits = vec![(claim.receiver_id().clone(), storage_deposit)];
+        if let Some(referrer) = &claim.referrer {
+            storage_deposits.push((referrer.clone(), storage_deposit));
+        }
+        let storage_deposit = Self::storage_deposits(&self.token_contract, storage_deposits)
+            .unwrap_or_else(|| env::abort());
+
+        // Chain to transfer tokens after storage_deposit
+        storage_deposit.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(transfer_gas)
+                .on_storage_deposit_then_transfer(claim),
+        )
+    }
+
+    /// Deposit attached to the registrations paid by the contract.
+    fn token_storage_deposit(&self) -> NearToken {
+        self.token_info
+            .as_ref()
+            .map_or(STORAGE_DEPOSIT_AMOUNT, |token_info| {
+                token_info.storage_deposit
+            })
+    }
+
+    /// Ensures the claim method matches the denomination of the leaves, so that USD cents
+    /// or weights are never paid out as tokens or the other way around.
+    fn assert_denomination(&self, denomination: Denomination) -> Result<(), ClaimError> {
+        let current = if self.pro_rata.is_some() {
+            Denomination::Weight
+        } else if self.usd_pricing.is_some() {
+            Denomination::UsdCents
+        } else if !self.weight_classes.is_empty() {
+            Denomination::WeightClass
+        } else {
+            Denomination::Tokens
+        };
+        if current != denomination {
+            return Err(ClaimError::WrongDenomination);
+        }
+        Ok(())
+    }
+
+    /// Converts the USD cents of `claim` to tokens with the oracle `price_data`.
+    fn internal_price_claim(
+        &self,
+        claim: PendingClaim,
+        min_amount: Option<U128>,
+        price_data: Option<PriceData>,
+    ) -> Result<PendingClaim, ClaimError> {
+        let usd_pricing = self
+            .usd_pricing
+            .as_ref()
+            .ok_or(ClaimError::WrongDenomination)?;
+        let price_data = price_data.ok_or(ClaimError::PriceUnavailable)?;
+        if price_data
+            .timestamp
+            .0
+            .saturating_add(usd_pricing.max_price_age.0)
+            < env::block_timestamp()
+        {
+            return Err(ClaimError::PriceUnavailable);
+        }
+        let amount = price_data
+            .prices
+            .iter()
+            .find(|price| price.asset_id == usd_pricing.asset_id)
+            .and_then(|price| price.price.as_ref())
+            .ok_or(ClaimError::PriceUnavailable)?
+            .tokens_for_cents(claim.amount.0)
+            .ok_or(ClaimError::AmountOverflow)?;
+        if amount < min_amount.map_or(0, |min_amount| min_amount.0) {
+            return Err(ClaimError::SlippageExceeded);
+        }
+        Ok(PendingClaim {
+            amount: U128(amount),
+            ..claim
+        })
+    }
+
+    /// Ensures `account_id` has not claimed yet and that its leaf is unexpired and in the tree.
+    fn internal_verify_leaf(
+        &self,
+        account_id: &AccountId,
+        amount: U128,
+        merkle_proof: &[String],
+        extras: &LeafExtras,
+    ) -> Result<(), ClaimError> {
+        self.assert_claims_open()?;
+        if amount.0 == 0 {
+            return Err(ClaimError::ZeroAmount);
+        }
+        // Ensure the user has not already claimed the leaf, or has another claim in escrow
+        if self.is_leaf_claimed(account_id, extras.nonce) || self.escrow.contains_key(account_id) {
+            return Err(ClaimError::AlreadyClaimed);
+        }
+        // All-digit referrers would read as the nonce of another leaf
+        if extras
+            .referrer
+            .as_ref()
+            .is_some_and(|referrer| referrer.as_str().bytes().all(|byte| byte.is_ascii_digit()))
+        {
+            return Err(ClaimError::InvalidProof);
+        }
+        if let Some(expires_at) = extras.expires_at {
+            if env::block_timestamp() >= expires_at.0 {
+                return Err(ClaimError::LeafExpired);
+            }
+        }
+        if let Some(tier) = extras.tier {
+            self.assert_tier_open(tier)?;
+        }
+
+        // Verify the Merkle proof
+        let leaf = self.domain_leaf(extras.encode_leaf(account_id, amount.0));
+        if !Self::verify_merkle_proof_with(
+            self.hash_algorithm,
+            self.leaf_format,
+            leaf,
+            &self.merkle_root,
+            merkle_proof,
+        )? {
+            return Err(ClaimError::InvalidProof);
+        }
+        Ok(())
+    }
+
+    /// A claim paid for by the current caller, with the storage deposit they must attach.
+    fn internal_new_claim(&self, account_id: AccountId, amount: U128) -> PendingClaim {
+        PendingClaim {
+            storage_deposit: U128(
+                self.claimer_storage_deposit
+                    .unwrap_or(NO_DEPOSIT)
+                    .as_yoctonear(),
+            ),
+            badge_deposit: U128(self.badge_deposit().as_yoctonear()),
+            record_deposit: U128(self.record_deposit().as_yoctonear()),
+            unwrap_wnear: self.unwrap_wnear,
+            ..PendingClaim::new(account_id, amount, self.caller_claim_fee())
+        }
+    }
+
+    /// Static gas of `on_storage_deposit_then_transfer` for the transfer legs of `claim`.
+    fn transfer_gas(claim: &PendingClaim) -> Gas {
+        if !claim.split.is_empty() {
+            return Self::split_gas(claim);
+        }
+        let mut transfer_gas = GAS_FOR_TRANSFER_CALLBACK;
+        if claim.treasury_amount() > 0 {
+            transfer_gas = transfer_gas.saturating_add(GAS_FOR_FT_TRANSFER);
+        }
+        if claim.referrer.is_some() {
+            transfer_gas = transfer_gas.saturating_add(GAS_FOR_FT_TRANSFER);
+        }
+        if claim.transfer_call.is_some() || claim.stream_duration.is_some() {
+            // The ft_transfer_call leg, and the transfer of unused tokens by its callback
+            transfer_gas = transfer_gas.saturating_add(GAS_FOR_FT_TRANSFER_CALL);
+        }
+        if claim.unwraps() {
+            transfer_gas = transfer_gas.saturating_add(GAS_FOR_UNWRAP);
+        }
+        if claim.liquid_stake {
+            transfer_gas = transfer_gas.saturating_add(GAS_FOR_LIQUID_STAKING);
+        }
+        if claim.social_deposit.0 > 0 {
+            transfer_gas = transfer_gas.saturating_add(GAS_FOR_SOCIAL_POST);
+        }
+        if claim.badge_deposit.0 > 0 {
+            transfer_gas = transfer_gas.saturating_add(GAS_FOR_BADGE);
+        }
+        if claim.lockup_deposit.0 > 0 {
+            transfer_gas = transfer_gas.saturating_add(GAS_FOR_LOCKUP);
+        }
+        transfer_gas
+    }
+
+    /// Static gas of the callback completing `claim` after its transfer.
+    fn completion_gas(claim: &PendingClaim) -> Gas {
+        let mut completion_gas = Gas::from_tgas(5);
+        if claim.social_deposit.0 > 0 {
+            completion_gas = completion_gas.saturating_add(GAS_FOR_SOCIAL_POST);
+        }
+        if claim.badge_deposit.0 > 0 {
+            completion_gas = completion_gas.saturating_add(GAS_FOR_BADGE);
+        }
+        if claim.lockup_deposit.0 > 0 {
+            completion_gas = completion_gas.saturating_add(GAS_FOR_LOCKUP);
+        }
+        completion_gas
+    }
+
+    /// Batches one storage_deposit per `(account_id, deposit)`, `None` if there are none.
+    fn storage_deposits(
+        token_contract: &AccountId,
+        registrations: Vec<(AccountId, NearToken)>,
+    ) -> Option<Promise> {
+        registrations
+            .into_iter()
+            .fold(None, |promise: Option<Promise>, (account_id, deposit)| {
+                Some(
+                    promise
+                        .unwrap_or_else(|| Promise::new(token_contract.clone()))
+                        .function_call(
+                            "storage_deposit".to_string(),
+                            Self::storage_deposit_args(&account_id),
+                            deposit,
+                            GAS_FOR_STORAGE_DEPOSIT,
+                        ),
+                )
+            })
+    }
+
+    /// Transfers the claimed tokens, batched with the treasury and referrer legs. A locked
+    /// claim without any leg to send completes right away.
+    fn internal_transfer(&mut self, claim: PendingClaim) -> PromiseOrValue<ClaimOutcome> {
+        let mut transfer = Promise::new(self.token_contract.clone());
+        let transfer_call = claim
+            .transfer_call
+            .clone()
+            .or_else(|| self.stream_transfer_call(&claim));
+        let referrer = claim
+            .referrer
+            .clone()
+            .filter(|_| claim.referral_bonus.0 > 0);
+        if transfer_call.is_none()
+            && !claim.unwraps()
+            && claim.lock_months.is_some()
+            && claim.treasury_amount() == 0
+            && referrer.is_none()
+        {
+            return PromiseOrValue::Value(self.internal_complete_claim(claim));
+        }
+        if transfer_call.is_none() && !claim.unwraps() && claim.lock_months.is_none() {
+            transfer = transfer.function_call(
+                "ft_transfer".to_string(),
+                Self::ft_transfer_args(
+                    claim.receiver_id(),
+                    claim.net_amount(),
+                    claim.memo.as_deref(),
+                ),
+                NearToken::from_yoctonear(1),
+                GAS_FOR_FT_TRANSFER,
+            );
+        }
+        // Batched with the claimer's transfer, so all legs succeed or fail together
+        if claim.treasury_amount() > 0 {
+            transfer = transfer.function_call(
+                "ft_transfer".to_string(),
+                Self::ft_transfer_args(&self.treasury_id, claim.treasury_amount(), None),
+                NearToken::from_yoctonear(1),
+                GAS_FOR_FT_TRANSFER,
+            );
+        }
+        if let Some(referrer) = &referrer {
+            transfer = transfer.function_call(
+                "ft_transfer".to_string(),
+                Self::ft_transfer_args(referrer, claim.referral_bonus.0, None),
+                NearToken::from_yoctonear(1),
+                GAS_FOR_FT_TRANSFER,
+            );
+        }
+        if claim.unwraps() {
+            return self.internal_unwrap(transfer, claim).into();
+        }
+        // The ft_transfer_call leg goes last, as the batch returns the result of its last call
+        if let Some(transfer_call) = transfer_call {
+            return transfer
+                .function_call(
+                    "ft_transfer_call".to_string(),
+                    json!({
+                        "receiver_id": transfer_call.receiver_id,
+                        "amount": U128(claim.net_amount()),
+                        "memo": claim.memo,
+                        "msg": transfer_call.msg,
+                    })
+                    .to_string()
+                    .into_bytes(),
+                    NearToken::from_yoctonear(1),
+                    GAS_FOR_FT_TRANSFER_CALL,
+                )
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(
+                            Self::completion_gas(&claim).saturating_add(GAS_FOR_FT_TRANSFER),
+                        )
+                        .on_ft_transfer_call_then_claimed(claim),
+                )
+                .into();
+        }
+        transfer
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Self::completion_gas(&claim))
+                    .on_ft_transfer_then_claimed(claim),
+            )
+            .into()
+    }
+
+    /// Records a successful claim and pays its NEAR fee to the treasury.
+    fn internal_complete_claim(&mut self, claim: PendingClaim) -> ClaimOutcome {
+        env::log_str(&format!(
+            "Account @{} claimed {} tokens from @{}.",
+            claim.account_id, claim.amount.0, self.token_contract
+        ));
+        self.internal_lock_claim(&claim);
+        self.internal_delete_claim_key(&claim);
+        self.reserved = self
+            .reserved
+            .saturating_sub(claim.total_amount().unwrap_or_default());
+        if claim.fee.0 > 0 {
+            Promise::new(self.treasury_id.clone()).transfer(NearToken::from_yoctonear(claim.fee.0));
+        }
+        if claim.social_deposit.0 > 0 {
+            self.internal_post_to_social(&claim);
+        }
+        let receipt_index = self.claim_index.len() as u64;
+        if claim.badge_deposit.0 > 0 {
+            self.internal_mint_badge(&claim, receipt_index);
+        }
+        if claim.lockup_deposit.0 > 0 {
+            self.internal_create_lockup(&claim);
+        }
+        if let Some(referrer) = &claim.referrer {
+            let mut stats = self
+                .referral_stats
+                .get(referrer)
+                .cloned()
+                .unwrap_or_default();
+            stats.referrals += 1;
+            stats.rewards = U128(stats.rewards.0 + claim.referral_bonus.0);
+            self.referral_stats.insert(referrer.clone(), stats);
+        }
+        self.internal_record_streak(&claim);
+        let outcome = ClaimOutcome {
+            status: ClaimStatus::Success,
+            amount: U128(claim.net_amount()),
+            receipt_index: Some(U64(receipt_index)),
+            claimed_at: None,
+        };
+        let storage_usage = env::storage_usage();
+        if claim.claims_leaf() {
+            self.leaf_records.insert(
+                (
+                    self.round,
+                    claim.account_id.clone(),
+                    claim.nonce.map(|nonce| nonce.0),
+                ),
+                receipt_index,
+            );
+        }
+        self.claim_records.insert(
+            receipt_index,
+            ClaimRecord {
+                index: U64(receipt_index),
+                amount: outcome.amount,
+                timestamp: U64(env::block_timestamp()),
+                block_height: U64(env::block_height()),
+                referrer: claim.referrer.clone(),
+                referral_bonus: claim.referral_bonus,
+                badge_token_id: None,
+                round: self.round,
+                nonce: claim.nonce,
+            },
+        );
+        self.internal_count_round_claim(claim.amount);
+        self.claim_index.push(claim.account_id.clone());
+        if claim.record_deposit.0 > 0 {
+            self.internal_charge_record_storage(&claim, storage_usage);
+        }
+        self.claimed_snapshot
+            .push(self.hash_algorithm, claim.account_id.as_bytes());
+        AirdropEvent::Claim(vec![ClaimEvent {
+            account_id: claim.account_id,
+            amount: claim.amount,
+            fee: claim.fee,
+            protocol_fee: claim.protocol_fee,
+            forfeited: claim.forfeited,
+            early_bird_bonus: claim.early_bird_bonus,
+            referrer: claim.referrer,
+            referral_bonus: claim.referral_bonus,
+            memo: claim.memo,
+        }])
+        .emit();
+        outcome
+    }
+
+    /// Part of a leaf `amount` still claimable under the decay schedule.
+    fn internal_decayed_amount(&self, amount: u128) -> u128 {
+        match &self.decay_schedule {
+            Some(schedule) => apply_bps(amount, schedule.claimable_bps(env::block_timestamp())),
+            None => amount,
+        }
+    }
+
+    fn storage_deposit_args(account_id: &AccountId) -> Vec<u8> {
+        json!({
+            "account_id": account_id,
+            "registration_only": true
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn ft_transfer_args(receiver_id: &AccountId, amount: u128, memo: Option<&str>) -> Vec<u8> {
+        json!({
+            "receiver_id": receiver_id,
+            "amount": U128(amount),
+            "memo": memo,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    /// Reverts a failed claim: the account can claim again, or the raffle winner or claim key
+    /// claim the prize or key again, reserved bonuses return
+    /// to their pool and the claimer's deposit is refunded. The failure is counted in
+    /// the health counters and emitted as a `claim_failed` event.
+    fn internal_rollback(&mut self, claim: &PendingClaim, status: ClaimStatus) -> Option<Promise> {
+        if claim.claims_leaf() {
+            self.internal_unmark_leaf_claimed(&claim.account_id, claim.nonce);
+        }
+        if claim.raffle_prize {
+            self.raffle_winners
+                .insert(claim.account_id.clone(), claim.amount);
+        }
+        self.internal_restore_claim_key(claim);
+        self.internal_revert_shortfall(claim);
+        self.internal_release_reservation(claim);
+        self.internal_record_failure(&claim.account_id, claim.amount, status);
+        self.internal_refund(claim)
+    }
+
+    /// Counts a failed claim in the health counters and emits a `claim_failed` event.
+    fn internal_record_failure(
+        &mut self,
+        account_id: &AccountId,
+        amount: U128,
+        status: ClaimStatus,
+    ) {
+        match status {
+            ClaimStatus::StorageFailed => self.health.storage_deposit_failures += 1,
+            ClaimStatus::TransferFailed => self.health.transfer_failures += 1,
+            _ => {}
+        }
+        self.health.rolled_back_claims += 1;
+        self.health.last_failure_at = Some(U64(env::block_timestamp()));
+        AirdropEvent::ClaimFailed(vec![ClaimFailedEvent {
+            account_id: account_id.clone(),
+            amount,
+            status,
+        }])
+        .emit();
+    }
+
+    /// Tokens the contract still owes to claimers and must not sweep or withdraw: reserved
+    /// and locked claims, shortfalls and the bonus pools.
+    fn owed_tokens(&self) -> u128 {
+        [
+            self.locked,
+            self.shortfall_total,
+            self.bonus_pool,
+            self.lock_bonus_pool,
+            self.streak_bonus_pool,
+        ]
+        .into_iter()
+        .fold(self.reserved, u128::saturating_add)
+    }
+
+    /// Counts tokens leaving the contract outside of claims.
+    fn internal_count_withdrawal(&mut self, amount: u128) {
+        self.withdrawn = self
+            .withdrawn
+            .checked_add(amount)
+            .unwrap_or_else(|| env::panic_str("Accounting overflow"));
+    }
+
+    /// Returns the bonuses and the distribution quota reserved by a claim that is not paid out.
+    fn internal_release_reservation(&mut self, claim: &PendingClaim) {
+        let total_amount = claim.total_amount().unwrap_or_default();
+        self.internal_restore_bonuses(claim);
+        self.distributed = self.distributed.saturating_sub(total_amount);
+        self.reserved = self.reserved.saturating_sub(total_amount);
+        if claim.pays_leaf()
+            && claim
+                .root_version
+                .is_none_or(|version| version == self.root_version)
+        {
+            self.root_claimed = self.root_claimed.saturating_sub(claim.amount.0);
+        }
+    }
+
+    /// Reverts `claim` if the Merkle root changed since it was verified. Returns whether it did.
+    fn internal_check_root_version(&mut self, claim: &PendingClaim) -> bool {
+        if claim
+            .root_version
+            .is_none_or(|version| version == self.root_version)
+        {
+            return false;
+        }
+        log!(
+            "Merkle root changed since the claim of @{} was verified",
+            claim.account_id
+        );
+        self.internal_rollback(claim, ClaimStatus::RootChanged);
+        true
+    }
+
+    /// Counts `amount` against the total declared with the current root.
+    fn internal_count_root_claim(&mut self, amount: u128) -> Result<(), ClaimError> {
+        let root_claimed = self
+            .root_claimed
+            .checked_add(amount)
+            .ok_or(ClaimError::AmountOverflow)?;
+        if self
+            .root_totals
+            .as_ref()
+            .is_some_and(|totals| root_claimed > totals.total_amount.0)
+        {
+            return Err(ClaimError::RootTotalExceeded);
+        }
+        self.root_claimed = root_claimed;
+        Ok(())
+    }
+
+    /// Charges the storage written since `storage_usage` to the record deposit of `claim`, and
+    /// refunds the excess to its claimer.
+    fn internal_charge_record_storage(&mut self, claim: &PendingClaim, storage_usage: u64) {
+        // Write the cached entries so that their storage is measured
+        self.claim_records.flush();
+        self.leaf_records.flush();
+        self.claim_index.flush();
+        let bytes = env::storage_usage().saturating_sub(storage_usage);
+        let cost = env::storage_byte_cost()
+            .saturating_mul(bytes.into())
+            .as_yoctonear();
+        let refund = claim.record_deposit.0.saturating_sub(cost);
+        log!(
+            "Claim record of @{} stored in {} bytes, refunding {} yoctoNEAR",
+            claim.account_id,
+            bytes,
+            refund
+        );
+        if refund > 0 {
+            Promise::new(claim.claimer.clone()).transfer(NearToken::from_yoctonear(refund));
+        }
+    }
+
+    /// Returns the deposit attached to a failed claim to the account that paid it.
+    fn internal_refund(&self, claim: &PendingClaim) -> Option<Promise> {
+        if claim.deposit.0 == 0 {
+            return None;
+        }
+        log!(
+            "Refunding {} yoctoNEAR to @{}",
+            claim.deposit.0,
+            claim.claimer
+        );
+        Some(
+            Promise::new(claim.claimer.clone())
+                .transfer(NearToken::from_yoctonear(claim.deposit.0)),
+        )
+    }
+}
+
+/// Share of `amount` corresponding to `bps` basis points, rounded down.
+fn apply_bps(amount: u128, bps: u16) -> u128 {
+    let bps = u128::from(bps);
+    amount / BPS_DENOMINATOR * bps + amount % BPS_DENOMINATOR * bps / BPS_DENOMINATOR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::get_context;
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::get_logs;
+    use near_sdk::{testing_env, AccountId, Gas};
+
+    // Constants for testing
+    const TOKEN_CONTRACT: &str = "token.testnet";
+    const OWNER: &str = "owner.testnet";
+    const USER1: &str = "user1.testnet";
+
+    const ONE_YOCTO: NearToken = NearToken::from_yoctonear(1);
+
+    /// Contract owned by `OWNER` airdropping `TOKEN_CONTRACT`, with the default test root.
+    fn new_contract() -> AirdropContract {
+        new_contract_with_root(
+            "42bb039d55571a5564e772449aab51904f292f69ea5efb6becde8f8f5c37d643".to_string(),
+        )
+    }
+
+    /// Contract owned by `OWNER` airdropping `TOKEN_CONTRACT`, with `merkle_root`.
+    fn new_contract_with_root(merkle_root: String) -> AirdropContract {
+        AirdropContract::new(
+            OWNER.parse().unwrap(),
+            TOKEN_CONTRACT.parse().unwrap(),
+            merkle_root,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_merkle_proof_verification() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), NO_DEPOSIT);
+        testing_env!(context.build());
+
+        new_contract_with_root(
+            "af6df487c9daa2c7d6ec7fb9a33f22d6af13323c1f0d9b1a7df3ec0aaea02e94".to_string(),
+        );
+
+        // Example Merkle proof for "user1.testnet + : + 100"
+        let leaf = "user1.testnet:100".to_string();
+        let proof = vec![
+            "154a0a614231d830d36a51e980c0cb836e8d2d718345e6c5e0e10bb3687ddb99".to_string(),
+            "86b99e84ab1b07c73445edf731d9c0d876c6229e36a5bf22c210690e2cdc18b2".to_string(),
+        ];
+
+        let valid = AirdropContract::verify_merkle_proof(
+            leaf,
+            "af6df487c9daa2c7d6ec7fb9a33f22d6af13323c1f0d9b1a7df3ec0aaea02e94",
+            &proof,
+        )
+        .unwrap();
+
+        assert!(valid, "Merkle proof should be valid for user1.testnet.");
+    }
+
+    #[test]
+    fn test_merkle_tree() {
+        let entries: Vec<(AccountId, u128)> = vec![
+            (USER1.parse().unwrap(), 100),
+            (OWNER.parse().unwrap(), 200),
+            ("user3.testnet".parse().unwrap(), 300),
+        ];
+        for (hash_algorithm, leaf_format) in [
+            (HashAlgorithm::Keccak256, LeafFormat::V1),
+            (HashAlgorithm::Sha256, LeafFormat::V2),
+        ] {
+            let tree = MerkleTree::from_entries_with(hash_algorithm, leaf_format, entries.clone());
+            for (index, (account_id, amount)) in entries.iter().enumerate() {
+                let proof = tree.proof(index).unwrap();
+                assert_eq!(tree.proof_for(account_id), Some(proof.clone()));
+                assert!(AirdropContract::verify_merkle_proof_with(
+                    hash_algorithm,
+                    leaf_format,
+                    format!("{}:{}", account_id, amount),
+                    &tree.root(),
+                    &proof,
+                )
+                .unwrap());
+            }
+            assert_eq!(tree.proof(3), None);
+        }
+
+        let single = MerkleTree::from_entries(vec![(USER1.parse().unwrap(), 100)]);
+        assert_eq!(single.proof(0), Some(vec![]));
+        assert_eq!(
+            single.root(),
+            hex::encode(env::keccak256(b"user1.testnet:100"))
+        );
+    }
+
+    #[test]
+    fn test_merkle_proof_verification_sha256() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), NO_DEPOSIT);
+        testing_env!(context.build());
+
+        let root = "c39ebd0143e99842b45768ba3cdc907ee9e86f5da52bb5375adb305f337bcd8e".to_string();
+        let proof = vec![
+            "1ac52cfc1256f1b626cfd9e7f5378d8765aec67dd55b042d81cd87ce381e48e4".to_string(),
+            "b5c78870685e0b2940d4412458118fdda449f5a5c25134b777a8fdc6b16814a8".to_string(),
+        ];
+
+        assert!(AirdropContract::verify_merkle_proof_with(
+            HashAlgorithm::Sha256,
+            LeafFormat::V1,
+            "user1.testnet:100".to_string(),
+            &root,
+            &proof,
+        )
+        .unwrap());
+        assert!(!AirdropContract::verify_merkle_proof_with(
+            HashAlgorithm::Keccak256,
+            LeafFormat::V1,
+            "user1.testnet:100".to_string(),
+            &root,
+            &proof,
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_merkle_proof_verification_domain_separated() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), NO_DEPOSIT);
+        testing_env!(context.build());
+
+        let root = "f9722140c7a10c6e80f3577b84529a7b8b55a5113e165fbb13c3566d64b1280f".to_string();
+        let proof = vec![
+            "3a4adc2b7e56691d06d6b351cf053f1d6281a5ee0c395ce83ef49933a24a094a".to_string(),
+            "3eaceab88820870290a988568c0818f5d8e71911f4b29880465bd6ff5d9fc88c".to_string(),
+        ];
+
+        assert!(AirdropContract::verify_merkle_proof_with(
+            HashAlgorithm::Sha256,
+            LeafFormat::V2,
+            "user1.testnet:100".to_string(),
+            &root,
+            &proof,
+        )
+        .unwrap());
+        // The same tree must not verify under the legacy scheme.
+        assert!(!AirdropContract::verify_merkle_proof_with(
+            HashAlgorithm::Sha256,
+            LeafFormat::V1,
+            "user1.testnet:100".to_string(),
+            &root,
+            &proof,
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_merkle_multiproof_verification() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), NO_DEPOSIT);
+        testing_env!(context.build());
+
+        let root = "31c6c0b4377d91872eba7c899e8dfe05887c4035afd34baa20a577fb918c8282".to_string();
+        let leaves = vec![
+            "user1.testnet:100".to_string(),
+            "user1.testnet:50".to_string(),
+        ];
+        let proof =
+            vec!["070693ec438c553b2b35c13d1f7537b2e9545d137b4f848974991525f5c44ba8".to_string()];
+
+        assert!(AirdropContract::verify_merkle_multiproof_with(
+            HashAlgorithm::Sha256,
+            LeafFormat::V1,
+            leaves.clone(),
+            &root,
+            &proof,
+            &[true, false],
+        )
+        .unwrap());
+        assert!(!AirdropContract::verify_merkle_multiproof_with(
+            HashAlgorithm::Sha256,
+            LeafFormat::V1,
+            leaves,
+            &root,
+            &proof,
+            &[false, true],
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_distribute_skips_claimed_accounts() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+
+        let processed = contract.distribute(vec![
+            (USER1.parse::<AccountId>().unwrap(), U128(100)),
+            (USER1.parse::<AccountId>().unwrap(), U128(100)),
+        ]);
+        assert_eq!(processed, 2);
+        assert!(contract.has_claimed(USER1.parse::<AccountId>().unwrap()));
+    }
+
+    #[test]
+    fn test_distribute_respects_bonuses_and_holds() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_timestamp(1_000);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_early_bird_bonus(
+            Some(EarlyBirdBonus {
+                bonus_bps: 1_000,
+                ends_at: U64(2_000),
+            }),
+            U128(100),
+        );
+        contract.set_distribution_cap(Some(U128(150)));
+        contract.place_legal_hold(USER1.parse().unwrap());
+
+        contract.distribute(vec![(USER1.parse().unwrap(), U128(100))]);
+        assert_eq!(contract.get_bonus_pool(), U128(100));
+        assert_eq!(contract.get_distributed(), U128(100));
+        let held = contract.get_held_claim(USER1.parse().unwrap()).unwrap();
+        assert!(held.pushed);
+        assert_eq!(held.net_amount(), 100);
+
+        // A failed reservation returns its bonus to the pool
+        let claim = PendingClaim::new(OWNER.parse().unwrap(), U128(100), NO_DEPOSIT);
+        assert_eq!(
+            contract.internal_reserve_claim(claim).err(),
+            Some(ClaimError::CapExceeded)
+        );
+        assert_eq!(contract.get_bonus_pool(), U128(100));
+        contract.distribute(vec![(OWNER.parse().unwrap(), U128(100))]);
+        assert_eq!(contract.get_distributed(), U128(100));
+        assert!(!contract.has_claimed(OWNER.parse().unwrap()));
+    }
+
+    #[test]
+    fn test_dao_owner_without_deposit() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+        contract.set_admin_deposit_required(false);
+
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), NO_DEPOSIT);
+        testing_env!(context.build());
+        contract.update_merkle_root(
+            "af6df487c9daa2c7d6ec7fb9a33f22d6af13323c1f0d9b1a7df3ec0aaea02e94".to_string(),
+            U128(1_000),
+            10,
+            None,
+        );
+        assert_eq!(
+            contract.get_merkle_root(),
+            "af6df487c9daa2c7d6ec7fb9a33f22d6af13323c1f0d9b1a7df3ec0aaea02e94"
+        );
+
+        let payload = contract.get_proposal_payloads(
+            "Rotate root".to_string(),
+            vec![(
+                "update_merkle_root".to_string(),
+                json!({ "merkle_root": "00" }),
+            )],
+        );
+        assert_eq!(
+            payload["proposal"]["kind"]["FunctionCall"]["actions"][0]["deposit"],
+            "0"
+        );
+    }
+
+    #[test]
+    fn test_stage_code() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+        assert!(contract.get_staged_code_hash().is_none());
+
+        let code = vec![0u8, 97, 115, 109];
+        contract.stage_code(code.clone());
+        let code_hash = Base58CryptoHash::from(env::sha256_array(&code));
+        assert_eq!(contract.get_staged_code_hash(), Some(code_hash));
+
+        contract.deploy_staged_code(code_hash);
+        assert!(contract.get_staged_code_hash().is_none());
+    }
+
+    #[test]
+    fn test_claim_errors() {
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+
+        assert_eq!(
+            contract
+                .claim_airdrop(
+                    U128(100),
+                    vec!["not hex".to_string()],
+                    None,
+                    None,
+                    None,
+                    None,
+                    None
+                )
+                .err(),
+            Some(ClaimError::InvalidProofEncoding)
+        );
+        assert_eq!(
+            contract
+                .claim_airdrop(
+                    U128(100),
+                    vec!["abcd".to_string()],
+                    None,
+                    None,
+                    None,
+                    None,
+                    None
+                )
+                .err(),
+            Some(ClaimError::InvalidProofEncoding)
+        );
+        assert_eq!(
+            contract
+                .claim_airdrop(
+                    U128(100),
+                    vec!["00".repeat(32); 65],
+                    None,
+                    None,
+                    None,
+                    None,
+                    None
+                )
+                .err(),
+            Some(ClaimError::ProofTooLong)
+        );
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(0), vec![], None, None, None, None, None)
+                .err(),
+            Some(ClaimError::ZeroAmount)
+        );
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], None, None, None, None, None)
+                .err(),
+            Some(ClaimError::InvalidProof)
+        );
+        assert_eq!(
+            ClaimError::AlreadyClaimed.to_string(),
+            "ERR_ALREADY_CLAIMED: You have already claimed your airdrop."
+        );
+    }
+
+    #[test]
+    fn test_failed_transfer_refunds_claimer() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), NO_DEPOSIT);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+        let claim = PendingClaim {
+            account_id: USER1.parse::<AccountId>().unwrap(),
+            amount: U128(100),
+            claimer: USER1.parse::<AccountId>().unwrap(),
+            deposit: U128(1),
+            storage_deposit: U128(0),
+            fee: U128(0),
+            protocol_fee: U128(0),
+            forfeited: U128(0),
+            early_bird_bonus: U128(0),
+            referrer: None,
+            referral_bonus: U128(0),
+            memo: None,
+            transfer_call: None,
+            receiver_id: None,
+            social_deposit: U128(0),
+            badge_deposit: U128(0),
+            record_deposit: U128(0),
+            shortfall: U128(0),
+            pays_shortfall: false,
+            nonce: None,
+            root_version: None,
+            liquid_stake: false,
+            unwrap_wnear: false,
+            stream_duration: None,
+            lockup_duration: None,
+            lockup_deposit: U128(0),
+            lock_months: None,
+            lock_bonus: U128(0),
+            streak_bonus: U128(0),
+            pushed: false,
+            claim_key: None,
+            raffle_prize: false,
+            split: Vec::new(),
+        };
+        contract.claimed.insert(claim.account_id.clone());
+
+        assert_eq!(
+            contract
+                .on_ft_transfer_then_claimed(claim.clone(), Err(near_sdk::PromiseError::Failed))
+                .status,
+            ClaimStatus::TransferFailed
+        );
+        assert!(!contract.has_claimed(claim.account_id));
+        let health = contract.get_health();
+        assert_eq!(health.transfer_failures, 1);
+        assert_eq!(health.rolled_back_claims, 1);
+        assert!(get_logs()
+            .iter()
+            .any(|log| log.contains(r#""event":"claim_failed""#)));
+        assert!(get_logs().contains(&"Refunding 1 yoctoNEAR to @user1.testnet".to_string()));
+    }
+
+    #[test]
+    fn test_claim_fee() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+        contract.set_claim_fee(NearToken::from_millinear(10));
+
+        // Only 1 yoctoNEAR attached, the fee is missing
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], None, None, None, None, None)
+                .err(),
+            Some(ClaimError::InvalidDeposit)
+        );
+
+        let fee = NearToken::from_millinear(10).as_yoctonear();
+        let claim = PendingClaim {
+            account_id: USER1.parse::<AccountId>().unwrap(),
+            amount: U128(100),
+            claimer: USER1.parse::<AccountId>().unwrap(),
+            deposit: U128(fee + 1),
+            storage_deposit: U128(0),
+            fee: U128(fee),
+            protocol_fee: U128(0),
+            forfeited: U128(0),
+            early_bird_bonus: U128(0),
+            referrer: None,
+            referral_bonus: U128(0),
+            memo: None,
+            transfer_call: None,
+            receiver_id: None,
+            social_deposit: U128(0),
+            badge_deposit: U128(0),
+            record_deposit: U128(0),
+            shortfall: U128(0),
+            pays_shortfall: false,
+            nonce: None,
+            root_version: None,
+            liquid_stake: false,
+            unwrap_wnear: false,
+            stream_duration: None,
+            lockup_duration: None,
+            lockup_deposit: U128(0),
+            lock_months: None,
+            lock_bonus: U128(0),
+            streak_bonus: U128(0),
+            pushed: false,
+            claim_key: None,
+            raffle_prize: false,
+            split: Vec::new(),
+        };
+        assert_eq!(
+            contract.on_ft_transfer_then_claimed(claim, Ok(())).status,
+            ClaimStatus::Success
+        );
+        let logs = get_logs();
+        let event = logs.last().unwrap();
+        assert!(event.starts_with("EVENT_JSON:"));
+        assert!(event.contains(r#""event":"claim""#));
+        assert!(event.contains(&format!(r#""fee":"{}""#, fee)));
+    }
+
+    #[test]
+    fn test_claim_memo() {
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+        let args = AirdropContract::ft_transfer_args(
+            &USER1.parse::<AccountId>().unwrap(),
+            100,
+            Some("deposit 42"),
+        );
+        let args: serde_json::Value = serde_json::from_slice(&args).unwrap();
+        assert_eq!(args["memo"], "deposit 42");
+
+        let claim = PendingClaim {
+            memo: Some("deposit 42".to_string()),
+            ..PendingClaim::new(USER1.parse::<AccountId>().unwrap(), U128(100), NO_DEPOSIT)
+        };
+        assert_eq!(
+            contract.on_ft_transfer_then_claimed(claim, Ok(())).status,
+            ClaimStatus::Success
+        );
+        assert!(get_logs()
+            .last()
+            .unwrap()
+            .contains(r#""memo":"deposit 42""#));
+    }
+
+    #[test]
+    fn test_claimer_pays_storage() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+        contract.on_storage_balance_bounds(Ok(StorageBalanceBounds {
+            min: U128(1_000),
+            max: None,
+        }));
+        assert_eq!(
+            contract.get_claimer_storage_deposit(),
+            Some(NearToken::from_yoctonear(1_000))
+        );
+
+        // The storage deposit must be attached on top of the 1 yoctoNEAR
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], None, None, None, None, None)
+                .err(),
+            Some(ClaimError::InvalidDeposit)
+        );
+        let context = get_context(
+            USER1.parse::<AccountId>().unwrap(),
+            NearToken::from_yoctonear(1_001),
+        );
+        testing_env!(context.build());
+        let claim = contract.internal_new_claim(USER1.parse::<AccountId>().unwrap(), U128(100));
+        assert_eq!(claim.deposit, U128(1_001));
+        assert_eq!(claim.storage_deposit, U128(1_000));
+
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        contract.set_claimer_pays_storage(false);
+        assert_eq!(contract.get_claimer_storage_deposit(), None);
+    }
+
+    #[test]
+    fn test_claim_and_stake_returns_unused_tokens() {
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+        assert_eq!(
+            contract
+                .claim_and_stake(U128(100), vec![], None, "pool".to_string())
+                .err(),
+            Some(ClaimError::StakingUnavailable)
+        );
+
+        let claim = PendingClaim {
+            transfer_call: Some(TransferCall {
+                receiver_id: "staking.testnet".parse().unwrap(),
+                msg: "{}".to_string(),
+            }),
+            ..PendingClaim::new(USER1.parse::<AccountId>().unwrap(), U128(100), NO_DEPOSIT)
+        };
+        assert_eq!(
+            contract
+                .on_ft_transfer_call_then_claimed(claim, Ok(U128(60)))
+                .status,
+            ClaimStatus::Success
+        );
+        let logs = get_logs();
+        assert!(logs.contains(&"Returning 40 unused tokens to @user1.testnet".to_string()));
+        assert!(contract
+            .get_claim_record(USER1.parse::<AccountId>().unwrap(), None, None)
+            .is_some());
+    }
+
+    #[test]
+    fn test_claim_and_liquid_stake() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = AirdropContract::new(
+            OWNER.parse::<AccountId>().unwrap(),
+            "wrap.testnet".parse::<AccountId>().unwrap(),
+            "42bb039d55571a5564e772449aab51904f292f69ea5efb6becde8f8f5c37d643".to_string(),
+            None,
+            None,
+        );
+        assert_eq!(
+            contract
+                .claim_and_liquid_stake(U128(100), vec![], None)
+                .err(),
+            Some(ClaimError::LiquidStakingUnavailable)
+        );
+        contract.set_liquid_staking_contract(Some("meta-v2.pool.testnet".parse().unwrap()));
+
+        let claim = PendingClaim {
+            liquid_stake: true,
+            ..PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT)
+        };
+        assert_eq!(
+            AirdropContract::transfer_gas(&claim),
+            GAS_FOR_TRANSFER_CALLBACK
+                .saturating_add(GAS_FOR_UNWRAP)
+                .saturating_add(GAS_FOR_LIQUID_STAKING)
+        );
+        assert!(matches!(
+            contract.on_unwrapped(claim.clone(), Ok(())),
+            PromiseOrValue::Promise(_)
+        ));
+        assert!(matches!(
+            contract.on_unwrapped(claim, Err(near_sdk::PromiseError::Failed)),
+            PromiseOrValue::Value(ClaimOutcome {
+                status: ClaimStatus::TransferFailed,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only wNEAR claims can be liquid staked")]
+    fn test_liquid_staking_requires_wnear() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_liquid_staking_contract(Some("meta-v2.pool.testnet".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_unwrap_wnear() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = AirdropContract::new(
+            OWNER.parse::<AccountId>().unwrap(),
+            "wrap.testnet".parse::<AccountId>().unwrap(),
+            "42bb039d55571a5564e772449aab51904f292f69ea5efb6becde8f8f5c37d643".to_string(),
+            None,
+            None,
+        );
+        assert!(!contract
+            .internal_new_claim(USER1.parse().unwrap(), U128(100))
+            .unwraps());
+        contract.set_unwrap_wnear(true);
+        assert!(contract.get_unwrap_wnear());
+
+        let claim = contract.internal_new_claim(USER1.parse().unwrap(), U128(100));
+        assert!(claim.unwraps());
+        assert_eq!(
+            AirdropContract::transfer_gas(&claim),
+            GAS_FOR_TRANSFER_CALLBACK.saturating_add(GAS_FOR_UNWRAP)
+        );
+        // Transfer calls keep sending wNEAR
+        let transfer_call = PendingClaim {
+            transfer_call: Some(TransferCall {
+                receiver_id: "exchange.testnet".parse().unwrap(),
+                msg: String::new(),
+            }),
+            ..claim.clone()
+        };
+        assert!(!transfer_call.unwraps());
+
+        assert!(matches!(
+            contract.on_unwrapped(claim, Ok(())),
+            PromiseOrValue::Value(ClaimOutcome {
+                status: ClaimStatus::Success,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "The airdropped token is not wNEAR")]
+    fn test_unwrap_requires_wnear() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_unwrap_wnear(true);
+    }
+
+    #[test]
+    fn test_claim_and_swap() {
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        assert_eq!(
+            contract
+                .claim_and_swap(U128(100), vec![], None, 7, U128(95))
+                .err(),
+            Some(ClaimError::SwapUnavailable)
+        );
+
+        let swap_route = SwapRoute {
+            exchange_id: "ref.testnet".parse().unwrap(),
+            token_out: "usdc.testnet".parse().unwrap(),
+        };
+        let msg = contract.swap_msg(&swap_route, 7, U128(95), &USER1.parse().unwrap());
+        assert_eq!(
+            near_sdk::serde_json::from_str::<near_sdk::serde_json::Value>(&msg).unwrap(),
+            json!({
+                "actions": [{
+                    "pool_id": 7,
+                    "token_in": "token.testnet",
+                    "token_out": "usdc.testnet",
+                    "min_amount_out": "95",
+                }],
+                "swap_out_recipient": "user1.testnet",
+            })
+        );
+    }
+
+    #[test]
+    fn test_transfer_call_payout_msg_allowlist() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        assert_eq!(
+            contract
+                .claim_airdrop(
+                    U128(100),
+                    vec![],
+                    None,
+                    None,
+                    Some("{}".to_string()),
+                    None,
+                    None
+                )
+                .err(),
+            Some(ClaimError::MsgNotAllowed)
+        );
+
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        contract.set_transfer_call_payout(Some(TransferCallPayout {
+            msg: String::new(),
+            allowed_msgs: vec!["deposit".to_string()],
+        }));
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        assert_eq!(
+            contract
+                .claim_airdrop(
+                    U128(100),
+                    vec![],
+                    None,
+                    None,
+                    Some("swap".to_string()),
+                    None,
+                    None
+                )
+                .err(),
+            Some(ClaimError::MsgNotAllowed)
+        );
+        // An allowed message passes on to the proof check
+        assert_eq!(
+            contract
+                .claim_airdrop(
+                    U128(100),
+                    vec![],
+                    None,
+                    None,
+                    Some("deposit".to_string()),
+                    None,
+                    None
+                )
+                .err(),
+            Some(ClaimError::InvalidProof)
+        );
+    }
+
+    #[test]
+    fn test_eligibility_gates() {
+        let gate = EligibilityGate::NftHolder {
+            collection: "og.testnet".parse().unwrap(),
+        };
+        assert!(gate.passed(br#"[{"token_id":"1","owner_id":"user1.testnet"}]"#));
+        assert!(!gate.passed(b"[]"));
+        assert!(!gate.passed(b"null"));
+
+        let gate = EligibilityGate::Human {
+            registry: "registry.i-am-human.testnet".parse().unwrap(),
+        };
+        assert!(gate.passed(br#"[["fractal.i-am-human.testnet",[1]]]"#));
+        assert!(!gate.passed(b"[]"));
+
+        let gate = EligibilityGate::Verified {
+            registry: "kyc.testnet".parse().unwrap(),
+        };
+        assert!(gate.passed(b"true"));
+        assert!(!gate.passed(b"false"));
+
+        let gate = EligibilityGate::TokenHolder {
+            token: "gov.testnet".parse().unwrap(),
+            min_balance: U128(1_000),
+        };
+        assert!(gate.passed(br#""1000""#));
+        assert!(!gate.passed(br#""999""#));
+        assert!(!gate.passed(b"null"));
+
+        let gate = EligibilityGate::Staker {
+            pools: vec![
+                "astro-stakers.poolv1.testnet".parse().unwrap(),
+                "legends.pool.testnet".parse().unwrap(),
+            ],
+        };
+        assert_eq!(gate.checks(), 2);
+        assert!(gate.passed(br#""1""#));
+        assert!(!gate.passed(br#""0""#));
+    }
+
+    #[test]
+    #[should_panic(expected = "A staker gate needs at least one staking pool")]
+    fn test_staker_gate_requires_pools() {
+        testing_env!(get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO).build());
+        let mut contract = new_contract();
+        contract.set_eligibility_gates(vec![EligibilityGate::Staker { pools: vec![] }]);
+    }
+
+    #[test]
+    fn test_usd_pricing() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_timestamp(2_000);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+        contract.set_usd_pricing(Some(UsdPricing {
+            oracle_id: "priceoracle.testnet".parse().unwrap(),
+            asset_id: "token.testnet".to_string(),
+            max_price_age: U64(500),
+        }));
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], None, None, None, None, None)
+                .err(),
+            Some(ClaimError::WrongDenomination)
+        );
+
+        // 1 token with 18 decimals is worth $2
+        let price_data = |timestamp| PriceData {
+            timestamp: U64(timestamp),
+            prices: vec![AssetOptionalPrice {
+                asset_id: "token.testnet".to_string(),
+                price: Some(Price {
+                    multiplier: U128(20_000),
+                    decimals: 22,
+                }),
+            }],
+        };
+        let claim = PendingClaim::new(USER1.parse::<AccountId>().unwrap(), U128(5_000), NO_DEPOSIT);
+        let priced = contract
+            .internal_price_claim(claim.clone(), None, Some(price_data(1_600)))
+            .unwrap();
+        assert_eq!(priced.amount, U128(25 * 10u128.pow(18)));
+        assert_eq!(
+            contract
+                .internal_price_claim(claim.clone(), None, Some(price_data(1_000)))
+                .err(),
+            Some(ClaimError::PriceUnavailable)
+        );
+        assert_eq!(
+            contract
+                .internal_price_claim(
+                    claim,
+                    Some(U128(26 * 10u128.pow(18))),
+                    Some(price_data(1_600))
+                )
+                .err(),
+            Some(ClaimError::SlippageExceeded)
+        );
+    }
+
+    #[test]
+    fn test_raffle_draw() {
+        let seed = [7u8; 32];
+        let swaps = raffle::draw_swaps(&seed, 10, 3);
+        assert_eq!(swaps.len(), 3);
+        for (i, j) in &swaps {
+            assert!(i <= j && *j < 10);
+        }
+        // The draw only depends on the seed
+        assert_eq!(swaps, raffle::draw_swaps(&seed, 10, 3));
+        assert_eq!(raffle::draw_swaps(&seed, 2, 3).len(), 2);
+
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_timestamp(1_000).random_seed(seed);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.start_raffle(U64(1_000), 1, U128(500));
+        assert_eq!(
+            contract.register_for_raffle(U128(100), vec![], None).err(),
+            Some(ClaimError::RaffleClosed)
+        );
+        contract.raffle_entered.insert(USER1.parse().unwrap());
+        contract.raffle_entrants.push(USER1.parse().unwrap());
+        contract.draw_raffle();
+        assert_eq!(
+            contract.get_raffle_winners(),
+            vec![USER1.parse::<AccountId>().unwrap()]
+        );
+        assert_eq!(
+            contract.get_raffle_prize(USER1.parse().unwrap()),
+            Some(U128(500))
+        );
+    }
+
+    #[test]
+    fn test_raffle_prize_apart_from_leaf() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let user1 = USER1.parse::<AccountId>().unwrap();
+        contract.claimed.insert(user1.clone());
+        contract.raffle_winners.insert(user1.clone(), U128(500));
+
+        // A winner who claimed its leaf still gets the prize, once
+        testing_env!(get_context(user1.clone(), ONE_YOCTO).build());
+        assert!(contract.claim_raffle_prize().is_ok());
+        assert_eq!(contract.get_raffle_prize(user1.clone()), None);
+        assert_eq!(
+            contract.claim_raffle_prize().err(),
+            Some(ClaimError::NotAWinner)
+        );
+
+        // A failed payout gives the prize back without touching the leaf
+        let claim = PendingClaim {
+            raffle_prize: true,
+            ..PendingClaim::new(user1.clone(), U128(500), NO_DEPOSIT)
+        };
+        contract.internal_rollback(&claim, ClaimStatus::TransferFailed);
+        assert_eq!(contract.get_raffle_prize(user1.clone()), Some(U128(500)));
+        assert!(contract.is_claimed(&user1));
+    }
+
+    #[test]
+    fn test_pro_rata_share() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_timestamp(1_000);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.start_pro_rata(U128(1_000), U64(2_000));
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(1), vec![], None, None, None, None, None)
+                .err(),
+            Some(ClaimError::WrongDenomination)
+        );
+        assert_eq!(
+            contract.claim_pro_rata().err(),
+            Some(ClaimError::RegistrationOpen)
+        );
+
+        let pro_rata = ProRata {
+            pool: U128(1_000),
+            registration_ends_at: U64(2_000),
+            total_weight: U128(3),
+            registrants: 2,
+        };
+        assert_eq!(pro_rata.share(1), Some(333));
+        assert_eq!(pro_rata.share(2), Some(666));
+        assert_eq!(
+            ProRata {
+                total_weight: U128(0),
+                ..pro_rata
+            }
+            .share(1),
+            None
+        );
+    }
+
+    #[test]
+    fn test_weight_classes() {
+        testing_env!(get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO).build());
+        let mut contract = new_contract();
+        contract.set_weight_classes(vec![
+            WeightClass {
+                min_weight: U128(10),
+                amount: U128(100),
+            },
+            WeightClass {
+                min_weight: U128(50),
+                amount: U128(1_000),
+            },
+        ]);
+        assert_eq!(contract.get_weighted_amount(U128(9)), None);
+        assert_eq!(contract.get_weighted_amount(U128(10)), Some(U128(100)));
+        assert_eq!(contract.get_weighted_amount(U128(49)), Some(U128(100)));
+        assert_eq!(contract.get_weighted_amount(U128(500)), Some(U128(1_000)));
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(50), vec![], None, None, None, None, None)
+                .err(),
+            Some(ClaimError::WrongDenomination)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Claims have started, the weight classes can no longer change")]
+    fn test_weight_classes_frozen_after_first_claim() {
+        testing_env!(get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO).build());
+        let mut contract = new_contract();
+        contract.distributed = 100;
+        contract.set_weight_classes(vec![]);
+    }
+
+    #[test]
+    fn test_escrowed_claim() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_timestamp(1_000);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_escrow_period(Some(U64(500)));
+
+        let account_id = USER1.parse::<AccountId>().unwrap();
+        let claim = PendingClaim::new(account_id.clone(), U128(100), NO_DEPOSIT);
+        assert!(matches!(
+            contract.internal_start_claim(claim),
+            Ok(PromiseOrValue::Value(ClaimOutcome {
+                status: ClaimStatus::Escrowed,
+                ..
+            }))
+        ));
+        assert!(contract.has_claimed(account_id.clone()));
+        assert_eq!(
+            contract
+                .get_escrowed_claim(account_id.clone())
+                .unwrap()
+                .releases_at,
+            U64(1_500)
+        );
+
+        contract.cancel_escrowed_claim(account_id.clone());
+        assert!(contract.get_escrowed_claim(account_id.clone()).is_none());
+        assert!(contract.has_claimed(account_id));
+    }
+
+    #[test]
+    fn test_legal_hold() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let account_id = USER1.parse::<AccountId>().unwrap();
+        contract.place_legal_hold(account_id.clone());
+        assert!(contract.is_on_legal_hold(account_id.clone()));
+        assert!(get_logs()[0].contains(r#""event":"legal_hold""#));
+
+        let claim = PendingClaim::new(account_id.clone(), U128(100), NO_DEPOSIT);
+        assert!(matches!(
+            contract.internal_start_claim(claim.clone()),
+            Ok(PromiseOrValue::Value(ClaimOutcome {
+                status: ClaimStatus::Held,
+                amount: U128(100),
+                ..
+            }))
+        ));
+        assert!(contract.has_claimed(account_id.clone()));
+        assert_eq!(contract.get_accounting().reserved, U128(100));
+        assert_eq!(
+            contract.internal_start_claim(claim).err(),
+            Some(ClaimError::AlreadyClaimed)
+        );
+
+        contract.lift_legal_hold(account_id.clone());
+        assert!(!contract.is_on_legal_hold(account_id.clone()));
+        assert!(get_logs()
+            .last()
+            .unwrap()
+            .contains(r#""deferred_amount":"100""#));
+        contract.release_held_claim(account_id.clone());
+        assert!(contract.get_held_claim(account_id).is_none());
+    }
+
+    #[test]
+    fn test_withdrawal_challenge_period() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_timestamp(1_000);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_withdrawal_delay(U64(500));
+        contract.set_guardian(Some(USER1.parse().unwrap()));
+        let withdrawal = contract.announce_withdrawal(U128(100));
+        assert_eq!(withdrawal.executable_at, U64(1_500));
+        assert!(get_logs()
+            .last()
+            .unwrap()
+            .contains(r#""event":"withdrawal_announced""#));
+
+        testing_env!(get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO).build());
+        contract.veto_withdrawal();
+        assert_eq!(contract.get_pending_withdrawal(), None);
+
+        context.block_timestamp(2_000);
+        testing_env!(context.build());
+        contract.announce_withdrawal(U128(100));
+        context.block_timestamp(2_500);
+        testing_env!(context.build());
+        contract.withdraw_token(U128(100));
+        // The announcement is only consumed once the balance check passes
+        assert!(contract.get_pending_withdrawal().is_some());
+        let withdraw = |contract: &mut AirdropContract, balance| {
+            contract
+                .on_check_balance_then_withdraw(
+                    TOKEN_CONTRACT.parse().unwrap(),
+                    OWNER.parse().unwrap(),
+                    U128(100),
+                    Ok(Some(U128(balance))),
+                )
+                .err()
+        };
+        assert_eq!(withdraw(&mut contract, 50), Some(ClaimError::Underfunded));
+        assert!(contract.get_pending_withdrawal().is_some());
+        assert_eq!(withdraw(&mut contract, 100), None);
+        assert_eq!(contract.get_pending_withdrawal(), None);
+    }
+
+    #[test]
+    fn test_distribute_deducts_announced_withdrawal() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_timestamp(1_000);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_withdrawal_delay(U64(500));
+        contract.announce_withdrawal(U128(300));
+        context.block_timestamp(1_500);
+        testing_env!(context.build());
+
+        // The repeated recipient is skipped and its tokens stay announced
+        let user1 = USER1.parse::<AccountId>().unwrap();
+        contract.distribute(vec![(user1.clone(), U128(100)), (user1, U128(100))]);
+        assert_eq!(
+            contract
+                .get_pending_withdrawal()
+                .map(|withdrawal| withdrawal.amount),
+            Some(U128(200))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The withdrawal is executable from 1500")]
+    fn test_withdrawal_before_delay() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_timestamp(1_000);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_withdrawal_delay(U64(500));
+        contract.announce_withdrawal(U128(100));
+        contract.withdraw_token(U128(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "The withdrawal must be announced first")]
+    fn test_council_withdrawal_requires_announcement() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_withdrawal_delay(U64(500));
+        contract.set_council(Council {
+            members: vec![OWNER.parse().unwrap()],
+            threshold: 1,
+        });
+        contract.propose_action(CouncilAction::WithdrawToken { amount: U128(100) });
+    }
+
+    #[test]
+    #[should_panic(expected = "The withdrawal must be announced first")]
+    fn test_distribute_requires_announcement() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_withdrawal_delay(U64(500));
+        contract.distribute(vec![(USER1.parse().unwrap(), U128(100))]);
+    }
+
+    #[test]
+    fn test_finalization_sweep_requires_announcement() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_withdrawal_delay(U64(500));
+        contract.set_claim_deadline(Some(U64(1_000)));
+
+        // Finalized, but nothing is swept without an executable announcement
+        testing_env!(context.block_timestamp(1_000).build());
+        contract.announce_withdrawal(U128(300));
+        assert!(matches!(
+            contract.finalize_campaign(10, false),
+            PromiseOrValue::Value(true)
+        ));
+        assert!(contract.is_finalized());
+        assert!(contract.get_pending_withdrawal().is_some());
+
+        // The announced amount caps the sweep
+        testing_env!(context.block_timestamp(1_500).build());
+        assert!(matches!(
+            contract.finalize_campaign(10, false),
+            PromiseOrValue::Promise(_)
+        ));
+        assert_eq!(contract.get_pending_withdrawal(), None);
+        assert!(contract.on_balance_then_sweep(U128(300), Ok(U128(500))));
+        assert_eq!(
+            get_logs().last().unwrap(),
+            "Sweeping 300 tokens to @owner.testnet"
+        );
+    }
+
+    #[test]
+    fn test_claim_rate_limit() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_height(10);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_claim_rate_limit(Some(ClaimRateLimit {
+            max_claims: 1,
+            period: RatePeriod::Block,
+        }));
+
+        let first = PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT);
+        let second = PendingClaim::new(OWNER.parse().unwrap(), U128(100), NO_DEPOSIT);
+        assert!(contract.internal_start_claim(first).is_ok());
+        assert_eq!(
+            contract.internal_start_claim(second.clone()).err(),
+            Some(ClaimError::RateLimited)
+        );
+
+        testing_env!(context.block_height(11).build());
+        assert!(contract.internal_start_claim(second).is_ok());
+    }
+
+    #[test]
+    fn test_failed_claim_start_keeps_state() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_height(10).block_timestamp(1_000);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_claim_rate_limit(Some(ClaimRateLimit {
+            max_claims: 1,
+            period: RatePeriod::Block,
+        }));
+        contract.set_early_bird_bonus(
+            Some(EarlyBirdBonus {
+                bonus_bps: 1_000,
+                ends_at: U64(2_000),
+            }),
+            U128(100),
+        );
+        contract.set_distribution_cap(Some(U128(50)));
+
+        // As in the callback of USD claims, where errors do not revert the state
+        let claim = PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT);
+        assert_eq!(
+            contract.internal_start_claim(claim).err(),
+            Some(ClaimError::CapExceeded)
+        );
+        assert_eq!(contract.get_bonus_pool(), U128(100));
+        assert_eq!(contract.rate_window_claims, 0);
+        assert!(!contract.has_claimed(USER1.parse().unwrap()));
+
+        let claim = PendingClaim::new(USER1.parse().unwrap(), U128(40), NO_DEPOSIT);
+        assert!(contract.internal_start_claim(claim).is_ok());
+        assert_eq!(contract.get_bonus_pool(), U128(96));
+    }
+
+    #[test]
+    fn test_accounting() {
+        let context = get_context(TOKEN_CONTRACT.parse::<AccountId>().unwrap(), NO_DEPOSIT);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        assert!(matches!(
+            contract.ft_on_transfer(OWNER.parse().unwrap(), U128(1_000), String::new()),
+            PromiseOrValue::Value(U128(0))
+        ));
+
+        let claim = PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT);
+        let claim = contract.internal_reserve_claim(claim).unwrap();
+        assert_eq!(contract.get_accounting().reserved, U128(100));
+        assert_eq!(
+            contract.on_ft_transfer_then_claimed(claim, Ok(())).status,
+            ClaimStatus::Success
+        );
+        let accounting = contract.on_balance_then_check_invariants(Ok(U128(900)));
+        assert_eq!(
+            accounting,
+            Accounting {
+                funded: U128(1_000),
+                distributed: U128(100),
+                reserved: U128(0),
+                locked: U128(0),
+                withdrawn: U128(0),
+            }
+        );
+
+        let context = get_context("other.testnet".parse::<AccountId>().unwrap(), NO_DEPOSIT);
+        testing_env!(context.build());
+        assert!(matches!(
+            contract.ft_on_transfer(OWNER.parse().unwrap(), U128(5), String::new()),
+            PromiseOrValue::Value(U128(5))
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token balance 800 is below the expected 900")]
+    fn test_accounting_drift() {
+        let context = get_context(TOKEN_CONTRACT.parse::<AccountId>().unwrap(), NO_DEPOSIT);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.ft_on_transfer(OWNER.parse().unwrap(), U128(900), String::new());
+        contract.on_balance_then_check_invariants(Ok(U128(800)));
+    }
+
+    #[test]
+    fn test_distribution_cap() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_distribution_cap(Some(U128(150)));
+
+        let first = PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT);
+        let second = PendingClaim::new(OWNER.parse().unwrap(), U128(100), NO_DEPOSIT);
+        assert!(contract.internal_start_claim(first.clone()).is_ok());
+        assert_eq!(contract.get_distributed(), U128(100));
+        assert_eq!(
+            contract.internal_start_claim(second.clone()).err(),
+            Some(ClaimError::CapExceeded)
+        );
+
+        contract.internal_rollback(&first, ClaimStatus::TransferFailed);
+        assert_eq!(contract.get_distributed(), U128(0));
+        assert!(contract.internal_start_claim(second).is_ok());
+    }
+
+    #[test]
+    fn test_rounds() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let account_id = USER1.parse::<AccountId>().unwrap();
+        let claim = PendingClaim::new(account_id.clone(), U128(100), NO_DEPOSIT);
+        let claim = contract.internal_reserve_claim(claim).unwrap();
+        assert_eq!(
+            contract.on_ft_transfer_then_claimed(claim, Ok(())).status,
+            ClaimStatus::Success
+        );
+
+        contract.close_round();
+        let claim = PendingClaim::new(OWNER.parse().unwrap(), U128(100), NO_DEPOSIT);
+        assert_eq!(
+            contract.internal_start_claim(claim).err(),
+            Some(ClaimError::RoundClosed)
+        );
+
+        // The same account claims again in the next round
+        assert_eq!(contract.open_round("ab".repeat(32), U128(1_000), 10), 1);
+        assert_eq!(contract.get_current_round(), 1);
+        assert!(!contract.has_claimed(account_id.clone()));
+        assert!(contract.has_claimed_in_round(account_id.clone(), 0));
+        let claim = PendingClaim::new(account_id.clone(), U128(200), NO_DEPOSIT);
+        let claim = contract.internal_reserve_claim(claim).unwrap();
+        contract.on_ft_transfer_then_claimed(claim, Ok(()));
+        assert!(contract.has_claimed_in_round(account_id.clone(), 1));
+        assert_eq!(
+            contract
+                .get_claim_record(account_id.clone(), None, None)
+                .unwrap()
+                .amount,
+            U128(200)
+        );
+        // The receipt of the previous round is kept
+        let record = contract
+            .get_claim_record(account_id.clone(), Some(0), None)
+            .unwrap();
+        assert_eq!((record.round, record.amount), (0, U128(100)));
+        contract.admin_unclaim(account_id.clone(), Some(0), None);
+        assert!(!contract.has_claimed_in_round(account_id.clone(), 0));
+        assert!(contract.has_claimed(account_id.clone()));
+        assert_eq!(
+            contract.get_claim_record(account_id.clone(), Some(0), None),
+            None
+        );
+        assert!(contract.get_claim_record(account_id, None, None).is_some());
+
+        let first = contract.get_round(0).unwrap();
+        assert_eq!(
+            first.merkle_root,
+            "42bb039d55571a5564e772449aab51904f292f69ea5efb6becde8f8f5c37d643"
+        );
+        assert_eq!((first.claims, first.claimed_amount), (1, U128(100)));
+        assert!(first.closed_at.is_some());
+        let second = contract.get_round(1).unwrap();
+        assert_eq!(second.merkle_root, "ab".repeat(32));
+        assert_eq!((second.claims, second.claimed_amount), (1, U128(200)));
+    }
+
+    #[test]
+    fn test_root_totals() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        assert_eq!(contract.get_root_totals(), None);
+        contract.update_merkle_root("ab".repeat(32), U128(150), 2, None);
+        assert_eq!(
+            contract.get_root_totals(),
+            Some(RootTotals {
+                total_amount: U128(150),
+                leaf_count: 2,
+            })
+        );
+
+        let first = PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT);
+        let second = PendingClaim::new(OWNER.parse().unwrap(), U128(100), NO_DEPOSIT);
+        assert!(contract.internal_start_claim(first.clone()).is_ok());
+        assert_eq!(contract.get_root_claimed(), U128(100));
+        assert_eq!(
+            contract.internal_start_claim(second.clone()).err(),
+            Some(ClaimError::RootTotalExceeded)
+        );
+        assert!(!contract.has_claimed(OWNER.parse().unwrap()));
+
+        contract.internal_rollback(&first, ClaimStatus::TransferFailed);
+        assert_eq!(contract.get_root_claimed(), U128(0));
+        assert!(contract.internal_start_claim(second).is_ok());
+    }
+
+    #[test]
+    fn test_root_changed_mid_flight() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_root_update_cooldown(U64(0));
+        contract.update_merkle_root("ab".repeat(32), U128(1_000), 10, None);
+        assert_eq!(contract.get_root_version(), 1);
+        let claim = contract
+            .internal_reserve_claim(PendingClaim::new(
+                USER1.parse().unwrap(),
+                U128(100),
+                NO_DEPOSIT,
+            ))
+            .unwrap();
+        assert_eq!(claim.root_version, Some(1));
+
+        contract.update_merkle_root("cd".repeat(32), U128(1_000), 10, None);
+        let PromiseOrValue::Value(outcome) =
+            contract.on_storage_deposit_then_transfer(claim, Ok(None))
+        else {
+            panic!("Expected the claim to be reverted");
+        };
+        assert_eq!(outcome.status, ClaimStatus::RootChanged);
+        assert!(!contract.has_claimed(USER1.parse().unwrap()));
+        assert_eq!(contract.get_accounting().distributed, U128(0));
+        assert_eq!(contract.get_root_claimed(), U128(0));
+    }
+
+    #[test]
+    fn test_max_claim_amount() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_max_claim_amount(Some(U128(100)));
+
+        let too_large = PendingClaim::new(USER1.parse().unwrap(), U128(101), NO_DEPOSIT);
+        assert_eq!(
+            contract.internal_start_claim(too_large).err(),
+            Some(ClaimError::AmountTooLarge)
+        );
+        assert!(!contract.has_claimed(USER1.parse().unwrap()));
+        let claim = PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT);
+        assert!(contract.internal_start_claim(claim).is_ok());
+    }
+
+    #[test]
+    fn test_admin_unclaim() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let account_id = USER1.parse::<AccountId>().unwrap();
+        let referrer = OWNER.parse::<AccountId>().unwrap();
+        let claim = PendingClaim {
+            referrer: Some(referrer.clone()),
+            referral_bonus: U128(10),
+            ..PendingClaim::new(account_id.clone(), U128(100), NO_DEPOSIT)
+        };
+        contract.claimed.insert(account_id.clone());
+        assert_eq!(
+            contract.on_ft_transfer_then_claimed(claim, Ok(())).status,
+            ClaimStatus::Success
+        );
+
+        contract.admin_unclaim(account_id.clone(), None, None);
+        assert!(!contract.has_claimed(account_id.clone()));
+        assert_eq!(contract.get_claim_record(account_id, None, None), None);
+        assert_eq!(
+            contract.get_referral_stats(referrer),
+            ReferralStats::default()
+        );
+        assert!(get_logs().last().unwrap().contains(r#""event":"unclaim""#));
+    }
+
+    #[test]
+    fn test_admin_mark_claimed() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.claimed.insert(OWNER.parse().unwrap());
+        contract.admin_mark_claimed(vec![OWNER.parse().unwrap(), USER1.parse().unwrap()]);
+        assert!(contract.has_claimed(USER1.parse().unwrap()));
+        assert_eq!(get_logs().last().unwrap(), "Marked 1 accounts as claimed");
+
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], None, None, None, None, None)
+                .err(),
+            Some(ClaimError::AlreadyClaimed)
+        );
+    }
+
+    #[test]
+    fn test_claimed_snapshot() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let empty_root = contract.get_claimed_snapshot_root();
+        let claim = contract
+            .internal_reserve_claim(PendingClaim::new(
+                USER1.parse().unwrap(),
+                U128(100),
+                NO_DEPOSIT,
+            ))
+            .unwrap();
+        contract.on_ft_transfer_then_claimed(claim, Ok(()));
+        assert_eq!(contract.get_claimed_snapshot_size(), U64(1));
+        assert_ne!(contract.get_claimed_snapshot_root(), empty_root);
+
+        // Every leaf verifies with its siblings and the zero hashes of the missing subtrees
+        let mut tree = IncrementalMerkleTree::default();
+        let accounts = [USER1, OWNER, TOKEN_CONTRACT];
+        for account in accounts {
+            tree.push(HashAlgorithm::Keccak256, account.as_bytes());
+        }
+        let hash = |a: &[u8], b: &[u8]| LeafFormat::V2.hash_node(HashAlgorithm::Keccak256, a, b);
+        let leaf =
+            |account: &str| LeafFormat::V2.hash_leaf(HashAlgorithm::Keccak256, account.as_bytes());
+        let mut zeros = vec![vec![0u8; 32]];
+        for level in 0..merkle::INCREMENTAL_TREE_DEPTH {
+            zeros.push(hash(&zeros[level], &zeros[level]));
+        }
+        let mut proof = vec![zeros[0].clone(), hash(&leaf(USER1), &leaf(OWNER))];
+        proof.extend_from_slice(&zeros[2..merkle::INCREMENTAL_TREE_DEPTH]);
+        assert_eq!(
+            merkle::process_proof(
+                HashAlgorithm::Keccak256,
+                LeafFormat::V2,
+                TOKEN_CONTRACT.as_bytes(),
+                &proof
+            ),
+            tree.root(HashAlgorithm::Keccak256)
+        );
+        assert_eq!(tree.len(), 3);
+        assert_eq!(
+            IncrementalMerkleTree::default().root(HashAlgorithm::Keccak256),
+            zeros[merkle::INCREMENTAL_TREE_DEPTH]
+        );
+    }
+
+    #[test]
+    fn test_partial_fill() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_partial_fills(true);
+        contract.funded = 60;
+        let claim = contract
+            .internal_reserve_claim(PendingClaim::new(
+                USER1.parse().unwrap(),
+                U128(100),
+                NO_DEPOSIT,
+            ))
+            .unwrap();
+        assert_eq!(claim.amount, U128(60));
+        assert_eq!(
+            contract.get_shortfall(USER1.parse().unwrap()),
+            Some(U128(40))
+        );
+        assert_eq!(
+            contract
+                .internal_reserve_claim(PendingClaim::new(
+                    OWNER.parse().unwrap(),
+                    U128(100),
+                    NO_DEPOSIT,
+                ))
+                .err(),
+            Some(ClaimError::Underfunded)
+        );
+        contract.on_ft_transfer_then_claimed(claim, Ok(()));
+
+        // The shortfall is claimable once the pool is topped up, and restored if it fails
+        contract.funded = 100;
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let shortfall = contract
+            .internal_reserve_claim(PendingClaim {
+                pays_shortfall: true,
+                ..contract.internal_new_claim(USER1.parse().unwrap(), U128(40))
+            })
+            .unwrap();
+        assert_eq!(shortfall.amount, U128(40));
+        contract.internal_rollback(&shortfall, ClaimStatus::TransferFailed);
+        assert!(contract.has_claimed(USER1.parse().unwrap()));
+        assert_eq!(
+            contract.get_shortfall(USER1.parse().unwrap()),
+            Some(U128(40))
+        );
+        assert!(contract.claim_shortfall().is_ok());
+        assert_eq!(contract.get_shortfall(USER1.parse().unwrap()), None);
+    }
+
+    #[test]
+    fn test_ious() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_ious_enabled(true);
+        let claim = contract
+            .internal_reserve_claim(PendingClaim::new(
+                USER1.parse().unwrap(),
+                U128(100),
+                NO_DEPOSIT,
+            ))
+            .unwrap();
+        let outcome = contract
+            .on_ft_transfer_then_claimed(claim.clone(), Err(near_sdk::PromiseError::Failed));
+        assert_eq!(outcome.status, ClaimStatus::Iou);
+        assert_eq!(outcome.amount, U128(100));
+        assert!(contract.has_claimed(USER1.parse().unwrap()));
+        assert_eq!(contract.get_accounting().reserved, U128(100));
+        assert_eq!(
+            contract.get_ious(0, 10),
+            vec![(USER1.parse().unwrap(), U128(100))]
+        );
+
+        assert_eq!(contract.settle_ious(10), 1);
+        assert_eq!(contract.get_iou_count(), 0);
+        assert_eq!(
+            contract.on_ft_transfer_then_claimed(claim, Ok(())).status,
+            ClaimStatus::Success
+        );
+        assert_eq!(contract.get_accounting().reserved, U128(0));
+    }
+
+    #[test]
+    fn test_claimer_pays_records() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        assert_eq!(contract.get_record_deposit(), NO_DEPOSIT);
+        contract.set_claimer_pays_records(true);
+        let record_deposit = contract.get_record_deposit();
+        assert!(!record_deposit.is_zero());
+
+        let context = get_context(
+            USER1.parse::<AccountId>().unwrap(),
+            record_deposit.saturating_add(ONE_YOCTO),
+        );
+        testing_env!(context.build());
+        assert_eq!(contract.assert_claim_deposit(), Ok(()));
+        let claim = contract
+            .internal_reserve_claim(contract.internal_new_claim(USER1.parse().unwrap(), U128(100)))
+            .unwrap();
+        assert_eq!(claim.record_deposit, U128(record_deposit.as_yoctonear()));
+        contract.on_ft_transfer_then_claimed(claim, Ok(()));
+        assert!(get_logs()
+            .iter()
+            .any(|log| log.starts_with("Claim record of @user1.testnet stored in")));
+    }
+
+    #[test]
+    fn test_hashed_claimed_storage() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_claimed_storage(ClaimedStorage::Hashed);
+        assert_eq!(contract.get_claimed_storage(), ClaimedStorage::Hashed);
+        let claim = PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT);
+        assert!(contract.internal_start_claim(claim.clone()).is_ok());
+        assert!(contract.has_claimed(USER1.parse().unwrap()));
+        assert!(contract.claimed.is_empty());
+
+        contract.internal_rollback(&claim, ClaimStatus::TransferFailed);
+        assert!(!contract.has_claimed(USER1.parse().unwrap()));
+    }
+
+    #[test]
+    fn test_import_claimed() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        assert_eq!(
+            contract.import_claimed(vec![USER1.parse().unwrap()], None),
+            1
+        );
+        assert_eq!(
+            contract.import_claimed(vec![USER1.parse().unwrap(), OWNER.parse().unwrap()], None),
+            1
+        );
+        assert_eq!(contract.get_imported_claimed(), U64(2));
+        assert!(contract.has_claimed(USER1.parse().unwrap()));
+        assert_eq!(
+            get_logs().last().unwrap(),
+            "Imported 1 claimed accounts, 2 in total"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Claims have started")]
+    fn test_import_claimed_after_first_claim() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        assert!(contract
+            .internal_start_claim(PendingClaim::new(
+                OWNER.parse().unwrap(),
+                U128(100),
+                NO_DEPOSIT
+            ))
+            .is_ok());
+        contract.import_claimed(vec![USER1.parse().unwrap()], None);
+    }
+
+    #[test]
+    fn test_relayed_claims() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let public_key: PublicKey = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp"
+            .parse()
+            .unwrap();
+        let authorization = ClaimAuthorization {
+            account_id: hex::encode(&public_key.as_bytes()[1..]).parse().unwrap(),
+            public_key,
+            signature: vec![0; 64].into(),
+        };
+
+        // Without relayed claims, the yoctoNEAR is required and authorizations are rejected
+        let relayer: AccountId = "relayer.testnet".parse().unwrap();
+        testing_env!(get_context(relayer.clone(), NO_DEPOSIT).build());
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], None, None, None, None, None)
+                .err(),
+            Some(ClaimError::InvalidDeposit)
+        );
+        testing_env!(get_context(relayer.clone(), ONE_YOCTO).build());
+        assert_eq!(
+            contract
+                .claim_airdrop(
+                    U128(100),
+                    vec![],
+                    None,
+                    None,
+                    None,
+                    Some(authorization.clone()),
+                    None
+                )
+                .err(),
+            Some(ClaimError::InvalidAuthorization)
+        );
+
+        testing_env!(get_context(OWNER.parse().unwrap(), ONE_YOCTO).build());
+        contract.set_relayed_claims(true);
+        assert!(contract.get_relayed_claims());
+        contract.set_relayer(relayer.clone(), Some(10));
+        let mut context = get_context(relayer.clone(), NO_DEPOSIT);
+        testing_env!(context.signer_account_id(relayer).build());
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], None, None, None, None, None)
+                .err(),
+            Some(ClaimError::InvalidProof)
+        );
+        assert_eq!(
+            contract
+                .claim_airdrop(
+                    U128(100),
+                    vec![],
+                    None,
+                    None,
+                    None,
+                    Some(authorization),
+                    None
+                )
+                .err(),
+            Some(ClaimError::InvalidAuthorization)
+        );
+    }
+
+    #[test]
+    fn test_commit_reveal_root() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let new_root = "ab".repeat(32);
+        let mut preimage = hex::decode(&new_root).unwrap();
+        preimage.extend_from_slice(b"salt");
+        let commitment = Base58CryptoHash::from(env::sha256_array(&preimage));
+        assert_eq!(
+            contract.get_root_commitment_hash(new_root.clone(), "salt".to_string()),
+            commitment
+        );
+        contract.commit_root(commitment);
+        assert_eq!(contract.get_root_commitments()[0].commitment, commitment);
+
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.block_height(1).build());
+        contract.reveal_root(new_root.clone(), "salt".to_string(), U128(1_000), 10);
+        assert_eq!(contract.get_merkle_root(), new_root);
+        assert!(contract.get_root_commitments().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "No matching root commitment")]
+    fn test_reveal_root_with_wrong_salt() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let new_root = "ab".repeat(32);
+        contract
+            .commit_root(contract.get_root_commitment_hash(new_root.clone(), "salt".to_string()));
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.block_height(1).build());
+        contract.reveal_root(new_root, "pepper".to_string(), U128(1_000), 10);
+    }
+
+    #[test]
+    fn test_root_update_cooldown() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        assert_eq!(
+            contract.get_root_update_cooldown(),
+            U64(DEFAULT_ROOT_UPDATE_COOLDOWN)
+        );
+        contract.update_merkle_root("ab".repeat(32), U128(1_000), 10, None);
+        assert_eq!(
+            contract.get_next_root_update_at(),
+            U64(DEFAULT_ROOT_UPDATE_COOLDOWN)
+        );
+
+        // Lowering the cooldown waits a full period of the current one
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.block_timestamp(10).build());
+        contract.set_root_update_cooldown(U64(0));
+        assert_eq!(
+            contract.get_next_root_update_at(),
+            U64(DEFAULT_ROOT_UPDATE_COOLDOWN + 10)
+        );
+
+        testing_env!(context
+            .block_timestamp(DEFAULT_ROOT_UPDATE_COOLDOWN + 10)
+            .build());
+        contract.update_merkle_root("cd".repeat(32), U128(1_000), 10, None);
+        contract.update_merkle_root("ef".repeat(32), U128(1_000), 10, None);
+        assert_eq!(contract.get_merkle_root(), "ef".repeat(32));
+    }
+
+    #[test]
+    #[should_panic(expected = "Root updates are on cooldown")]
+    fn test_root_update_during_cooldown() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.update_merkle_root("ab".repeat(32), U128(1_000), 10, None);
+        contract.update_merkle_root("cd".repeat(32), U128(1_000), 10, None);
+    }
+
+    #[test]
+    fn test_admin_log() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_claim_fee(NearToken::from_millinear(1));
+        contract.update_merkle_root("ab".repeat(32), U128(1_000), 10, None);
+        contract.set_guardian(Some(USER1.parse().unwrap()));
+
+        assert_eq!(contract.get_admin_log_length(), U64(3));
+        let log = contract.get_admin_log(1, 10);
+        assert_eq!(
+            log.iter()
+                .map(|(index, action)| (index.0, action.method.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(1, "update_merkle_root"), (2, "set_guardian")]
+        );
+        assert_eq!(log[0].1.caller, OWNER.parse::<AccountId>().unwrap());
+        assert_eq!(
+            log[0].1.args_hash,
+            Base58CryptoHash::from(env::sha256_array(&[]))
+        );
+        assert_eq!(log[0].1.block_height, U64(env::block_height()));
+        assert!(contract.get_admin_log(3, 10).is_empty());
+    }
+
+    #[test]
+    fn test_council() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_council(Council {
+            members: vec![OWNER.parse().unwrap(), USER1.parse().unwrap()],
+            threshold: 2,
+        });
+        let new_root = "ab".repeat(32);
+        let id = contract.propose_action(CouncilAction::UpdateMerkleRoot {
+            merkle_root: new_root.clone(),
+            total_amount: U128(1_000),
+            leaf_count: 10,
+            leaf_format: None,
+        });
+        assert_eq!(
+            contract.get_council_proposal(id).unwrap().confirmations,
+            vec![OWNER.parse::<AccountId>().unwrap()]
+        );
+        assert_eq!(
+            contract.get_merkle_root(),
+            "42bb039d55571a5564e772449aab51904f292f69ea5efb6becde8f8f5c37d643"
+        );
+
+        // The second confirmation executes the proposal
+        testing_env!(get_context(USER1.parse().unwrap(), ONE_YOCTO).build());
+        assert!(contract.confirm_action(id));
+        assert_eq!(contract.get_merkle_root(), new_root);
+        assert!(contract.get_council_proposal(id).is_none());
+        assert!(contract.get_council_proposals(0, 10).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires council confirmation")]
+    fn test_council_blocks_direct_root_update() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_council(Council {
+            members: vec![USER1.parse().unwrap()],
+            threshold: 1,
+        });
+        contract.update_merkle_root("ab".repeat(32), U128(1_000), 10, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires council confirmation")]
+    fn test_council_blocks_direct_treasury_change() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_council(Council {
+            members: vec![USER1.parse().unwrap()],
+            threshold: 1,
+        });
+        contract.set_treasury(OWNER.parse().unwrap());
+    }
+
+    #[test]
+    fn test_council_sets_treasury_and_deadline() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_council(Council {
+            members: vec![USER1.parse().unwrap()],
+            threshold: 1,
+        });
+
+        testing_env!(get_context(USER1.parse().unwrap(), ONE_YOCTO).build());
+        contract.propose_action(CouncilAction::SetTreasury {
+            treasury_id: "treasury.testnet".parse().unwrap(),
+        });
+        contract.propose_action(CouncilAction::SetClaimDeadline {
+            claim_deadline: Some(U64(1_000)),
+        });
+        assert_eq!(
+            contract.get_treasury(),
+            "treasury.testnet".parse::<AccountId>().unwrap()
+        );
+        assert_eq!(contract.get_claim_deadline(), Some(U64(1_000)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires council confirmation")]
+    fn test_council_blocks_direct_unclaimed_burn_change() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_council(Council {
+            members: vec![USER1.parse().unwrap()],
+            threshold: 1,
+        });
+        contract.set_unclaimed_burn(Some(UnclaimedBurn::TokenBurn));
+    }
+
+    #[test]
+    fn test_council_sets_unclaimed_burn() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_council(Council {
+            members: vec![USER1.parse().unwrap()],
+            threshold: 1,
+        });
+
+        testing_env!(get_context(USER1.parse().unwrap(), ONE_YOCTO).build());
+        contract.propose_action(CouncilAction::SetUnclaimedBurn {
+            unclaimed_burn: Some(UnclaimedBurn::TokenBurn),
+        });
+        assert_eq!(
+            contract.get_unclaimed_burn(),
+            Some(UnclaimedBurn::TokenBurn)
+        );
+    }
+
+    #[test]
+    fn test_nft_badge() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let deposit = NearToken::from_millinear(10);
+        contract.set_nft_badge(Some(NftBadge {
+            nft_contract: "badges.testnet".parse().unwrap(),
+            series_id: "1".to_string(),
+            deposit,
+        }));
+        assert_eq!(contract.get_nft_badge().unwrap().deposit, deposit);
+
+        // Every claim pays for its badge
+        testing_env!(get_context(USER1.parse().unwrap(), ONE_YOCTO).build());
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], None, None, None, None, None)
+                .err(),
+            Some(ClaimError::InvalidDeposit)
+        );
+        testing_env!(
+            get_context(USER1.parse().unwrap(), deposit.saturating_add(ONE_YOCTO)).build()
+        );
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], None, None, None, None, None)
+                .err(),
+            Some(ClaimError::InvalidProof)
+        );
+
+        let claim = contract.internal_new_claim(USER1.parse().unwrap(), U128(100));
+        assert_eq!(claim.badge_deposit, U128(deposit.as_yoctonear()));
+        assert_eq!(
+            AirdropContract::completion_gas(&claim),
+            Gas::from_tgas(5).saturating_add(GAS_FOR_BADGE)
+        );
+        assert_eq!(
+            contract.on_ft_transfer_then_claimed(claim, Ok(())).status,
+            ClaimStatus::Success
+        );
+        // Recorded by `on_badge_minted` once minted
+        assert_eq!(
+            contract
+                .get_claim_record(USER1.parse().unwrap(), None, None)
+                .unwrap()
+                .badge_token_id,
+            None
+        );
+    }
+
+    #[test]
+    fn test_social_posts() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+
+        testing_env!(get_context(USER1.parse().unwrap(), ONE_YOCTO).build());
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], None, None, None, None, Some(true))
+                .err(),
+            Some(ClaimError::SocialPostsDisabled)
+        );
+
+        testing_env!(get_context(OWNER.parse().unwrap(), ONE_YOCTO).build());
+        let deposit = NearToken::from_millinear(10);
+        contract.set_social_posts(Some(SocialPosts {
+            social_contract: "social.near".parse().unwrap(),
+            badge: "publicai-airdrop".to_string(),
+            deposit,
+        }));
+
+        // The post is paid on top of the claim deposit
+        testing_env!(get_context(USER1.parse().unwrap(), ONE_YOCTO).build());
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], None, None, None, None, Some(true))
+                .err(),
+            Some(ClaimError::InvalidDeposit)
+        );
+        testing_env!(
+            get_context(USER1.parse().unwrap(), deposit.saturating_add(ONE_YOCTO)).build()
+        );
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], None, None, None, None, Some(true))
+                .err(),
+            Some(ClaimError::InvalidProof)
+        );
+
+        let args =
+            contract.social_post_args("publicai-airdrop", &USER1.parse().unwrap(), U128(100));
+        let args: serde_json::Value = serde_json::from_slice(&args).unwrap();
+        let data = &args["data"]["alice.near"];
+        assert_eq!(data["badge"]["publicai-airdrop"]["holder"][USER1], "");
+        assert!(data["post"]["main"]
+            .as_str()
+            .unwrap()
+            .contains("@user1.testnet claimed 100 tokens of the token.testnet airdrop"));
+    }
+
+    #[test]
+    fn test_web4_get() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), NO_DEPOSIT);
+        testing_env!(context.build());
+        let contract = new_contract();
+        let request = |path: &str| Web4Request {
+            account_id: Some(USER1.parse().unwrap()),
+            path: path.to_string(),
+        };
+
+        let page = contract.web4_get(request("/"));
+        assert_eq!(
+            page.content_type.as_deref(),
+            Some("text/html; charset=UTF-8")
+        );
+        let html = String::from_utf8(page.body.unwrap().0).unwrap();
+        assert!(!html.contains("__CONFIG__"));
+        assert!(html.contains(r#""account_id":"user1.testnet""#));
+
+        let config = contract.web4_get(request("/config.json"));
+        let config: serde_json::Value = serde_json::from_slice(&config.body.unwrap().0).unwrap();
+        assert_eq!(config["token_contract"], TOKEN_CONTRACT);
+        assert_eq!(config["claim_deposit"], "1");
+
+        assert_eq!(contract.web4_get(request("/missing")).status, Some(404));
+    }
+
+    #[test]
+    fn test_claim_to() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let public_key: PublicKey = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp"
+            .parse()
+            .unwrap();
+        let implicit: AccountId = hex::encode(&public_key.as_bytes()[1..]).parse().unwrap();
+        let receiver: AccountId = "ledger.testnet".parse().unwrap();
+
+        // Someone else's leaf needs a valid authorization
+        testing_env!(get_context(USER1.parse().unwrap(), ONE_YOCTO).build());
+        assert_eq!(
+            contract
+                .claim_to(
+                    implicit.clone(),
+                    receiver.clone(),
+                    U128(100),
+                    vec![],
+                    None,
+                    None
+                )
+                .err(),
+            Some(ClaimError::InvalidAuthorization)
+        );
+        assert_eq!(
+            contract
+                .claim_to(
+                    implicit,
+                    receiver.clone(),
+                    U128(100),
+                    vec![],
+                    None,
+                    Some(ReceiverAuthorization {
+                        public_key,
+                        signature: vec![0; 64].into(),
+                    })
+                )
+                .err(),
+            Some(ClaimError::InvalidAuthorization)
+        );
+        // The account itself needs none
+        assert_eq!(
+            contract
+                .claim_to(
+                    USER1.parse().unwrap(),
+                    receiver.clone(),
+                    U128(100),
+                    vec![],
+                    None,
+                    None
+                )
+                .err(),
+            Some(ClaimError::InvalidProof)
+        );
+
+        let claim = PendingClaim {
+            receiver_id: Some(receiver.clone()),
+            ..PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT)
+        };
+        assert_eq!(claim.receiver_id(), &receiver);
+
+        // Signatures only authorize the leaf of one round and nonce
+        let contract_id: AccountId = "airdrop.testnet".parse().unwrap();
+        assert_eq!(
+            receiver_authorization_message(
+                &contract_id,
+                &USER1.parse().unwrap(),
+                &receiver,
+                U128(100),
+                2,
+                Some(U64(7))
+            ),
+            "airdrop.testnet:user1.testnet:ledger.testnet:100:2:7"
+        );
+        assert_eq!(
+            claim_authorization_message(&contract_id, &USER1.parse().unwrap(), U128(100), 0, None),
+            "airdrop.testnet:user1.testnet:100:0:"
+        );
+    }
+
+    #[test]
+    fn test_relayer_quotas() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let relayer: AccountId = "relayer.testnet".parse().unwrap();
+        contract.set_relayed_claims(true);
+        contract.set_relayer(relayer.clone(), Some(1));
+
+        // Delegate actions of USER1 signed by an unregistered relayer
+        let mut context = get_context(USER1.parse().unwrap(), NO_DEPOSIT);
+        testing_env!(context
+            .signer_account_id("other.testnet".parse().unwrap())
+            .build());
+        assert_eq!(
+            contract.internal_count_relayed_claim(),
+            Err(ClaimError::RelayerNotAllowed)
+        );
+
+        testing_env!(context.signer_account_id(relayer.clone()).build());
+        assert_eq!(contract.internal_count_relayed_claim(), Ok(()));
+        assert_eq!(
+            contract.internal_count_relayed_claim(),
+            Err(ClaimError::RelayerQuotaExceeded)
+        );
+        assert_eq!(contract.get_relayer(relayer.clone()).unwrap().claims, 1);
+
+        // The counter resets the next day
+        testing_env!(context.block_timestamp(86_400_000_000_000).build());
+        assert_eq!(contract.internal_count_relayed_claim(), Ok(()));
+
+        testing_env!(get_context(OWNER.parse().unwrap(), ONE_YOCTO).build());
+        contract.set_relayer(relayer.clone(), None);
+        assert_eq!(contract.get_relayer(relayer), None);
+    }
+
+    #[test]
+    fn test_repeated_relayed_claim_keeps_quota() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let relayer: AccountId = "relayer.testnet".parse().unwrap();
+        contract.set_relayed_claims(true);
+        contract.set_relayer(relayer.clone(), Some(1));
+        contract.set_idempotent_claims(true);
+        contract.internal_mark_leaf_claimed(USER1.parse().unwrap(), None);
+
+        // Retries of a claimed leaf return its outcome without counting against the quota
+        let mut context = get_context(USER1.parse().unwrap(), ONE_YOCTO);
+        testing_env!(context.signer_account_id(relayer.clone()).build());
+        match contract.claim_airdrop(U128(100), vec![], None, None, None, None, None) {
+            Ok(PromiseOrValue::Value(outcome)) => {
+                assert_eq!(outcome.status, ClaimStatus::AlreadyClaimed)
+            }
+            _ => panic!("Expected the outcome of the earlier claim"),
+        }
+        assert_eq!(contract.get_relayer(relayer).unwrap().claims, 0);
+    }
+
+    #[test]
+    fn test_croncat_tick() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let manager: AccountId = "manager.croncat.testnet".parse().unwrap();
+        contract.set_croncat_manager(Some(manager.clone()));
+        contract.set_escrow_period(Some(U64(100)));
+        contract.set_claim_deadline(Some(U64(1_000)));
+        let claim = PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT);
+        contract.internal_start_claim(claim).unwrap();
+
+        // Nothing is released during the dispute window
+        testing_env!(context.predecessor_account_id(manager).build());
+        assert_eq!(contract.croncat_tick(), 0);
+        assert!(contract
+            .get_escrowed_claim(USER1.parse().unwrap())
+            .is_some());
+
+        testing_env!(context.block_timestamp(100).build());
+        assert_eq!(contract.croncat_tick(), 1);
+        assert!(contract
+            .get_escrowed_claim(USER1.parse().unwrap())
+            .is_none());
+        assert!(!contract.is_finalized());
+
+        testing_env!(context.block_timestamp(1_000).build());
+        assert_eq!(contract.croncat_tick(), 0);
+        assert!(contract.is_finalized());
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires council confirmation, see propose_action")]
+    fn test_croncat_tick_by_owner_requires_council() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_croncat_manager(Some("manager.croncat.testnet".parse().unwrap()));
+        contract.set_council(Council {
+            members: vec![OWNER.parse().unwrap()],
+            threshold: 1,
+        });
+        contract.croncat_tick();
+    }
+
+    #[test]
+    fn test_croncat_expires_ious() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_ious_enabled(true);
+        contract.set_iou_expiry(Some(U64(100)));
+        let claim = contract
+            .internal_reserve_claim(PendingClaim::new(
+                USER1.parse().unwrap(),
+                U128(100),
+                NO_DEPOSIT,
+            ))
+            .unwrap();
+        contract.on_ft_transfer_then_claimed(claim, Err(near_sdk::PromiseError::Failed));
+
+        // The tokens owed by the IOU are not swept
+        assert!(contract.on_balance_then_sweep(U128(u128::MAX), Ok(U128(500))));
+        assert_eq!(
+            get_logs().last().unwrap(),
+            "Sweeping 400 tokens to @owner.testnet"
+        );
+
+        testing_env!(context.block_timestamp(99).build());
+        contract.croncat_tick();
+        assert_eq!(contract.get_iou_count(), 1);
+
+        testing_env!(context.block_timestamp(100).build());
+        contract.croncat_tick();
+        assert_eq!(contract.get_iou_count(), 0);
+        assert!(!contract.has_claimed(USER1.parse().unwrap()));
+        assert_eq!(contract.get_accounting().reserved, U128(0));
+        assert!(get_logs()
+            .iter()
+            .any(|log| log == "The IOU of 100 tokens to @user1.testnet expired"));
+    }
+
+    #[test]
+    fn test_finalize_campaign() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_claim_deadline(Some(U64(1_000)));
+        for (index, account_id) in [OWNER, USER1].into_iter().enumerate() {
+            let account_id: AccountId = account_id.parse().unwrap();
+            contract.claimed.insert(account_id.clone());
+            contract
+                .leaf_records
+                .insert((0, account_id.clone(), None), index as u64);
+            contract.claim_records.insert(
+                index as u64,
+                ClaimRecord {
+                    index: U64(index as u64),
+                    amount: U128(100),
+                    timestamp: U64(0),
+                    block_height: U64(0),
+                    referrer: None,
+                    referral_bonus: U128(0),
+                    badge_token_id: None,
+                    round: 0,
+                    nonce: None,
+                },
+            );
+            contract.claim_index.push(account_id);
+        }
+
+        testing_env!(context.block_timestamp(1_000).build());
+        assert!(matches!(
+            contract.finalize_campaign(1, false),
+            PromiseOrValue::Value(false)
+        ));
+        assert!(contract.is_finalized());
+        assert!(contract
+            .get_claim_record(USER1.parse().unwrap(), None, None)
+            .is_none());
+        assert!(contract
+            .get_claim_record(OWNER.parse().unwrap(), None, None)
+            .is_some());
+        assert!(matches!(
+            contract.finalize_campaign(1, false),
+            PromiseOrValue::Promise(_)
+        ));
+        assert!(contract.claim_index.is_empty());
+        assert!(!contract.has_claimed(USER1.parse().unwrap()));
+
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], None, None, None, None, None)
+                .err(),
+            Some(ClaimError::CampaignClosed)
+        );
+    }
+
+    #[test]
+    fn test_unclaimed_burn() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let burn = UnclaimedBurn::Address {
+            account_id: "burn.testnet".parse().unwrap(),
+        };
+        contract.set_unclaimed_burn(Some(burn.clone()));
+        assert_eq!(contract.get_unclaimed_burn(), Some(burn));
+        assert!(contract.on_balance_then_sweep(U128(u128::MAX), Ok(U128(500))));
+
+        assert!(contract.on_unclaimed_burned(
+            U128(500),
+            Some("burn.testnet".parse().unwrap()),
+            Ok(())
+        ));
+        let logs = get_logs();
+        let event = logs.last().unwrap();
+        assert!(event.contains(r#""event":"burn""#));
+        assert!(event.contains(r#""amount":"500","burn_address":"burn.testnet""#));
+    }
+
+    #[test]
+    fn test_treasury_receives_sweeps() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_treasury("treasury.testnet".parse().unwrap());
+        assert!(contract.on_balance_then_sweep(U128(u128::MAX), Ok(U128(500))));
+        assert_eq!(
+            get_logs().last().unwrap(),
+            "Sweeping 500 tokens to @treasury.testnet"
+        );
+        assert_eq!(contract.get_accounting().withdrawn, U128(500));
+    }
+
+    #[test]
+    fn test_withdrawal_leaves_owed_tokens() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_streak_bonus(None, U128(100));
+        let claim = PendingClaim::new(USER1.parse().unwrap(), U128(200), NO_DEPOSIT);
+        contract.internal_reserve_claim(claim).unwrap();
+
+        // The reserved claim and the streak bonus pool stay in the contract
+        let withdraw = |contract: &mut AirdropContract, amount| {
+            contract
+                .on_check_balance_then_withdraw(
+                    TOKEN_CONTRACT.parse().unwrap(),
+                    OWNER.parse().unwrap(),
+                    U128(amount),
+                    Ok(Some(U128(500))),
+                )
+                .err()
+        };
+        assert_eq!(withdraw(&mut contract, 201), Some(ClaimError::Underfunded));
+        assert_eq!(withdraw(&mut contract, 200), None);
+        assert_eq!(contract.get_accounting().withdrawn, U128(200));
+    }
+
+    #[test]
+    fn test_idempotent_claims() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_idempotent_claims(true);
+        let claim = PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT);
+        let claim = contract.internal_reserve_claim(claim).unwrap();
+        contract.on_ft_transfer_then_claimed(claim, Ok(()));
+
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let outcome = contract.claim_airdrop(U128(100), vec![], None, None, None, None, None);
+        assert!(matches!(
+            outcome,
+            Ok(PromiseOrValue::Value(ClaimOutcome {
+                status: ClaimStatus::AlreadyClaimed,
+                amount: U128(100),
+                receipt_index: Some(U64(0)),
+                claimed_at: Some(_),
+            }))
+        ));
+        let outcome = contract.claim_to(
+            USER1.parse().unwrap(),
+            "ledger.testnet".parse().unwrap(),
+            U128(100),
+            vec![],
+            None,
+            None,
+        );
+        assert!(matches!(
+            outcome,
+            Ok(PromiseOrValue::Value(ClaimOutcome {
+                status: ClaimStatus::AlreadyClaimed,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_claim_gas_precheck() {
+        let mut context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.prepaid_gas(Gas::from_tgas(50));
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let claim = PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT);
+        assert_eq!(
+            contract.internal_start_claim(claim).err(),
+            Some(ClaimError::InsufficientGas)
+        );
+        assert!(!contract.has_claimed(USER1.parse().unwrap()));
+    }
+
+    #[test]
+    fn test_storage_report() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.claimed.insert(USER1.parse().unwrap());
+        contract.claimed.insert(OWNER.parse().unwrap());
+
+        let report = contract.get_storage_report();
+        assert_eq!(report.claimed_set_bytes, U64(4 + 4 + 13 + 4 + 13));
+        assert_eq!(
+            report.cost_per_claim,
+            env::storage_byte_cost().saturating_mul(415)
+        );
+    }
+
+    #[test]
+    fn test_verify_token() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        assert_eq!(contract.token_storage_deposit(), STORAGE_DEPOSIT_AMOUNT);
+
+        let metadata = FtMetadata {
+            spec: "ft-1.0.0".to_string(),
+            symbol: "PAI".to_string(),
+            decimals: 18,
+        };
+        let bounds = StorageBalanceBounds {
+            min: U128(1_000),
+            max: None,
+        };
+        contract.on_token_verified(Ok(metadata), Ok(bounds));
+        assert_eq!(contract.get_token_info().unwrap().decimals, 18);
+        assert_eq!(
+            contract.token_storage_deposit(),
+            NearToken::from_yoctonear(1_000)
+        );
+    }
+
+    #[test]
+    fn test_set_token_contract() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.propose_token_contract(Some("token2.testnet".parse().unwrap()));
+        assert_eq!(
+            contract.get_token_change().unwrap().executable_at,
+            U64(TOKEN_CHANGE_TIMELOCK)
+        );
+
+        testing_env!(context.block_timestamp(TOKEN_CHANGE_TIMELOCK).build());
+        contract.set_token_contract();
+        contract.on_balance_then_set_token(Ok(U128(0)));
+        assert_eq!(contract.token_contract.as_str(), "token2.testnet");
+        assert!(contract.get_token_change().is_none());
+        assert!(get_logs()
+            .last()
+            .unwrap()
+            .contains(r#""event":"token_change""#));
+    }
+
+    #[test]
+    fn test_basket_leaf() {
+        let basket = vec![
+            (TOKEN_CONTRACT.parse::<AccountId>().unwrap(), U128(100)),
+            ("usdc.testnet".parse::<AccountId>().unwrap(), U128(5)),
+        ];
+        assert_eq!(
+            encode_basket_leaf(&USER1.parse().unwrap(), &basket),
+            "user1.testnet:token.testnet=100,usdc.testnet=5"
+        );
+    }
+
+    #[test]
+    fn test_basket_failed_leg() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let account_id: AccountId = USER1.parse().unwrap();
+        let usdc: AccountId = "usdc.testnet".parse().unwrap();
+        contract.distributed = 100;
+        contract.reserved = 100;
+        contract.basket_legs.insert(
+            account_id.clone(),
+            vec![TOKEN_CONTRACT.parse().unwrap(), usdc.clone()],
+        );
+
+        assert!(contract.on_basket_leg_transferred(
+            account_id.clone(),
+            TOKEN_CONTRACT.parse().unwrap(),
+            U128(100),
+            0,
+            Ok(())
+        ));
+        assert_eq!(contract.get_accounting().distributed, U128(100));
+        assert!(!contract.on_basket_leg_transferred(
+            account_id.clone(),
+            usdc,
+            U128(5),
+            0,
+            Err(near_sdk::PromiseError::Failed)
+        ));
+        assert_eq!(
+            contract.get_basket_legs(account_id),
+            vec![TOKEN_CONTRACT.parse::<AccountId>().unwrap()]
+        );
+        assert_eq!(contract.get_health().transfer_failures, 1);
+    }
+
+    #[test]
+    fn test_claim_split() {
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let receivers = vec![
+            ("alice.testnet".parse::<AccountId>().unwrap(), U128(60)),
+            ("bob.testnet".parse::<AccountId>().unwrap(), U128(40)),
+        ];
+        assert_eq!(
+            contract
+                .claim_split(receivers.clone(), U128(90), vec![], None)
+                .err(),
+            Some(ClaimError::InvalidSplit)
+        );
+        assert_eq!(
+            contract.claim_split(vec![], U128(0), vec![], None).err(),
+            Some(ClaimError::InvalidSplit)
+        );
+        // Split claims pay out tokens and require the claim deposit
+        let mut context = get_context(USER1.parse::<AccountId>().unwrap(), NO_DEPOSIT);
+        testing_env!(context.build());
+        assert_eq!(
+            contract
+                .claim_split(receivers.clone(), U128(100), vec![], None)
+                .err(),
+            Some(ClaimError::InvalidDeposit)
+        );
+        testing_env!(context.attached_deposit(ONE_YOCTO).build());
+        contract.weight_classes = vec![WeightClass {
+            min_weight: U128(1),
+            amount: U128(100),
+        }];
+        assert_eq!(
+            contract
+                .claim_split(receivers.clone(), U128(100), vec![], None)
+                .err(),
+            Some(ClaimError::WrongDenomination)
+        );
+        contract.weight_classes = vec![];
+
+        // A failed leg is owed to the claimed account, the other legs are paid
+        let account_id: AccountId = USER1.parse().unwrap();
+        contract.distributed = 100;
+        contract.reserved = 100;
+        assert!(contract.on_split_leg_transferred(
+            account_id.clone(),
+            "alice.testnet".parse().unwrap(),
+            U128(60),
+            0,
+            Ok(())
+        ));
+        assert!(!contract.on_split_leg_transferred(
+            account_id.clone(),
+            "bob.testnet".parse().unwrap(),
+            U128(40),
+            0,
+            Err(near_sdk::PromiseError::Failed)
+        ));
+        assert_eq!(contract.get_shortfall(account_id.clone()), Some(U128(40)));
+        assert_eq!(contract.reserved, 60);
+        assert_eq!(contract.get_distributed(), U128(60));
+        assert_eq!(contract.get_health().transfer_failures, 1);
+
+        // The split claim is recorded for the legs that were paid
+        let claim = PendingClaim {
+            split: receivers.clone(),
+            ..PendingClaim::new(account_id.clone(), U128(100), NO_DEPOSIT)
+        };
+        testing_env!(
+            context.build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![
+                PromiseResult::Successful(b"true".to_vec()),
+                PromiseResult::Successful(b"false".to_vec()),
+            ]
+        );
+        let outcome = contract.on_split_then_claimed(claim.clone());
+        assert_eq!(outcome.status, ClaimStatus::PartiallyPaid);
+        assert_eq!(outcome.amount, U128(60));
+        assert_eq!(outcome.receipt_index, Some(U64(0)));
+        assert_eq!(contract.reserved, 0);
+        assert_eq!(contract.get_accounting().distributed, U128(60));
+        assert_eq!(contract.get_claims(0, 10)[0].1, account_id);
+
+        // Without any paid leg, nothing is recorded
+        testing_env!(
+            context.build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed, PromiseResult::Failed]
+        );
+        let outcome = contract.on_split_then_claimed(claim);
+        assert_eq!(outcome.status, ClaimStatus::TransferFailed);
+        assert_eq!(outcome.receipt_index, None);
+        assert_eq!(contract.get_claims(0, 10).len(), 1);
+    }
+
+    #[test]
+    fn test_repeated_split_claim() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_idempotent_claims(true);
+        contract.internal_mark_leaf_claimed(USER1.parse().unwrap(), None);
+
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let receivers = vec![("alice.testnet".parse::<AccountId>().unwrap(), U128(100))];
+        assert!(matches!(
+            contract.claim_split(receivers, U128(100), vec![], None),
+            Ok(PromiseOrValue::Value(ClaimOutcome {
+                status: ClaimStatus::AlreadyClaimed,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_hash_encodings() {
+        let root = "42bb039d55571a5564e772449aab51904f292f69ea5efb6becde8f8f5c37d643";
+        let bytes = hex::decode(root).unwrap();
+        for encoded in [
+            format!("0x{}", root.to_uppercase()),
+            near_sdk::bs58::encode(&bytes).into_string(),
+            near_sdk::base64::engine::general_purpose::STANDARD.encode(&bytes),
+        ] {
+            assert_eq!(AirdropContract::decode_hash(&encoded), Ok(bytes.clone()));
+            assert_eq!(AirdropContract::normalize_root(&encoded), root);
+        }
+        assert_eq!(
+            AirdropContract::decode_hash("0x42bb"),
+            Err(ClaimError::InvalidProofEncoding)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid Merkle root")]
+    fn test_invalid_root() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        new_contract_with_root("0xabc".to_string());
+    }
+
+    #[test]
+    fn test_protocol_fee() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+        contract.set_fee_bps(250);
+        assert_eq!(contract.get_fee_bps(), 250);
+        assert_eq!(apply_bps(1_000_000, 250), 25_000);
+        assert_eq!(apply_bps(39, 250), 0);
+        assert_eq!(apply_bps(u128::MAX, 250), u128::MAX / 40);
+    }
+
+    #[test]
+    fn test_fee_exemptions() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let user1 = USER1.parse::<AccountId>().unwrap();
+        contract.set_fee_bps(250);
+        contract.set_claim_fee(NearToken::from_millinear(10));
+        assert_eq!(
+            contract.add_fee_exemptions(vec![user1.clone(), user1.clone()]),
+            1
+        );
+        assert_eq!(contract.get_fee_exemptions(0, 10), vec![user1.clone()]);
+
+        let claim = contract
+            .internal_reserve_claim(PendingClaim::new(
+                OWNER.parse().unwrap(),
+                U128(1_000),
+                NO_DEPOSIT,
+            ))
+            .unwrap();
+        assert_eq!(claim.protocol_fee, U128(25));
+
+        testing_env!(get_context(user1.clone(), ONE_YOCTO).build());
+        assert_eq!(contract.assert_claim_deposit(), Ok(()));
+        let claim = contract.internal_new_claim(user1.clone(), U128(1_000));
+        assert_eq!(claim.fee, U128(0));
+        let claim = contract.internal_reserve_claim(claim).unwrap();
+        assert_eq!(claim.protocol_fee, U128(0));
+
+        testing_env!(get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO).build());
+        assert_eq!(contract.remove_fee_exemptions(vec![user1.clone()]), 1);
+        assert!(!contract.is_fee_exempt(user1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Fee exceeds the maximum")]
+    fn test_protocol_fee_cap() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+        contract.set_fee_bps(MAX_FEE_BPS + 1);
+    }
+
+    #[test]
+    fn test_referral_stats() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+        contract.set_referral_bps(500);
+
+        let claim = PendingClaim {
+            account_id: USER1.parse::<AccountId>().unwrap(),
+            amount: U128(1_000),
+            claimer: USER1.parse::<AccountId>().unwrap(),
+            deposit: U128(1),
+            storage_deposit: U128(0),
+            fee: U128(0),
+            protocol_fee: U128(0),
+            forfeited: U128(0),
+            early_bird_bonus: U128(0),
+            referrer: Some(OWNER.parse::<AccountId>().unwrap()),
+            referral_bonus: U128(apply_bps(1_000, contract.get_referral_bps())),
+            memo: None,
+            transfer_call: None,
+            receiver_id: None,
+            social_deposit: U128(0),
+            badge_deposit: U128(0),
+            record_deposit: U128(0),
+            shortfall: U128(0),
+            pays_shortfall: false,
+            nonce: None,
+            root_version: None,
+            liquid_stake: false,
+            unwrap_wnear: false,
+            stream_duration: None,
+            lockup_duration: None,
+            lockup_deposit: U128(0),
+            lock_months: None,
+            lock_bonus: U128(0),
+            streak_bonus: U128(0),
+            pushed: false,
+            claim_key: None,
+            raffle_prize: false,
+            split: Vec::new(),
+        };
+        assert_eq!(
+            contract.on_ft_transfer_then_claimed(claim, Ok(())).status,
+            ClaimStatus::Success
+        );
+        assert_eq!(
+            contract.get_referral_stats(OWNER.parse::<AccountId>().unwrap()),
+            ReferralStats {
+                referrals: 1,
+                rewards: U128(50),
+            }
+        );
+    }
+
+    #[test]
+    fn test_claim_record() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_timestamp(1_000).block_height(42);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+        let account_id = USER1.parse::<AccountId>().unwrap();
+        assert_eq!(
+            contract.get_claim_record(account_id.clone(), None, None),
+            None
+        );
+
+        let claim = PendingClaim {
+            protocol_fee: U128(10),
+            ..PendingClaim::new(account_id.clone(), U128(1_000), NO_DEPOSIT)
+        };
+        assert_eq!(
+            contract.on_ft_transfer_then_claimed(claim, Ok(())).status,
+            ClaimStatus::Success
+        );
+        assert_eq!(
+            contract.get_claim_record(account_id, None, None),
+            Some(ClaimRecord {
+                index: U64(0),
+                amount: U128(990),
+                timestamp: U64(1_000),
+                block_height: U64(42),
+                referrer: None,
+                referral_bonus: U128(0),
+                badge_token_id: None,
+                round: 0,
+                nonce: None,
+            })
+        );
+
+        let claim = PendingClaim::new(OWNER.parse::<AccountId>().unwrap(), U128(5), NO_DEPOSIT);
+        assert_eq!(
+            contract.on_ft_transfer_then_claimed(claim, Ok(())).status,
+            ClaimStatus::Success
+        );
+        assert_eq!(
+            contract.get_claims(1, 10),
+            vec![(
+                U64(1),
+                OWNER.parse::<AccountId>().unwrap(),
+                U128(5),
+                U64(1_000)
+            )]
+        );
+        assert_eq!(contract.get_claims(0, 10).len(), 2);
+        assert!(contract.get_claims(5, 10).is_empty());
+    }
+
+    #[test]
+    fn test_decay_schedule() {
+        let schedule = DecaySchedule {
+            start_at: U64(1_000),
+            end_at: U64(2_000),
+            floor_bps: 2_000,
+        };
+        assert_eq!(schedule.claimable_bps(0), 10_000);
+        assert_eq!(schedule.claimable_bps(1_000), 10_000);
+        assert_eq!(schedule.claimable_bps(1_500), 6_000);
+        assert_eq!(schedule.claimable_bps(2_000), 2_000);
+        assert_eq!(schedule.claimable_bps(5_000), 2_000);
+
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_timestamp(1_500);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_decay_schedule(Some(schedule));
+        assert_eq!(contract.get_claimable_amount(U128(1_000)), U128(600));
+    }
+
+    #[test]
+    fn test_early_bird_bonus_is_bounded_by_pool() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_timestamp(1_000);
+        testing_env!(context.build());
+
+        let mut contract = new_contract();
+        contract.set_early_bird_bonus(
+            Some(EarlyBirdBonus {
+                bonus_bps: 2_000,
+                ends_at: U64(2_000),
+            }),
+            U128(300),
+        );
+
+        let claim = PendingClaim {
+            deposit: U128(0),
+            ..PendingClaim::new(USER1.parse::<AccountId>().unwrap(), U128(1_000), NO_DEPOSIT)
+        };
+        contract.internal_start_claim(claim.clone()).unwrap();
+        assert_eq!(contract.get_bonus_pool(), U128(100));
+
+        // Only the rest of the pool is left for the next claimer
+        let mut second = claim;
+        second.account_id = OWNER.parse::<AccountId>().unwrap();
+        contract.internal_start_claim(second).unwrap();
+        assert_eq!(contract.get_bonus_pool(), U128(0));
+    }
+
+    #[test]
+    fn test_claim_locked() {
+        let mut context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_timestamp(1_000);
+        testing_env!(context.build());
+        let mut contract = AirdropContract::new(
+            USER1.parse::<AccountId>().unwrap(),
+            TOKEN_CONTRACT.parse::<AccountId>().unwrap(),
+            "42bb039d55571a5564e772449aab51904f292f69ea5efb6becde8f8f5c37d643".to_string(),
+            None,
+            None,
+        );
+        contract.set_lock_boosts(
+            vec![LockBoost {
+                months: 6,
+                bonus_bps: 5_000,
+            }],
+            U128(600),
+        );
+        assert_eq!(
+            contract.claim_locked(U128(1_000), vec![], None, 3).err(),
+            Some(ClaimError::LockUnavailable)
+        );
+
+        let claim = PendingClaim {
+            deposit: U128(0),
+            lock_months: Some(6),
+            ..PendingClaim::new(USER1.parse::<AccountId>().unwrap(), U128(1_000), NO_DEPOSIT)
+        };
+        let claim = contract.internal_reserve_claim(claim).unwrap();
+        assert_eq!(claim.net_amount(), 1_500);
+        assert_eq!(contract.get_lock_bonus_pool(), U128(100));
+        // The rest of the pool cannot pay the bonus of another lock
+        let second = PendingClaim {
+            account_id: OWNER.parse::<AccountId>().unwrap(),
+            ..claim.clone()
+        };
+        assert_eq!(
+            contract.internal_reserve_claim(second).err(),
+            Some(ClaimError::LockUnavailable)
+        );
+
+        // Without treasury or referrer legs, nothing is transferred
+        assert!(matches!(
+            contract.internal_transfer(claim),
+            PromiseOrValue::Value(ClaimOutcome {
+                status: ClaimStatus::Success,
+                ..
+            })
+        ));
+        let unlocks_at = 1_000 + 6 * 30 * 24 * 60 * 60 * 1_000_000_000;
+        let lock = LockedBalance {
+            amount: U128(1_500),
+            unlocks_at: U64(unlocks_at),
+        };
+        assert_eq!(
+            contract.get_locked_balance(USER1.parse().unwrap()),
+            Some(lock.clone())
+        );
+        // Locked tokens are not reserved, so they do not block changes needing no claim in flight
+        assert_eq!(contract.reserved, 0);
+        assert_eq!(contract.get_accounting().locked, U128(1_500));
+        contract.set_leaf_domain(None);
+        // Nor are they swept, along with the rest of the lock bonus pool
+        assert!(contract.on_balance_then_sweep(U128(u128::MAX), Ok(U128(2_000))));
+        assert_eq!(
+            get_logs().last().unwrap(),
+            "Sweeping 400 tokens to @user1.testnet"
+        );
+
+        // A failed transfer locks the tokens again
+        context.block_timestamp(unlocks_at);
+        testing_env!(context.build());
+        contract.unlock();
+        assert_eq!(contract.get_locked_balance(USER1.parse().unwrap()), None);
+        assert!(!contract.on_unlocked(
+            USER1.parse().unwrap(),
+            lock.clone(),
+            Err(near_sdk::PromiseError::Failed)
+        ));
+        assert_eq!(
+            contract.get_locked_balance(USER1.parse().unwrap()),
+            Some(lock.clone())
+        );
+        assert!(contract.on_unlocked(USER1.parse().unwrap(), lock, Ok(())));
+        assert_eq!(contract.get_accounting().locked, U128(0));
     }
-    /// Query owner
-    pub fn owner(&self) -> AccountId {
-        self.owner_id.clone()
+
+    #[test]
+    #[should_panic(expected = "The tokens are locked until")]
+    fn test_unlock_before_lock_ends() {
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.locks.insert(
+            USER1.parse().unwrap(),
+            LockedBalance {
+                amount: U128(100),
+                unlocks_at: U64(1_000),
+            },
+        );
+        contract.unlock();
     }
-    /// Only owner can call. Transfer `amount` of given token to `to`.
-    #[payable]
-    pub fn withdraw_token(&mut self, amount: U128) -> Promise {
-        assert_one_yocto();
-        // Ensure only owner can call
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.owner_id,
-            "Only the owner can withdraw tokens"
+
+    #[test]
+    fn test_streak_bonus() {
+        testing_env!(get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO).build());
+        let mut contract = AirdropContract::new(
+            USER1.parse::<AccountId>().unwrap(),
+            TOKEN_CONTRACT.parse::<AccountId>().unwrap(),
+            "42bb039d55571a5564e772449aab51904f292f69ea5efb6becde8f8f5c37d643".to_string(),
+            None,
+            None,
         );
+        let streak_bonus = StreakBonus {
+            bonus_bps_per_round: 500,
+            max_bonus_bps: 1_000,
+        };
+        assert_eq!(streak_bonus.bonus_bps(1), 0);
+        assert_eq!(streak_bonus.bonus_bps(2), 500);
+        assert_eq!(streak_bonus.bonus_bps(12), 1_000);
+        contract.set_streak_bonus(Some(streak_bonus), U128(80));
 
-        Promise::new(self.token_contract.clone())
-            .function_call(
-                "ft_balance_of".to_string(),
-                serde_json::json!({
-                    "account_id": env::current_account_id()
-                })
-                .to_string()
-                .into_bytes(),
-                NearToken::from_near(0),
-                Gas::from_gas(10_000_000_000_000),
-            )
-            .then(
-                Self::ext(env::current_account_id())
-                    .with_static_gas(Gas::from_gas(30_000_000_000_000))
-                    .on_check_balance_then_withdraw(
-                        self.token_contract.clone(),
-                        self.owner_id.clone(),
-                        amount,
-                    ),
-            )
+        let claim = PendingClaim {
+            deposit: U128(0),
+            ..PendingClaim::new(USER1.parse::<AccountId>().unwrap(), U128(1_000), NO_DEPOSIT)
+        };
+        let first = contract.internal_reserve_claim(claim.clone()).unwrap();
+        assert_eq!(first.streak_bonus, U128(0));
+        contract.internal_complete_claim(first);
+        assert_eq!(contract.get_streak(USER1.parse().unwrap()), 1);
+
+        // The next round extends the streak
+        contract.round = 1;
+        assert_eq!(contract.get_streak(USER1.parse().unwrap()), 1);
+        let second = contract.internal_reserve_claim(claim.clone()).unwrap();
+        assert_eq!(second.net_amount(), 1_050);
+        contract.internal_complete_claim(second);
+        assert_eq!(contract.get_streak(USER1.parse().unwrap()), 2);
+
+        // Skipping a round breaks it, and the bonus is bounded by the pool
+        contract.round = 3;
+        assert_eq!(contract.get_streak(USER1.parse().unwrap()), 0);
+        contract.round = 2;
+        let third = contract.internal_reserve_claim(claim).unwrap();
+        assert_eq!(third.streak_bonus, U128(30));
+        assert_eq!(contract.get_streak_bonus_pool(), U128(0));
     }
-    #[private]
-    pub fn on_check_balance_then_withdraw(
-        &self,
-        token_contract: AccountId,
-        to: AccountId,
-        amount: U128,
-        #[callback_result] call_result: Result<Option<U128>, near_sdk::PromiseError>,
-    ) -> Promise {
-        let balance = match call_result {
-            Ok(Some(b)) => b.0,
-            _ => env::panic_str("Failed to get token balance"),
+
+    #[test]
+    fn test_claim_with_key() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let public_key: PublicKey = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp"
+            .parse()
+            .unwrap();
+        contract.add_claim_keys(
+            vec![public_key.clone()],
+            U128(100),
+            NearToken::from_millinear(100),
+        );
+        assert_eq!(contract.get_claim_key(public_key.clone()), Some(U128(100)));
+
+        // Signed by the claim key on the contract account itself
+        let mut context = get_context(env::current_account_id(), NO_DEPOSIT);
+        context.signer_account_pk(public_key.clone());
+        testing_env!(context.build());
+        contract.claim_with_key(USER1.parse().unwrap()).unwrap();
+        assert_eq!(contract.get_claim_key(public_key.clone()), None);
+        assert_eq!(contract.get_distributed(), U128(100));
+        // Key claims leave the leaf of the account and the root totals alone
+        assert!(!contract.has_claimed(USER1.parse().unwrap()));
+        assert_eq!(contract.get_root_claimed(), U128(0));
+        assert_eq!(
+            contract.claim_with_key(OWNER.parse().unwrap()).err(),
+            Some(ClaimError::InvalidClaimKey)
+        );
+
+        // A failed key claim makes the key usable again
+        let claim = PendingClaim {
+            claim_key: Some(public_key.clone()),
+            ..PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT)
         };
-        assert!(amount.0 <= balance, "Not enough token balance to withdraw");
+        contract.internal_rollback(&claim, ClaimStatus::TransferFailed);
+        assert_eq!(contract.get_claim_key(public_key), Some(U128(100)));
+    }
 
-        Promise::new(token_contract).function_call(
-            "ft_transfer".to_string(),
-            serde_json::json!({
-                "receiver_id": to,
-                "amount": amount,
-            })
-            .to_string()
-            .into_bytes(),
-            NearToken::from_yoctonear(1),
-            Gas::from_gas(10_000_000_000_000),
-        )
+    #[test]
+    #[should_panic(expected = "The withdrawal must be announced first")]
+    fn test_claim_keys_require_announcement() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        contract.set_withdrawal_delay(U64(500));
+        contract.add_claim_keys(
+            vec!["ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp"
+                .parse()
+                .unwrap()],
+            U128(100),
+            NearToken::from_millinear(100),
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use near_sdk::json_types::U128;
-    use near_sdk::{test_utils::VMContextBuilder, testing_env, AccountId, Gas};
+    #[test]
+    fn test_claim_with_email() {
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let email_hash = hex::encode(env::sha256(b"salt:user@example.com"));
 
-    // Constants for testing
-    const TOKEN_CONTRACT: &str = "token.testnet";
-    const OWNER: &str = "owner.testnet";
-    const USER1: &str = "user1.testnet";
+        // Email claims are disabled without an attestor
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        assert_eq!(
+            contract
+                .claim_with_email(email_hash.clone(), U128(100), vec![], vec![0; 64].into())
+                .err(),
+            Some(ClaimError::InvalidAttestation)
+        );
+
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let attestor_key: PublicKey = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp"
+            .parse()
+            .unwrap();
+        contract.set_attestor_key(Some(attestor_key.clone()));
+        assert_eq!(contract.get_attestor_key(), Some(attestor_key));
+
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        assert_eq!(
+            contract
+                .claim_with_email(email_hash.clone(), U128(100), vec![], vec![0; 64].into())
+                .err(),
+            Some(ClaimError::InvalidAttestation)
+        );
 
-    /// Helper function to create a mock context.
-    fn get_context(predecessor: AccountId, attached_deposit: u128) -> VMContextBuilder {
-        let mut builder = VMContextBuilder::new();
-        builder
-            .predecessor_account_id(predecessor)
-            .attached_deposit(NearToken::from_yoctonear(attached_deposit))
-            .prepaid_gas(Gas::from_gas(300_000_000_000_000)); // Allocate sufficient gas for testing
-        builder
+        // An email claimed by another account cannot be claimed again
+        contract
+            .claimed_emails
+            .insert(email_hash.clone(), OWNER.parse().unwrap());
+        assert_eq!(
+            contract
+                .claim_with_email(email_hash.clone(), U128(100), vec![], vec![0; 64].into())
+                .err(),
+            Some(ClaimError::AlreadyClaimed)
+        );
+        assert_eq!(
+            contract.get_email_claimant(email_hash),
+            Some(OWNER.parse().unwrap())
+        );
+        assert_eq!(
+            email_attestation_message(
+                &"airdrop.testnet".parse().unwrap(),
+                "ab",
+                &USER1.parse().unwrap()
+            ),
+            "airdrop.testnet:ab:user1.testnet"
+        );
     }
+
     #[test]
-    fn test_merkle_proof_verification() {
-        let context = get_context(OWNER.parse::<AccountId>().unwrap(), 0);
+    fn test_implicit_account() {
+        let implicit: AccountId =
+            "98793cd91a3f870fb126f66285808c7e094afcfc4eda8a970f6648cdf0dbd6de"
+                .parse()
+                .unwrap();
+        assert!(is_implicit_account(&implicit));
+        assert!(!is_implicit_account(&USER1.parse().unwrap()));
+        assert!(!is_implicit_account(
+            &"0x98793cd91a3f870fb126f66285808c7e094afcfc"
+                .parse()
+                .unwrap()
+        ));
+
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
         testing_env!(context.build());
+        let mut contract = new_contract();
+        assert_eq!(contract.get_implicit_account_funding(), None);
+        contract.set_implicit_account_funding(Some(NearToken::from_millinear(10)));
+        assert_eq!(
+            contract.get_implicit_account_funding(),
+            Some(NearToken::from_millinear(10))
+        );
+    }
 
-        AirdropContract::new(
-            OWNER.parse::<AccountId>().unwrap(),
-            TOKEN_CONTRACT.parse::<AccountId>().unwrap(),
-            "af6df487c9daa2c7d6ec7fb9a33f22d6af13323c1f0d9b1a7df3ec0aaea02e94".to_string(), // Replace with real Merkle Root
+    #[test]
+    fn test_compute_leaf() {
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), NO_DEPOSIT);
+        testing_env!(context.build());
+        let contract = new_contract();
+        let computed = contract.compute_leaf(" User1.Testnet ".to_string(), U128(100), None);
+        assert_eq!(computed.leaf, "user1.testnet:100");
+        assert_eq!(
+            computed.hash,
+            hex::encode(env::keccak256("user1.testnet:100".as_bytes()))
         );
+        assert_eq!(normalize_account_id("not valid"), None);
+    }
 
-        // Example Merkle proof for "user1.testnet + : + 100"
-        let leaf = "user1.testnet:100".to_string();
-        let proof = vec![
-            "154a0a614231d830d36a51e980c0cb836e8d2d718345e6c5e0e10bb3687ddb99".to_string(),
-            "86b99e84ab1b07c73445edf731d9c0d876c6229e36a5bf22c210690e2cdc18b2".to_string(),
+    #[test]
+    fn test_leaf_domain() {
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context
+            .current_account_id("airdrop.testnet".parse().unwrap())
+            .build());
+        let account_id = USER1.parse::<AccountId>().unwrap();
+        let leaf_domain = LeafDomain {
+            campaign_id: Some("s1".to_string()),
+        };
+        let leaf = leaf_domain.prefix_leaf(
+            &env::current_account_id(),
+            &LeafExtras::default().encode_leaf(&account_id, 100),
+        );
+        assert_eq!(leaf, "airdrop.testnet/s1|user1.testnet:100");
+        let mut contract = new_contract_with_root(hex::encode(env::keccak256(leaf.as_bytes())));
+
+        // The plain leaf of a tree shared with another deployment does not verify
+        let extras = LeafExtras::default();
+        assert_eq!(
+            contract.internal_verify_leaf(&account_id, U128(100), &[], &extras),
+            Err(ClaimError::InvalidProof)
+        );
+        contract.set_leaf_domain(Some(leaf_domain.clone()));
+        assert_eq!(contract.get_leaf_domain(), Some(leaf_domain));
+        assert_eq!(
+            contract
+                .compute_leaf(USER1.to_string(), U128(100), None)
+                .leaf,
+            leaf
+        );
+        assert!(contract
+            .internal_verify_leaf(&account_id, U128(100), &[], &extras)
+            .is_ok());
+
+        // Nor does the leaf of another campaign
+        contract.set_leaf_domain(Some(LeafDomain {
+            campaign_id: Some("s2".to_string()),
+        }));
+        assert_eq!(
+            contract.internal_verify_leaf(&account_id, U128(100), &[], &extras),
+            Err(ClaimError::InvalidProof)
+        );
+    }
+
+    #[test]
+    fn test_leaf_nonces() {
+        let extras = LeafExtras {
+            nonce: Some(U64(2)),
+            referrer: Some(OWNER.parse::<AccountId>().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            extras.encode_leaf(&USER1.parse::<AccountId>().unwrap(), 100),
+            "user1.testnet:100:2:owner.testnet"
+        );
+
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let account_id = USER1.parse::<AccountId>().unwrap();
+        let entries = [
+            format!("{}:100:1", account_id),
+            format!("{}:50:2", account_id),
         ];
+        let leaves: Vec<Vec<u8>> = entries
+            .iter()
+            .map(|leaf| env::keccak256(leaf.as_bytes()))
+            .collect();
+        let root = merkle::process_proof(
+            HashAlgorithm::Keccak256,
+            LeafFormat::V1,
+            entries[0].as_bytes(),
+            &[leaves[1].clone()],
+        );
+        let mut contract = new_contract_with_root(hex::encode(root));
 
-        let valid = AirdropContract::verify_merkle_proof(
-            leaf,
-            &"af6df487c9daa2c7d6ec7fb9a33f22d6af13323c1f0d9b1a7df3ec0aaea02e94".to_string(),
-            &proof,
+        // Both leaves of the account are claimable, each once
+        let first = LeafExtras {
+            nonce: Some(U64(1)),
+            ..Default::default()
+        };
+        let second = LeafExtras {
+            nonce: Some(U64(2)),
+            ..Default::default()
+        };
+        contract
+            .internal_verify_leaf(&account_id, U128(100), &[hex::encode(&leaves[1])], &first)
+            .unwrap();
+        let claim = PendingClaim {
+            nonce: first.nonce,
+            ..PendingClaim::new(account_id.clone(), U128(100), NO_DEPOSIT)
+        };
+        let first_claim = contract.internal_reserve_claim(claim).unwrap();
+        assert_eq!(
+            contract.internal_verify_leaf(
+                &account_id,
+                U128(100),
+                &[hex::encode(&leaves[1])],
+                &first
+            ),
+            Err(ClaimError::AlreadyClaimed)
         );
+        contract
+            .internal_verify_leaf(&account_id, U128(50), &[hex::encode(&leaves[0])], &second)
+            .unwrap();
+        assert!(!contract.has_claimed(account_id.clone()));
 
-        assert!(valid, "Merkle proof should be valid for user1.testnet.");
+        // Each leaf keeps its own receipt
+        let second_claim = PendingClaim {
+            nonce: second.nonce,
+            ..PendingClaim::new(account_id.clone(), U128(50), NO_DEPOSIT)
+        };
+        let second_claim = contract.internal_reserve_claim(second_claim).unwrap();
+        contract.on_ft_transfer_then_claimed(first_claim, Ok(()));
+        contract.on_ft_transfer_then_claimed(second_claim, Ok(()));
+        let record = |contract: &AirdropContract, nonce| {
+            contract
+                .get_claim_record(account_id.clone(), None, nonce)
+                .map(|record| record.amount)
+        };
+        assert_eq!(record(&contract, first.nonce), Some(U128(100)));
+        assert_eq!(record(&contract, second.nonce), Some(U128(50)));
+        assert_eq!(record(&contract, None), None);
+
+        testing_env!(get_context(OWNER.parse().unwrap(), ONE_YOCTO).build());
+        contract.admin_unclaim(account_id.clone(), None, first.nonce);
+        assert!(!contract.is_leaf_claimed(&account_id, first.nonce));
+        assert!(contract.is_leaf_claimed(&account_id, second.nonce));
+        assert_eq!(record(&contract, first.nonce), None);
+        assert_eq!(record(&contract, second.nonce), Some(U128(50)));
+    }
+
+    #[test]
+    fn test_leaf_expiry() {
+        let extras = LeafExtras {
+            referrer: Some(OWNER.parse::<AccountId>().unwrap()),
+            expires_at: Some(U64(2_000)),
+            nonce: None,
+            tier: None,
+            stream_duration: None,
+            lockup_duration: None,
+        };
+        assert_eq!(
+            extras.encode_leaf(&USER1.parse::<AccountId>().unwrap(), 100),
+            "user1.testnet:100:owner.testnet@2000"
+        );
+
+        let mut context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_timestamp(2_000);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], Some(extras), None, None, None, None)
+                .err(),
+            Some(ClaimError::LeafExpired)
+        );
+    }
+
+    #[test]
+    fn test_claim_tiers() {
+        let extras = LeafExtras {
+            tier: Some(1),
+            expires_at: Some(U64(2_000)),
+            ..Default::default()
+        };
+        assert_eq!(
+            extras.encode_leaf(&USER1.parse::<AccountId>().unwrap(), 100),
+            "user1.testnet:100#1@2000"
+        );
+
+        let mut context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        context.block_timestamp(1_000);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        assert_eq!(contract.assert_tier_open(1), Err(ClaimError::TierNotOpen));
+        contract.set_tier_start(1, Some(U64(1_500)));
+        assert_eq!(contract.get_tier_start(1), Some(U64(1_500)));
+        assert_eq!(contract.assert_tier_open(1), Err(ClaimError::TierNotOpen));
+        assert_eq!(
+            contract
+                .claim_airdrop(U128(100), vec![], Some(extras), None, None, None, None)
+                .err(),
+            Some(ClaimError::TierNotOpen)
+        );
+
+        context.block_timestamp(1_500);
+        testing_env!(context.build());
+        assert_eq!(contract.assert_tier_open(1), Ok(()));
+        contract.set_tier_start(1, None);
+        assert_eq!(contract.get_tier_start(1), None);
+        assert_eq!(contract.assert_tier_open(1), Err(ClaimError::TierNotOpen));
+    }
+
+    #[test]
+    fn test_streaming_payouts() {
+        let extras = LeafExtras {
+            stream_duration: Some(U64(50)),
+            ..Default::default()
+        };
+        assert_eq!(
+            extras.encode_leaf(&USER1.parse::<AccountId>().unwrap(), 100),
+            "user1.testnet:100~50"
+        );
+
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        let claim = PendingClaim {
+            stream_duration: Some(U64(50)),
+            ..PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT)
+        };
+        assert_eq!(
+            contract.assert_streamable(&claim),
+            Err(ClaimError::StreamingUnavailable)
+        );
+        contract.set_streaming_contract(Some("streaming.r-v2.testnet".parse().unwrap()));
+        assert_eq!(contract.assert_streamable(&claim), Ok(()));
+        assert_eq!(
+            AirdropContract::transfer_gas(&claim),
+            GAS_FOR_TRANSFER_CALLBACK.saturating_add(GAS_FOR_FT_TRANSFER_CALL)
+        );
+
+        let transfer_call = contract.stream_transfer_call(&claim).unwrap();
+        assert_eq!(transfer_call.receiver_id.as_str(), "streaming.r-v2.testnet");
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&transfer_call.msg).unwrap(),
+            json!({
+                "Create": {
+                    "request": {
+                        "owner_id": env::current_account_id(),
+                        "receiver_id": USER1,
+                        "tokens_per_sec": "2",
+                        "is_auto_start_enabled": true,
+                        "is_locked": true,
+                    },
+                },
+            })
+        );
+        // Streams cannot be combined with other payouts
+        let swap = PendingClaim {
+            transfer_call: Some(transfer_call),
+            ..claim.clone()
+        };
+        assert_eq!(
+            contract.assert_streamable(&swap),
+            Err(ClaimError::StreamingUnavailable)
+        );
+
+        // A stream that was not created fails the claim instead of paying a lump sum
+        assert_eq!(
+            contract
+                .on_ft_transfer_call_then_claimed(claim, Ok(U128(0)))
+                .status,
+            ClaimStatus::TransferFailed
+        );
+    }
+
+    #[test]
+    fn test_claim_into_lockup() {
+        let extras = LeafExtras {
+            lockup_duration: Some(U64(1_000)),
+            expires_at: Some(U64(2_000)),
+            ..Default::default()
+        };
+        assert_eq!(
+            extras.encode_leaf(&USER1.parse::<AccountId>().unwrap(), 100),
+            "user1.testnet:100^1000@2000"
+        );
+
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        assert_eq!(
+            contract
+                .claim_into_lockup(U128(100), vec![], extras.clone(), None)
+                .err(),
+            Some(ClaimError::LockupUnavailable)
+        );
+        contract.set_lockup_factory(Some(LockupFactory {
+            factory_id: "lockup.testnet".parse().unwrap(),
+            deposit: NearToken::from_near(4),
+        }));
+        let lockup_id = contract.get_lockup_account(USER1.parse().unwrap()).unwrap();
+        assert_eq!(
+            lockup_id.as_str(),
+            format!(
+                "{}.lockup.testnet",
+                &hex::encode(env::sha256(USER1.as_bytes()))[..40]
+            )
+        );
+
+        // Locked leaves cannot be claimed to the account itself
+        let claim = PendingClaim {
+            lockup_duration: Some(U64(1_000)),
+            ..PendingClaim::new(USER1.parse().unwrap(), U128(100), NO_DEPOSIT)
+        };
+        assert_eq!(
+            contract.assert_lockup(&claim),
+            Err(ClaimError::LockupRequired)
+        );
+        let claim = PendingClaim {
+            receiver_id: Some(lockup_id),
+            lockup_deposit: U128(NearToken::from_near(4).as_yoctonear()),
+            ..claim
+        };
+        assert_eq!(contract.assert_lockup(&claim), Ok(()));
+        assert_eq!(
+            AirdropContract::completion_gas(&claim),
+            Gas::from_tgas(5).saturating_add(GAS_FOR_LOCKUP)
+        );
+    }
+
+    #[test]
+    fn test_terms_acceptance() {
+        let terms_v1 = Base58CryptoHash::from([1u8; 32]);
+        let terms_v2 = Base58CryptoHash::from([2u8; 32]);
+        let user1 = USER1.parse::<AccountId>().unwrap();
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO);
+        testing_env!(context.build());
+        let mut contract = new_contract();
+        assert_eq!(contract.assert_terms_accepted(&user1), Ok(()));
+        contract.set_terms_hash(Some(terms_v1));
+        assert_eq!(
+            contract.assert_terms_accepted(&user1),
+            Err(ClaimError::TermsNotAccepted)
+        );
+
+        testing_env!(get_context(user1.clone(), NearToken::from_near(1)).build());
+        contract.accept_terms(terms_v1);
+        assert_eq!(contract.assert_terms_accepted(&user1), Ok(()));
+        assert_eq!(contract.get_terms_acceptance_count(), 1);
+        let acceptances = contract.get_terms_acceptances(0, 10);
+        assert_eq!(acceptances[0].0, user1);
+        assert_eq!(acceptances[0].1.terms_hash, terms_v1);
+
+        // New terms must be accepted again
+        testing_env!(get_context(OWNER.parse::<AccountId>().unwrap(), ONE_YOCTO).build());
+        contract.set_terms_hash(Some(terms_v2));
+        assert_eq!(
+            contract.assert_terms_accepted(&user1),
+            Err(ClaimError::TermsNotAccepted)
+        );
+        testing_env!(get_context(user1.clone(), NearToken::from_near(1)).build());
+        contract.accept_terms(terms_v2);
+        assert_eq!(contract.assert_terms_accepted(&user1), Ok(()));
+        assert_eq!(contract.get_terms_acceptance_count(), 1);
+        assert_eq!(
+            contract
+                .get_terms_acceptance(user1)
+                .map(|acceptance| acceptance.terms_hash),
+            Some(terms_v2)
+        );
     }
 
     #[test]
     #[should_panic]
     fn test_claim_airdrop() {
-        let context = get_context(OWNER.parse::<AccountId>().unwrap(), 0);
+        let context = get_context(OWNER.parse::<AccountId>().unwrap(), NO_DEPOSIT);
         testing_env!(context.build());
 
-        let mut contract = AirdropContract::new(
-            OWNER.parse::<AccountId>().unwrap(),
-            TOKEN_CONTRACT.parse::<AccountId>().unwrap(),
-            "42bb039d55571a5564e772449aab51904f292f69ea5efb6becde8f8f5c37d643".to_string(), // Replace with real Merkle Root
-        );
+        let mut contract = new_contract();
 
         // Example Merkle proof for "user1.testnet + : + 100"
         let proof = vec![
@@ -352,15 +7953,19 @@ mod tests {
             "9674039b49ffcb659ac14ed833f9e6c9070d457a36ef0a5a28bc257e145c8160".to_string(),
         ];
 
-        let context = get_context(USER1.parse::<AccountId>().unwrap(), 1);
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
         testing_env!(context.build());
 
-        contract.claim_airdrop(U128(100), proof);
+        contract
+            .claim_airdrop(U128(100), proof, None, None, None, None, None)
+            .unwrap();
 
         // Verify that the user cannot claim again
-        let context = get_context(USER1.parse::<AccountId>().unwrap(), 1);
+        let context = get_context(USER1.parse::<AccountId>().unwrap(), ONE_YOCTO);
         testing_env!(context.build());
 
-        contract.claim_airdrop(U128(100), vec![]);
+        contract
+            .claim_airdrop(U128(100), vec![], None, None, None, None, None)
+            .unwrap();
     }
 }
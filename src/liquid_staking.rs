@@ -0,0 +1,161 @@
+use near_sdk::json_types::U128;
+use near_sdk::{
+    env, log, near, require, serde_json, AccountId, Gas, NearToken, Promise, PromiseOrValue,
+    PromiseResult,
+};
+
+use crate::{
+    AirdropContract, AirdropContractExt, ClaimError, ClaimOutcome, Denomination, LeafExtras,
+    PendingClaim, GAS_FOR_FT_TRANSFER, GAS_FOR_STORAGE_DEPOSIT, STORAGE_DEPOSIT_AMOUNT,
+};
+
+/// Gas of the `deposit_and_stake` call of the liquid staking contract.
+const GAS_FOR_DEPOSIT_AND_STAKE: Gas = Gas::from_tgas(50);
+/// Gas of `on_liquid_staked`, without the completion of the claim.
+const GAS_FOR_STAKE_CALLBACK: Gas = Gas::from_tgas(10);
+/// Gas the unwrapped claim needs to stake the NEAR and send the liquid staking tokens:
+/// `GAS_FOR_DEPOSIT_AND_STAKE`, `GAS_FOR_STAKE_CALLBACK`, and the storage_deposit and
+/// ft_transfer of the tokens.
+pub(crate) const GAS_FOR_LIQUID_STAKING: Gas = Gas::from_tgas(90);
+
+#[near]
+impl AirdropContract {
+    /// Sets the liquid staking contract used by `claim_and_liquid_stake`, e.g. Meta Pool or
+    /// LiNEAR, or disables it with `None` (only callable by the owner, when the airdropped
+    /// token is wNEAR).
+    #[payable]
+    pub fn set_liquid_staking_contract(&mut self, liquid_staking_contract: Option<AccountId>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_liquid_staking_contract");
+        if liquid_staking_contract.is_some() {
+            require!(self.is_wnear(), "Only wNEAR claims can be liquid staked");
+        }
+        self.liquid_staking_contract = liquid_staking_contract;
+        log!(
+            "Liquid staking contract set to {:?}",
+            self.liquid_staking_contract
+        );
+    }
+
+    /// Returns the liquid staking contract used by `claim_and_liquid_stake`, if any.
+    pub fn get_liquid_staking_contract(&self) -> Option<AccountId> {
+        self.liquid_staking_contract.clone()
+    }
+
+    /// Claims like `claim_airdrop`, but unwraps the claimed wNEAR, stakes it with the liquid
+    /// staking contract and sends the liquid staking tokens to the caller.
+    #[payable]
+    #[handle_result]
+    pub fn claim_and_liquid_stake(
+        &mut self,
+        amount: U128,
+        merkle_proof: Vec<String>,
+        extras: Option<LeafExtras>,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_claim_deposit()?;
+        self.assert_denomination(Denomination::Tokens)?;
+        if self.liquid_staking_contract.is_none() {
+            return Err(ClaimError::LiquidStakingUnavailable);
+        }
+        let account_id = env::predecessor_account_id();
+        let extras = extras.unwrap_or_default();
+        if let Some(outcome) = self.internal_repeated_claim(&account_id, extras.nonce) {
+            return Ok(PromiseOrValue::Value(outcome));
+        }
+        self.internal_count_relayed_claim()?;
+        self.internal_verify_leaf(&account_id, amount, &merkle_proof, &extras)?;
+
+        self.internal_start_claim(PendingClaim {
+            referrer: extras.referrer,
+            liquid_stake: true,
+            nonce: extras.nonce,
+            stream_duration: extras.stream_duration,
+            lockup_duration: extras.lockup_duration,
+            ..self.internal_new_claim(account_id, amount)
+        })
+    }
+
+    /// Callback: Sends the liquid staking tokens minted for `claim` to the claimed account, or
+    /// the NEAR itself if the staking failed, and marks the claim as successful.
+    #[private]
+    pub fn on_liquid_staked(&mut self, claim: PendingClaim) -> ClaimOutcome {
+        let PromiseResult::Successful(result) = env::promise_result(0) else {
+            // The deposit was refunded to this contract
+            log!(
+                "Liquid staking failed, sending NEAR to @{}",
+                claim.receiver_id()
+            );
+            Promise::new(claim.receiver_id().clone())
+                .transfer(NearToken::from_yoctonear(claim.net_amount()));
+            return self.internal_complete_claim(claim);
+        };
+        // The NEAR is staked, only the amount minted is unknown if the result is not readable
+        let shares = serde_json::from_slice::<U128>(&result).ok();
+        let (Some(shares), Some(liquid_staking_contract)) =
+            (shares, self.liquid_staking_contract.clone())
+        else {
+            log!(
+                "Liquid staking tokens of @{} left with the contract",
+                claim.account_id
+            );
+            return self.internal_complete_claim(claim);
+        };
+        log!(
+            "Staked {} yoctoNEAR of @{} for {} liquid staking tokens",
+            claim.net_amount(),
+            claim.account_id,
+            shares.0
+        );
+        // Registered in the same batch, so a missing registration cannot strand the tokens
+        Promise::new(liquid_staking_contract)
+            .function_call(
+                "storage_deposit".to_string(),
+                Self::storage_deposit_args(claim.receiver_id()),
+                STORAGE_DEPOSIT_AMOUNT,
+                GAS_FOR_STORAGE_DEPOSIT,
+            )
+            .function_call(
+                "ft_transfer".to_string(),
+                Self::ft_transfer_args(claim.receiver_id(), shares.0, claim.memo.as_deref()),
+                NearToken::from_yoctonear(1),
+                GAS_FOR_FT_TRANSFER,
+            );
+        self.internal_complete_claim(claim)
+    }
+}
+
+impl AirdropContract {
+    /// Stakes the unwrapped NEAR of `claim` with the liquid staking contract.
+    pub(crate) fn internal_liquid_stake(
+        &mut self,
+        claim: PendingClaim,
+    ) -> PromiseOrValue<ClaimOutcome> {
+        let Some(liquid_staking_contract) = self.liquid_staking_contract.clone() else {
+            // Disabled since the claim started
+            log!(
+                "Liquid staking disabled, sending NEAR to @{}",
+                claim.receiver_id()
+            );
+            Promise::new(claim.receiver_id().clone())
+                .transfer(NearToken::from_yoctonear(claim.net_amount()));
+            return PromiseOrValue::Value(self.internal_complete_claim(claim));
+        };
+        let callback_gas = Self::completion_gas(&claim)
+            .saturating_add(GAS_FOR_STAKE_CALLBACK)
+            .saturating_add(GAS_FOR_STORAGE_DEPOSIT)
+            .saturating_add(GAS_FOR_FT_TRANSFER);
+        Promise::new(liquid_staking_contract)
+            .function_call(
+                "deposit_and_stake".to_string(),
+                vec![],
+                NearToken::from_yoctonear(claim.net_amount()),
+                GAS_FOR_DEPOSIT_AND_STAKE,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(callback_gas)
+                    .on_liquid_staked(claim),
+            )
+            .into()
+    }
+}
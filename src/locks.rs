@@ -0,0 +1,211 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{
+    assert_one_yocto, env, log, near, require, AccountId, Gas, NearToken, Promise, PromiseError,
+    PromiseOrValue,
+};
+
+use crate::{
+    apply_bps, AirdropContract, AirdropContractExt, ClaimError, ClaimOutcome, Denomination,
+    LeafExtras, PendingClaim, GAS_FOR_FT_TRANSFER,
+};
+
+/// Length of a lock month, in nanoseconds.
+const LOCK_MONTH: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+/// Gas of `on_unlocked`.
+const GAS_FOR_UNLOCK_CALLBACK: Gas = Gas::from_tgas(5);
+
+/// Bonus of claims locked in the contract for `months`, see `claim_locked`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LockBoost {
+    pub months: u16,
+    /// Bonus in basis points of the claimed amount, e.g. 5000 for 1.5x.
+    pub bonus_bps: u16,
+}
+
+/// Boosted claims of an account held by the contract until `unlocks_at`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LockedBalance {
+    pub amount: U128,
+    /// Time from which `unlock` transfers the tokens, in nanoseconds since the Unix epoch.
+    pub unlocks_at: U64,
+}
+
+#[near]
+impl AirdropContract {
+    /// Sets the lock periods offered by `claim_locked` and the pool their bonuses are paid
+    /// from (only callable by the owner). An empty list disables locked claims. Locks stop
+    /// being offered once the pool is exhausted.
+    #[payable]
+    pub fn set_lock_boosts(&mut self, lock_boosts: Vec<LockBoost>, bonus_pool: U128) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_lock_boosts");
+        for (i, boost) in lock_boosts.iter().enumerate() {
+            require!(boost.months > 0, "Locks must last at least a month");
+            require!(
+                lock_boosts[..i]
+                    .iter()
+                    .all(|other| other.months != boost.months),
+                "Duplicate lock period"
+            );
+        }
+        self.lock_boosts = lock_boosts;
+        self.lock_bonus_pool = bonus_pool.0;
+        log!("Lock boosts updated, bonus pool {}", bonus_pool.0);
+    }
+
+    /// Returns the lock periods offered by `claim_locked`.
+    pub fn get_lock_boosts(&self) -> Vec<LockBoost> {
+        self.lock_boosts.clone()
+    }
+
+    /// Returns the tokens left to pay lock bonuses.
+    pub fn get_lock_bonus_pool(&self) -> U128 {
+        U128(self.lock_bonus_pool)
+    }
+
+    /// Returns the tokens `account_id` has locked in the contract, if any.
+    pub fn get_locked_balance(&self, account_id: AccountId) -> Option<LockedBalance> {
+        self.locks.get(&account_id).cloned()
+    }
+
+    /// Claims like `claim_airdrop`, but locks the tokens in the contract for `months` in
+    /// exchange for the bonus of that lock period. The tokens are transferred by `unlock` once
+    /// the lock ends.
+    #[payable]
+    #[handle_result]
+    pub fn claim_locked(
+        &mut self,
+        amount: U128,
+        merkle_proof: Vec<String>,
+        extras: Option<LeafExtras>,
+        months: u16,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_claim_deposit()?;
+        self.assert_denomination(Denomination::Tokens)?;
+        if !self.lock_boosts.iter().any(|boost| boost.months == months) {
+            return Err(ClaimError::LockUnavailable);
+        }
+        let account_id = env::predecessor_account_id();
+        let extras = extras.unwrap_or_default();
+        if let Some(outcome) = self.internal_repeated_claim(&account_id, extras.nonce) {
+            return Ok(PromiseOrValue::Value(outcome));
+        }
+        self.internal_count_relayed_claim()?;
+        self.internal_verify_leaf(&account_id, amount, &merkle_proof, &extras)?;
+
+        self.internal_start_claim(PendingClaim {
+            referrer: extras.referrer,
+            nonce: extras.nonce,
+            stream_duration: extras.stream_duration,
+            lockup_duration: extras.lockup_duration,
+            lock_months: Some(months),
+            ..self.internal_new_claim(account_id, amount)
+        })
+    }
+
+    /// Transfers the caller's locked tokens once their lock has ended.
+    #[payable]
+    pub fn unlock(&mut self) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let lock = self
+            .locks
+            .remove(&account_id)
+            .unwrap_or_else(|| env::panic_str("No locked tokens"));
+        if env::block_timestamp() < lock.unlocks_at.0 {
+            env::panic_str(&format!(
+                "The tokens are locked until {}",
+                lock.unlocks_at.0
+            ));
+        }
+        Promise::new(self.token_contract.clone())
+            .function_call(
+                "ft_transfer".to_string(),
+                Self::ft_transfer_args(&account_id, lock.amount.0, None),
+                NearToken::from_yoctonear(1),
+                GAS_FOR_FT_TRANSFER,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_UNLOCK_CALLBACK)
+                    .on_unlocked(account_id, lock),
+            )
+    }
+
+    /// Callback: Releases the unlocked tokens, or locks them again if the transfer failed.
+    #[private]
+    pub fn on_unlocked(
+        &mut self,
+        account_id: AccountId,
+        lock: LockedBalance,
+        #[callback_result] call_result: Result<(), PromiseError>,
+    ) -> bool {
+        if call_result.is_err() {
+            log!("Unlock of @{} failed", account_id);
+            self.internal_add_lock(account_id, lock);
+            return false;
+        }
+        self.locked = self.locked.saturating_sub(lock.amount.0);
+        log!("Unlocked {} tokens of @{}", lock.amount.0, account_id);
+        true
+    }
+}
+
+impl AirdropContract {
+    /// Takes the bonus of locking `payout` for `months` from the lock bonus pool.
+    pub(crate) fn internal_take_lock_bonus(
+        &mut self,
+        months: u16,
+        payout: u128,
+    ) -> Result<u128, ClaimError> {
+        let boost = self
+            .lock_boosts
+            .iter()
+            .find(|boost| boost.months == months)
+            .ok_or(ClaimError::LockUnavailable)?;
+        let bonus = apply_bps(payout, boost.bonus_bps);
+        if bonus > self.lock_bonus_pool {
+            return Err(ClaimError::LockUnavailable);
+        }
+        self.lock_bonus_pool -= bonus;
+        Ok(bonus)
+    }
+
+    /// Locks the tokens of the successful `claim` if it is a locked claim, counting them as
+    /// locked until they are unlocked.
+    pub(crate) fn internal_lock_claim(&mut self, claim: &PendingClaim) {
+        let Some(months) = claim.lock_months else {
+            return;
+        };
+        let lock = LockedBalance {
+            amount: U128(claim.net_amount()),
+            unlocks_at: U64(env::block_timestamp().saturating_add(u64::from(months) * LOCK_MONTH)),
+        };
+        log!(
+            "Locked {} tokens of @{} until {}",
+            lock.amount.0,
+            claim.account_id,
+            lock.unlocks_at.0
+        );
+        self.locked = self
+            .locked
+            .checked_add(claim.net_amount())
+            .unwrap_or_else(|| env::panic_str("Accounting overflow"));
+        self.internal_add_lock(claim.account_id.clone(), lock);
+    }
+
+    /// Adds `lock` to the locked tokens of `account_id`, which unlock with the latest of
+    /// their locks.
+    fn internal_add_lock(&mut self, account_id: AccountId, lock: LockedBalance) {
+        let lock = match self.locks.get(&account_id) {
+            Some(existing) => LockedBalance {
+                amount: U128(existing.amount.0 + lock.amount.0),
+                unlocks_at: U64(existing.unlocks_at.0.max(lock.unlocks_at.0)),
+            },
+            None => lock,
+        };
+        self.locks.insert(account_id, lock);
+    }
+}
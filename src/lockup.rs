@@ -0,0 +1,174 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde_json::json;
+use near_sdk::{
+    env, log, near, require, AccountId, Gas, NearToken, Promise, PromiseOrValue, PromiseResult,
+};
+
+use crate::{
+    AirdropContract, AirdropContractExt, ClaimError, ClaimOutcome, Denomination, LeafExtras,
+    PendingClaim, NO_DEPOSIT,
+};
+
+/// Gas of the `create` call of the lockup factory.
+const GAS_FOR_LOCKUP_CREATE: Gas = Gas::from_tgas(80);
+/// Gas of `on_lockup_created`.
+const GAS_FOR_LOCKUP_CALLBACK: Gas = Gas::from_tgas(5);
+/// Gas the completion of a claim needs to create its lockup, `GAS_FOR_LOCKUP_CREATE` and
+/// `GAS_FOR_LOCKUP_CALLBACK`.
+pub(crate) const GAS_FOR_LOCKUP: Gas = Gas::from_tgas(85);
+
+/// Factory of the per-account lockup contracts receiving leaves with a lockup duration.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LockupFactory {
+    /// Factory creating `<hash>.<factory_id>` lockups with
+    /// `create(owner_account_id, lockup_duration)`.
+    pub factory_id: AccountId,
+    /// Deposit of a lockup creation, attached by claimers creating their lockup.
+    pub deposit: NearToken,
+}
+
+#[near]
+impl AirdropContract {
+    /// Sets the factory of the lockups receiving leaves with a lockup duration, or disables
+    /// lockups with `None` (only callable by the owner).
+    #[payable]
+    pub fn set_lockup_factory(&mut self, lockup_factory: Option<LockupFactory>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_lockup_factory");
+        if let Some(lockup_factory) = &lockup_factory {
+            require!(
+                !lockup_factory.deposit.is_zero(),
+                "The deposit must cover the creation of the lockups"
+            );
+        }
+        self.lockup_factory = lockup_factory;
+        log!("Lockup factory set to {:?}", self.lockup_factory);
+    }
+
+    /// Returns the factory of the lockups receiving leaves with a lockup duration, if any.
+    pub fn get_lockup_factory(&self) -> Option<LockupFactory> {
+        self.lockup_factory.clone()
+    }
+
+    /// Returns the lockup account of `account_id`, if a lockup factory is set.
+    pub fn get_lockup_account(&self, account_id: AccountId) -> Option<AccountId> {
+        self.lockup_account_id(&account_id)
+    }
+
+    /// Claims a leaf with a lockup duration into the caller's lockup account.
+    /// - `create_lockup`: Creates the lockup once the claim succeeds, for the deposit of the
+    ///   lockup factory attached on top of the claim deposit. Without it the lockup must
+    ///   already exist. The deposit is refunded if the creation fails.
+    #[payable]
+    #[handle_result]
+    pub fn claim_into_lockup(
+        &mut self,
+        amount: U128,
+        merkle_proof: Vec<String>,
+        extras: LeafExtras,
+        create_lockup: Option<bool>,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        let lockup_deposit = match &self.lockup_factory {
+            Some(lockup_factory) if create_lockup.unwrap_or(false) => lockup_factory.deposit,
+            _ => NO_DEPOSIT,
+        };
+        self.assert_claim_deposit_with(lockup_deposit)?;
+        self.assert_denomination(Denomination::Tokens)?;
+        let account_id = env::predecessor_account_id();
+        let lockup_id = self
+            .lockup_account_id(&account_id)
+            .filter(|_| extras.lockup_duration.is_some())
+            .ok_or(ClaimError::LockupUnavailable)?;
+        if let Some(outcome) = self.internal_repeated_claim(&account_id, extras.nonce) {
+            return Ok(PromiseOrValue::Value(outcome));
+        }
+        self.internal_count_relayed_claim()?;
+        self.internal_verify_leaf(&account_id, amount, &merkle_proof, &extras)?;
+
+        self.internal_start_claim(PendingClaim {
+            referrer: extras.referrer,
+            receiver_id: Some(lockup_id),
+            nonce: extras.nonce,
+            lockup_duration: extras.lockup_duration,
+            lockup_deposit: U128(lockup_deposit.as_yoctonear()),
+            ..self.internal_new_claim(account_id, amount)
+        })
+    }
+
+    /// Callback: Refunds the creation deposit to `claimer` if the lockup of `account_id` was
+    /// not created, e.g. because it already exists. The claim itself succeeded either way.
+    #[private]
+    pub fn on_lockup_created(
+        &mut self,
+        account_id: AccountId,
+        claimer: AccountId,
+        deposit: U128,
+    ) -> bool {
+        if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            log!("Lockup creation for @{} failed", account_id);
+            Promise::new(claimer).transfer(NearToken::from_yoctonear(deposit.0));
+            return false;
+        }
+        log!("Lockup created for @{}", account_id);
+        true
+    }
+}
+
+impl AirdropContract {
+    /// Lockup account of `owner_id`, named like the NEAR lockups after the first 20 bytes of
+    /// the sha256 of the owner.
+    pub(crate) fn lockup_account_id(&self, owner_id: &AccountId) -> Option<AccountId> {
+        let lockup_factory = self.lockup_factory.as_ref()?;
+        let hash = hex::encode(&env::sha256(owner_id.as_bytes())[..20]);
+        format!("{}.{}", hash, lockup_factory.factory_id)
+            .parse()
+            .ok()
+    }
+
+    /// Leaves with a lockup duration can only be paid to the lockup of the claimed account.
+    pub(crate) fn assert_lockup(&self, claim: &PendingClaim) -> Result<(), ClaimError> {
+        if claim.lockup_duration.is_some()
+            && (claim.receiver_id.is_none()
+                || claim.receiver_id != self.lockup_account_id(&claim.account_id)
+                || claim.transfer_call.is_some()
+                || claim.liquid_stake
+                || claim.stream_duration.is_some()
+                || claim.lock_months.is_some())
+        {
+            return Err(ClaimError::LockupRequired);
+        }
+        Ok(())
+    }
+
+    /// Creates the lockup of the successful `claim`, paid by its lockup deposit.
+    pub(crate) fn internal_create_lockup(&self, claim: &PendingClaim) {
+        let deposit = NearToken::from_yoctonear(claim.lockup_deposit.0);
+        let Some(lockup_factory) = &self.lockup_factory else {
+            // Disabled since the claim started
+            Promise::new(claim.claimer.clone()).transfer(deposit);
+            return;
+        };
+        Promise::new(lockup_factory.factory_id.clone())
+            .function_call(
+                "create".to_string(),
+                json!({
+                    "owner_account_id": claim.account_id,
+                    "lockup_duration": claim.lockup_duration.unwrap_or(U64(0)),
+                })
+                .to_string()
+                .into_bytes(),
+                deposit,
+                GAS_FOR_LOCKUP_CREATE,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_LOCKUP_CALLBACK)
+                    .on_lockup_created(
+                        claim.account_id.clone(),
+                        claim.claimer.clone(),
+                        claim.lockup_deposit,
+                    ),
+            );
+    }
+}
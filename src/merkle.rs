@@ -0,0 +1,268 @@
+//! Merkle tree hashing shared by the contract and off-chain tree generators. Outside of the
+//! contract (native builds, or the browser with the `wasm-bindgen` feature) the hash functions
+//! come from the `sha2`/`sha3` crates instead of the NEAR host, so this module can be used
+//! without a mocked blockchain.
+
+use near_sdk::{near, AccountId};
+use std::collections::VecDeque;
+
+use crate::LeafExtras;
+
+/// Domain tag prepended to leaf hashes in `LeafFormat::V2`.
+const LEAF_DOMAIN_TAG: u8 = 0x00;
+/// Domain tag prepended to internal node hashes in `LeafFormat::V2`.
+const NODE_DOMAIN_TAG: u8 = 0x01;
+/// Depth of an `IncrementalMerkleTree`, which holds up to 2^32 leaves.
+pub const INCREMENTAL_TREE_DEPTH: usize = 32;
+
+/// Hash function used for both leaf and internal node hashing of the Merkle tree.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    #[default]
+    Keccak256,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    #[cfg(all(target_arch = "wasm32", not(feature = "wasm-bindgen")))]
+    pub fn hash(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Keccak256 => near_sdk::env::keccak256(data),
+            HashAlgorithm::Sha256 => near_sdk::env::sha256(data),
+        }
+    }
+
+    #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-bindgen"))]
+    pub fn hash(&self, data: &[u8]) -> Vec<u8> {
+        use sha2::Digest;
+        match self {
+            HashAlgorithm::Keccak256 => sha3::Keccak256::digest(data).to_vec(),
+            HashAlgorithm::Sha256 => sha2::Sha256::digest(data).to_vec(),
+        }
+    }
+}
+
+/// Version of the leaf/node hashing scheme a Merkle root was built with.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LeafFormat {
+    /// Legacy scheme: `H(leaf)` for leaves and `H(a || b)` for nodes.
+    #[default]
+    V1,
+    /// Domain-separated scheme: `H(0x00 || H(leaf))` for leaves and `H(0x01 || a || b)` for nodes.
+    V2,
+}
+
+impl LeafFormat {
+    pub fn hash_leaf(&self, hash_algorithm: HashAlgorithm, leaf: &[u8]) -> Vec<u8> {
+        match self {
+            LeafFormat::V1 => hash_algorithm.hash(leaf),
+            LeafFormat::V2 => {
+                let inner = hash_algorithm.hash(leaf);
+                hash_algorithm.hash(&[&[LEAF_DOMAIN_TAG], inner.as_slice()].concat())
+            }
+        }
+    }
+
+    /// Hashes a pair of sibling nodes, ordering them so proofs need no direction flags.
+    pub fn hash_node(&self, hash_algorithm: HashAlgorithm, a: &[u8], b: &[u8]) -> Vec<u8> {
+        let (left, right) = if a < b { (a, b) } else { (b, a) };
+        match self {
+            LeafFormat::V1 => hash_algorithm.hash(&[left, right].concat()),
+            LeafFormat::V2 => hash_algorithm.hash(&[&[NODE_DOMAIN_TAG], left, right].concat()),
+        }
+    }
+}
+
+/// Rebuilds the root from `leaf` and its proof.
+pub fn process_proof(
+    hash_algorithm: HashAlgorithm,
+    leaf_format: LeafFormat,
+    leaf: &[u8],
+    proof: &[Vec<u8>],
+) -> Vec<u8> {
+    proof.iter().fold(
+        leaf_format.hash_leaf(hash_algorithm, leaf),
+        |hash, sibling| leaf_format.hash_node(hash_algorithm, &hash, sibling),
+    )
+}
+
+/// Rebuilds the root from `leaves` and their multiproof, `None` if the flags do not match
+/// the leaves and proof.
+pub fn process_multiproof(
+    hash_algorithm: HashAlgorithm,
+    leaf_format: LeafFormat,
+    leaves: &[String],
+    proof: Vec<Vec<u8>>,
+    flags: &[bool],
+) -> Option<Vec<u8>> {
+    if leaves.is_empty() || leaves.len() + proof.len() != flags.len() + 1 {
+        return None;
+    }
+    // Leaves are consumed first, then the hashes computed from them, in order.
+    let mut queue: VecDeque<Vec<u8>> = leaves
+        .iter()
+        .map(|leaf| leaf_format.hash_leaf(hash_algorithm, leaf.as_bytes()))
+        .collect();
+    let mut proof = proof.into_iter();
+    for flag in flags {
+        let a = queue.pop_front()?;
+        let b = if *flag {
+            queue.pop_front()
+        } else {
+            proof.next()
+        }?;
+        queue.push_back(leaf_format.hash_node(hash_algorithm, &a, &b));
+    }
+    if proof.next().is_some() || queue.len() != 1 {
+        return None;
+    }
+    queue.pop_front()
+}
+
+/// Append-only `LeafFormat::V2` Merkle tree of depth `INCREMENTAL_TREE_DEPTH`, storing a single
+/// node per level. Missing leaves are zero hashes: 32 zero bytes at the leaf level, then
+/// `node(z, z)` of the level below. Proofs of its leaves therefore have exactly
+/// `INCREMENTAL_TREE_DEPTH` siblings and verify with `process_proof`.
+#[near(serializers = [borsh])]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IncrementalMerkleTree {
+    // Root of the last complete left subtree of each level on the path of the next leaf
+    branch: Vec<Vec<u8>>,
+    size: u64,
+}
+
+impl IncrementalMerkleTree {
+    /// Appends `leaf`, hashed as a `V2` leaf.
+    pub fn push(&mut self, hash_algorithm: HashAlgorithm, leaf: &[u8]) {
+        assert!(
+            self.size < 1 << INCREMENTAL_TREE_DEPTH,
+            "The incremental Merkle tree is full"
+        );
+        let mut node = LeafFormat::V2.hash_leaf(hash_algorithm, leaf);
+        let mut size = self.size;
+        let mut level = 0;
+        // Merge the complete subtrees the new leaf closes
+        while size & 1 == 1 {
+            node = LeafFormat::V2.hash_node(hash_algorithm, &self.branch[level], &node);
+            size >>= 1;
+            level += 1;
+        }
+        if level == self.branch.len() {
+            self.branch.push(node);
+        } else {
+            self.branch[level] = node;
+        }
+        self.size += 1;
+    }
+
+    /// Root of the tree, padded with zero hashes.
+    pub fn root(&self, hash_algorithm: HashAlgorithm) -> Vec<u8> {
+        let mut node = vec![0; 32];
+        let mut zero = vec![0; 32];
+        let mut size = self.size;
+        for level in 0..INCREMENTAL_TREE_DEPTH {
+            node = if size & 1 == 1 {
+                LeafFormat::V2.hash_node(hash_algorithm, &self.branch[level], &node)
+            } else {
+                LeafFormat::V2.hash_node(hash_algorithm, &node, &zero)
+            };
+            zero = LeafFormat::V2.hash_node(hash_algorithm, &zero, &zero);
+            size >>= 1;
+        }
+        node
+    }
+
+    /// Number of leaves.
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+}
+
+/// Merkle tree of `account:amount` leaves, encoded and hashed exactly like the contract
+/// verifies them. An odd node at the end of a layer is carried up unchanged.
+#[derive(Clone, Debug)]
+pub struct MerkleTree {
+    entries: Vec<(AccountId, u128)>,
+    // Hashes of each layer, from the leaves up to the root
+    layers: Vec<Vec<Vec<u8>>>,
+}
+
+impl MerkleTree {
+    /// Builds a keccak256 `V1` tree, the contract's default scheme.
+    pub fn from_entries(entries: Vec<(AccountId, u128)>) -> Self {
+        Self::from_entries_with(HashAlgorithm::default(), LeafFormat::default(), entries)
+    }
+
+    /// Builds a tree with an explicit hash algorithm and leaf format.
+    pub fn from_entries_with(
+        hash_algorithm: HashAlgorithm,
+        leaf_format: LeafFormat,
+        entries: Vec<(AccountId, u128)>,
+    ) -> Self {
+        assert!(
+            !entries.is_empty(),
+            "A Merkle tree needs at least one entry"
+        );
+        let leaves: Vec<Vec<u8>> = entries
+            .iter()
+            .map(|(account_id, amount)| {
+                let leaf = LeafExtras::default().encode_leaf(account_id, *amount);
+                leaf_format.hash_leaf(hash_algorithm, leaf.as_bytes())
+            })
+            .collect();
+        let mut layers = vec![leaves];
+        while layers[layers.len() - 1].len() > 1 {
+            let layer = layers[layers.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => leaf_format.hash_node(hash_algorithm, a, b),
+                    [a] => a.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(layer);
+        }
+        Self { entries, layers }
+    }
+
+    /// Hex-encoded root, as expected by `new` and `update_merkle_root`.
+    pub fn root(&self) -> String {
+        hex::encode(&self.layers[self.layers.len() - 1][0])
+    }
+
+    /// Entries of the tree, in leaf order.
+    pub fn entries(&self) -> &[(AccountId, u128)] {
+        &self.entries
+    }
+
+    /// Hex-encoded proof of the entry at `index`, `None` if out of range.
+    pub fn proof(&self, index: usize) -> Option<Vec<String>> {
+        if index >= self.entries.len() {
+            return None;
+        }
+        let mut index = index;
+        let mut proof = Vec::new();
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(hex::encode(sibling));
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+
+    /// Hex-encoded proof of the first entry of `account_id`, `None` if it has none.
+    pub fn proof_for(&self, account_id: &AccountId) -> Option<Vec<String>> {
+        let index = self
+            .entries
+            .iter()
+            .position(|(entry, _)| entry == account_id)?;
+        self.proof(index)
+    }
+}
@@ -0,0 +1,120 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde_json::{self, json};
+use near_sdk::{env, log, near, require, AccountId, Gas, NearToken, Promise, PromiseResult};
+
+use crate::{AirdropContract, AirdropContractExt, PendingClaim};
+
+/// Gas of the `nft_mint` call of a claim.
+const GAS_FOR_NFT_MINT: Gas = Gas::from_tgas(30);
+/// Gas of `on_badge_minted`.
+const GAS_FOR_BADGE_CALLBACK: Gas = Gas::from_tgas(5);
+/// Gas the completion of a claim needs to mint its badge, `GAS_FOR_NFT_MINT` and
+/// `GAS_FOR_BADGE_CALLBACK`.
+pub(crate) const GAS_FOR_BADGE: Gas = Gas::from_tgas(35);
+
+/// NFT series minted to every successful claimer, POAP-style.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NftBadge {
+    /// Contract minting the badges, which must allow this contract to call `nft_mint`.
+    pub nft_contract: AccountId,
+    pub series_id: String,
+    /// Storage deposit of a mint, attached by claimers on top of the claim deposit.
+    pub deposit: NearToken,
+}
+
+#[near]
+impl AirdropContract {
+    /// Mints a badge of `nft_badge` to every successful claimer, or disables badges with `None`
+    /// (only callable by the owner).
+    #[payable]
+    pub fn set_nft_badge(&mut self, nft_badge: Option<NftBadge>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_nft_badge");
+        if let Some(nft_badge) = &nft_badge {
+            require!(
+                !nft_badge.deposit.is_zero(),
+                "The deposit must cover the storage of the badges"
+            );
+        }
+        self.nft_badge = nft_badge;
+        log!("NFT badge set to {:?}", self.nft_badge);
+    }
+
+    /// Returns the NFT series minted to claimers, if any.
+    pub fn get_nft_badge(&self) -> Option<NftBadge> {
+        self.nft_badge.clone()
+    }
+
+    /// Callback: records the token ID of the badge minted for `account_id` in the claim record
+    /// at `receipt_index`, or refunds the mint deposit to `claimer` if the mint failed. The
+    /// claim itself succeeded either way.
+    #[private]
+    pub fn on_badge_minted(
+        &mut self,
+        account_id: AccountId,
+        claimer: AccountId,
+        deposit: U128,
+        receipt_index: U64,
+    ) -> Option<String> {
+        let PromiseResult::Successful(result) = env::promise_result(0) else {
+            log!("Badge mint for @{} failed", account_id);
+            Promise::new(claimer).transfer(NearToken::from_yoctonear(deposit.0));
+            return None;
+        };
+        // `nft_mint` returns the NEP-171 token or its ID, some contracts nothing at all
+        let token_id = serde_json::from_slice::<serde_json::Value>(&result)
+            .ok()
+            .and_then(|token| {
+                token
+                    .get("token_id")
+                    .unwrap_or(&token)
+                    .as_str()
+                    .map(str::to_string)
+            });
+        let Some(token_id) = token_id else {
+            log!("Badge minted for @{}", account_id);
+            return None;
+        };
+        if let Some(record) = self.claim_records.get_mut(&receipt_index.0) {
+            record.badge_token_id = Some(token_id.clone());
+        }
+        log!("Badge {} minted for @{}", token_id, account_id);
+        Some(token_id)
+    }
+}
+
+impl AirdropContract {
+    /// Mints the badge of the successful `claim` with receipt `receipt_index`, paid by its badge
+    /// deposit.
+    pub(crate) fn internal_mint_badge(&self, claim: &PendingClaim, receipt_index: u64) {
+        let deposit = NearToken::from_yoctonear(claim.badge_deposit.0);
+        let Some(nft_badge) = &self.nft_badge else {
+            // Disabled since the claim started
+            Promise::new(claim.claimer.clone()).transfer(deposit);
+            return;
+        };
+        Promise::new(nft_badge.nft_contract.clone())
+            .function_call(
+                "nft_mint".to_string(),
+                json!({
+                    "series_id": nft_badge.series_id,
+                    "receiver_id": claim.receiver_id(),
+                })
+                .to_string()
+                .into_bytes(),
+                deposit,
+                GAS_FOR_NFT_MINT,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_BADGE_CALLBACK)
+                    .on_badge_minted(
+                        claim.account_id.clone(),
+                        claim.claimer.clone(),
+                        claim.badge_deposit,
+                        U64(receipt_index),
+                    ),
+            );
+    }
+}
@@ -0,0 +1,112 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, log, near, AccountId, PromiseOrValue};
+
+use crate::{AirdropContract, AirdropContractExt, ClaimError, ClaimOutcome, PendingClaim};
+
+#[near]
+impl AirdropContract {
+    /// Pays claims larger than the remaining pool what is left, recording the shortfall as
+    /// claimable later with `claim_shortfall`, instead of letting their transfer fail (only
+    /// callable by the owner). The pool is the funded tokens not yet distributed or withdrawn.
+    #[payable]
+    pub fn set_partial_fills(&mut self, enabled: bool) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_partial_fills");
+        self.partial_fills = enabled;
+        log!("Partial fills enabled: {}", enabled);
+    }
+
+    /// Claims the shortfall of an earlier partially filled claim of the caller, once the pool
+    /// has been topped up. Requires the same deposit as `claim_airdrop`, and may be partially
+    /// filled again.
+    #[payable]
+    #[handle_result]
+    pub fn claim_shortfall(&mut self) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_claim_deposit()?;
+        let account_id = env::predecessor_account_id();
+        let shortfall = self
+            .internal_remove_shortfall(&account_id)
+            .ok_or(ClaimError::NoShortfall)?;
+        let claim = PendingClaim {
+            pays_shortfall: true,
+            ..self.internal_new_claim(account_id, shortfall)
+        };
+        self.internal_start_claim(claim)
+    }
+
+    /// Returns whether claims larger than the remaining pool are partially filled.
+    pub fn get_partial_fills(&self) -> bool {
+        self.partial_fills
+    }
+
+    /// Returns the leaf amount still owed to `account_id` after a partial fill, if any.
+    pub fn get_shortfall(&self, account_id: AccountId) -> Option<U128> {
+        self.shortfalls.get(&account_id).copied()
+    }
+}
+
+impl AirdropContract {
+    /// Funded tokens not yet distributed, reserved or withdrawn.
+    fn remaining_pool(&self) -> u128 {
+        self.funded
+            .saturating_sub(self.withdrawn)
+            .saturating_sub(self.distributed)
+    }
+
+    /// Reduces the leaf amount of `claim` to the remaining pool, if partial fills are enabled,
+    /// keeping the rest as its shortfall.
+    pub(crate) fn internal_partial_fill(&self, claim: &mut PendingClaim) -> Result<(), ClaimError> {
+        if !self.partial_fills {
+            return Ok(());
+        }
+        let remaining = self.remaining_pool();
+        if claim.amount.0 <= remaining {
+            return Ok(());
+        }
+        if remaining == 0 {
+            return Err(ClaimError::Underfunded);
+        }
+        claim.shortfall = U128(claim.amount.0 - remaining);
+        claim.amount = U128(remaining);
+        Ok(())
+    }
+
+    /// Records the shortfall of the reserved `claim`, if partially filled.
+    pub(crate) fn internal_record_shortfall(&mut self, claim: &PendingClaim) {
+        if claim.shortfall.0 > 0 {
+            log!(
+                "Claim of @{} partially filled, {} tokens owed",
+                claim.account_id,
+                claim.shortfall.0
+            );
+            self.internal_insert_shortfall(claim.account_id.clone(), claim.shortfall);
+        }
+    }
+
+    /// Reverts `internal_record_shortfall` for the failed `claim`, restoring the shortfall it
+    /// was paying if any.
+    pub(crate) fn internal_revert_shortfall(&mut self, claim: &PendingClaim) {
+        if claim.pays_shortfall {
+            self.internal_insert_shortfall(
+                claim.account_id.clone(),
+                U128(claim.amount.0 + claim.shortfall.0),
+            );
+        } else if claim.shortfall.0 > 0 {
+            self.internal_remove_shortfall(&claim.account_id);
+        }
+    }
+
+    /// Records `shortfall` as owed to `account_id`, replacing any earlier shortfall.
+    pub(crate) fn internal_insert_shortfall(&mut self, account_id: AccountId, shortfall: U128) {
+        self.internal_remove_shortfall(&account_id);
+        self.shortfall_total = self.shortfall_total.saturating_add(shortfall.0);
+        self.shortfalls.insert(account_id, shortfall);
+    }
+
+    /// Drops the shortfall owed to `account_id`, returning it if any.
+    pub(crate) fn internal_remove_shortfall(&mut self, account_id: &AccountId) -> Option<U128> {
+        let shortfall = self.shortfalls.remove(account_id)?;
+        self.shortfall_total = self.shortfall_total.saturating_sub(shortfall.0);
+        Some(shortfall)
+    }
+}
@@ -0,0 +1,129 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{assert_one_yocto, env, log, near, require, AccountId, PromiseOrValue};
+
+use crate::{
+    AirdropContract, AirdropContractExt, ClaimError, ClaimOutcome, Denomination, LeafExtras,
+};
+
+/// Distribution of a fixed pool in proportion to leaf weights: accounts register their
+/// weight until `registration_ends_at`, then each receives `pool * weight / total_weight`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProRata {
+    /// Tokens shared among the registered accounts.
+    pub pool: U128,
+    /// End of the registration window, in nanoseconds since the Unix epoch.
+    pub registration_ends_at: U64,
+    /// Sum of the registered weights.
+    pub total_weight: U128,
+    /// Number of registered accounts.
+    pub registrants: u32,
+}
+
+impl ProRata {
+    /// Share of the pool of an account registered with `weight`, rounded down.
+    pub fn share(&self, weight: u128) -> Option<u128> {
+        if self.total_weight.0 == 0 {
+            return None;
+        }
+        self.pool
+            .0
+            .checked_mul(weight)
+            .map(|amount| amount / self.total_weight.0)
+    }
+}
+
+#[near]
+impl AirdropContract {
+    /// Starts a pro-rata distribution of `pool` tokens (only callable by the owner). From then
+    /// on, leaf amounts are weights: accounts register them with `register_weight` and claim
+    /// their share with `claim_pro_rata` once the registration window has closed.
+    #[payable]
+    pub fn start_pro_rata(&mut self, pool: U128, registration_ends_at: U64) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("start_pro_rata");
+        require!(
+            self.pro_rata.is_none(),
+            "A pro-rata distribution already exists"
+        );
+        self.pro_rata = Some(ProRata {
+            pool,
+            registration_ends_at,
+            total_weight: U128(0),
+            registrants: 0,
+        });
+        log!("Pro-rata distribution of {} tokens started", pool.0);
+    }
+
+    /// Registers the weight of the caller's leaf for the pro-rata distribution.
+    #[payable]
+    #[handle_result]
+    pub fn register_weight(
+        &mut self,
+        weight: U128,
+        merkle_proof: Vec<String>,
+        extras: Option<LeafExtras>,
+    ) -> Result<(), ClaimError> {
+        assert_one_yocto();
+        self.assert_denomination(Denomination::Weight)?;
+        let account_id = env::predecessor_account_id();
+        let mut pro_rata = self.pro_rata.clone().ok_or(ClaimError::WrongDenomination)?;
+        if env::block_timestamp() >= pro_rata.registration_ends_at.0 {
+            return Err(ClaimError::RegistrationClosed);
+        }
+        self.internal_verify_leaf(
+            &account_id,
+            weight,
+            &merkle_proof,
+            &extras.unwrap_or_default(),
+        )?;
+        if self.pro_rata_weights.contains_key(&account_id) {
+            return Err(ClaimError::AlreadyRegistered);
+        }
+        pro_rata.total_weight = U128(
+            pro_rata
+                .total_weight
+                .0
+                .checked_add(weight.0)
+                .ok_or(ClaimError::AmountOverflow)?,
+        );
+        pro_rata.registrants += 1;
+        self.pro_rata = Some(pro_rata);
+        self.pro_rata_weights.insert(account_id, weight);
+        Ok(())
+    }
+
+    /// Claims the caller's share of the pro-rata pool once the registration window has closed.
+    #[payable]
+    #[handle_result]
+    pub fn claim_pro_rata(&mut self) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_claim_deposit()?;
+        self.internal_count_relayed_claim()?;
+        self.assert_denomination(Denomination::Weight)?;
+        let pro_rata = self.pro_rata.clone().ok_or(ClaimError::WrongDenomination)?;
+        if env::block_timestamp() < pro_rata.registration_ends_at.0 {
+            return Err(ClaimError::RegistrationOpen);
+        }
+        let account_id = env::predecessor_account_id();
+        if self.is_claimed(&account_id) {
+            return Err(ClaimError::AlreadyClaimed);
+        }
+        let weight = self
+            .pro_rata_weights
+            .get(&account_id)
+            .ok_or(ClaimError::NotRegistered)?;
+        let amount = pro_rata.share(weight.0).ok_or(ClaimError::AmountOverflow)?;
+        let claim = self.internal_new_claim(account_id, U128(amount));
+        self.internal_start_claim(claim)
+    }
+
+    /// Returns the pro-rata distribution, if any.
+    pub fn get_pro_rata(&self) -> Option<ProRata> {
+        self.pro_rata.clone()
+    }
+
+    /// Returns the weight `account_id` registered for the pro-rata distribution, if any.
+    pub fn get_pro_rata_weight(&self, account_id: AccountId) -> Option<U128> {
+        self.pro_rata_weights.get(&account_id).copied()
+    }
+}
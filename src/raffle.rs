@@ -0,0 +1,177 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{assert_one_yocto, env, log, near, require, AccountId, PromiseOrValue};
+
+use crate::{
+    AirdropContract, AirdropContractExt, ClaimError, ClaimOutcome, LeafExtras, PendingClaim,
+};
+
+/// Raffle among the accounts of the Merkle tree: eligible accounts register until
+/// `registration_ends_at`, then `winners` of them are drawn and can claim `prize` each.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Raffle {
+    /// End of the registration window, in nanoseconds since the Unix epoch.
+    pub registration_ends_at: U64,
+    /// Number of winners to draw.
+    pub winners: u32,
+    /// Tokens each winner can claim.
+    pub prize: U128,
+    /// Hex-encoded `env::random_seed` the winners were drawn with, once drawn.
+    pub seed: Option<String>,
+}
+
+/// Draws `winners` distinct positions out of `entrants` with a partial Fisher-Yates shuffle:
+/// step `i` swaps position `i` with `i + r % (entrants - i)`, where `r` is the little-endian
+/// `u64` of the first 8 bytes of `sha256(seed || i as u32 little-endian)`. Returns the swaps.
+pub fn draw_swaps(seed: &[u8], entrants: u32, winners: u32) -> Vec<(u32, u32)> {
+    (0..winners.min(entrants))
+        .map(|i| {
+            let hash = env::sha256(&[seed, &i.to_le_bytes()].concat());
+            let r = u64::from_le_bytes(hash[..8].try_into().unwrap());
+            (i, i + (r % u64::from(entrants - i)) as u32)
+        })
+        .collect()
+}
+
+#[near]
+impl AirdropContract {
+    /// Starts a raffle (only callable by the owner). The previous raffle must have been drawn;
+    /// its entrants are cleared, while its winners keep their prizes.
+    #[payable]
+    pub fn start_raffle(&mut self, registration_ends_at: U64, winners: u32, prize: U128) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("start_raffle");
+        require!(
+            self.raffle
+                .as_ref()
+                .is_none_or(|raffle| raffle.seed.is_some()),
+            "The current raffle has not been drawn"
+        );
+        for account_id in self.raffle_entrants.drain(..) {
+            self.raffle_entered.remove(&account_id);
+        }
+        self.raffle = Some(Raffle {
+            registration_ends_at,
+            winners,
+            prize,
+            seed: None,
+        });
+        log!("Raffle started for {} winners", winners);
+    }
+
+    /// Registers the caller for the current raffle, proving eligibility with its leaf.
+    #[payable]
+    #[handle_result]
+    pub fn register_for_raffle(
+        &mut self,
+        amount: U128,
+        merkle_proof: Vec<String>,
+        extras: Option<LeafExtras>,
+    ) -> Result<(), ClaimError> {
+        assert_one_yocto();
+        let raffle = self.raffle.as_ref().ok_or(ClaimError::RaffleClosed)?;
+        if env::block_timestamp() >= raffle.registration_ends_at.0 {
+            return Err(ClaimError::RaffleClosed);
+        }
+        let account_id = env::predecessor_account_id();
+        self.internal_verify_leaf(
+            &account_id,
+            amount,
+            &merkle_proof,
+            &extras.unwrap_or_default(),
+        )?;
+        if !self.raffle_entered.insert(account_id.clone()) {
+            return Err(ClaimError::AlreadyRegistered);
+        }
+        self.raffle_entrants.push(account_id);
+        Ok(())
+    }
+
+    /// Draws the winners of the current raffle once its registration window has closed
+    /// (only callable by the owner). The seed is stored so anyone can recompute the draw.
+    #[payable]
+    pub fn draw_raffle(&mut self) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("draw_raffle");
+        let mut raffle = self
+            .raffle
+            .clone()
+            .unwrap_or_else(|| env::panic_str("No raffle"));
+        require!(raffle.seed.is_none(), "The raffle has already been drawn");
+        require!(
+            env::block_timestamp() >= raffle.registration_ends_at.0,
+            "The registration is still open"
+        );
+        let seed = env::random_seed();
+        for (i, j) in draw_swaps(&seed, self.raffle_entrants.len(), raffle.winners) {
+            if i != j {
+                let entrant = self.raffle_entrants[j].clone();
+                let entrant = self.raffle_entrants.replace(i, entrant);
+                self.raffle_entrants.replace(j, entrant);
+            }
+            let winner = self.raffle_entrants[i].clone();
+            self.raffle_winners.insert(winner, raffle.prize);
+        }
+        raffle.seed = Some(hex::encode(seed));
+        self.raffle = Some(raffle);
+        log!("Raffle drawn");
+    }
+
+    /// Claims the prize of a raffle winner. Prizes are tracked apart from leaves: the winner
+    /// can still claim its leaf, and the prize is paid once, until then it can be claimed again
+    /// if its payout fails.
+    #[payable]
+    #[handle_result]
+    pub fn claim_raffle_prize(&mut self) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_claim_deposit()?;
+        self.internal_count_relayed_claim()?;
+        let account_id = env::predecessor_account_id();
+        let prize = self
+            .raffle_winners
+            .remove(&account_id)
+            .ok_or(ClaimError::NotAWinner)?;
+        // Escrowed claims are held per account
+        if self.escrow.contains_key(&account_id) {
+            return Err(ClaimError::AlreadyClaimed);
+        }
+        let claim = PendingClaim {
+            raffle_prize: true,
+            ..self.internal_new_claim(account_id, prize)
+        };
+        self.internal_start_claim(claim)
+    }
+
+    /// Returns the current raffle, if any.
+    pub fn get_raffle(&self) -> Option<Raffle> {
+        self.raffle.clone()
+    }
+
+    /// Returns up to `limit` entrants of the current raffle from `from_index`. Once drawn,
+    /// the first `winners` entrants are the winners, in draw order.
+    pub fn get_raffle_entrants(&self, from_index: u32, limit: u32) -> Vec<AccountId> {
+        self.raffle_entrants
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the winners of the current raffle in draw order, empty until it is drawn.
+    pub fn get_raffle_winners(&self) -> Vec<AccountId> {
+        match &self.raffle {
+            Some(raffle) if raffle.seed.is_some() => self
+                .raffle_entrants
+                .iter()
+                .take(raffle.winners as usize)
+                .cloned()
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the prize `account_id` won in a raffle and has not claimed yet, if any.
+    pub fn get_raffle_prize(&self, account_id: AccountId) -> Option<U128> {
+        self.raffle_winners.get(&account_id).copied()
+    }
+}
@@ -0,0 +1,224 @@
+use near_sdk::json_types::{Base64VecU8, U128, U64};
+use near_sdk::{env, log, near, AccountId, CurveType, PublicKey};
+
+use crate::{is_implicit_account, AirdropContract, AirdropContractExt, ClaimError};
+
+// Length of a relayer quota period, in nanoseconds
+const DAY: u64 = 86_400_000_000_000;
+
+/// Claim of an implicit account signed with its own key, so a relayer can submit it.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClaimAuthorization {
+    /// Implicit account of the leaf, receiving the tokens.
+    pub account_id: AccountId,
+    /// ed25519 key the implicit account is derived from.
+    pub public_key: PublicKey,
+    /// Signature of `claim_authorization_message` by `public_key`.
+    pub signature: Base64VecU8,
+}
+
+/// Designation of another receiver by an implicit account, see `claim_to`.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReceiverAuthorization {
+    /// ed25519 key the implicit account is derived from.
+    pub public_key: PublicKey,
+    /// Signature of `receiver_authorization_message` by `public_key`.
+    pub signature: Base64VecU8,
+}
+
+/// Claims a registered relayer may submit per day, and its counter for the current day.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RelayerQuota {
+    pub daily_quota: u32,
+    /// Day of `claims`, in days since the Unix epoch.
+    pub day: u64,
+    /// Claims relayed during `day`.
+    pub claims: u32,
+}
+
+/// Message an account signs to authorize a relayed claim of `amount` on `contract_id`, for the
+/// leaf with `nonce` of `round` only.
+pub fn claim_authorization_message(
+    contract_id: &AccountId,
+    account_id: &AccountId,
+    amount: U128,
+    round: u32,
+    nonce: Option<U64>,
+) -> String {
+    format!(
+        "{}:{}:{}:{}:{}",
+        contract_id,
+        account_id,
+        amount.0,
+        round,
+        nonce_field(nonce)
+    )
+}
+
+/// Message an account signs to send its claim of `amount` on `contract_id` to `receiver_id`,
+/// for the leaf with `nonce` of `round` only.
+pub fn receiver_authorization_message(
+    contract_id: &AccountId,
+    account_id: &AccountId,
+    receiver_id: &AccountId,
+    amount: U128,
+    round: u32,
+    nonce: Option<U64>,
+) -> String {
+    format!(
+        "{}:{}:{}:{}:{}:{}",
+        contract_id,
+        account_id,
+        receiver_id,
+        amount.0,
+        round,
+        nonce_field(nonce)
+    )
+}
+
+/// Nonce field of the signed messages, empty for leaves without a nonce.
+fn nonce_field(nonce: Option<U64>) -> String {
+    nonce.map(|nonce| nonce.0.to_string()).unwrap_or_default()
+}
+
+#[near]
+impl AirdropContract {
+    /// Enables claims submitted by relayers (only callable by the owner): claims no longer
+    /// require the 1 yoctoNEAR, so they can run as NEP-366 delegate actions signed with
+    /// function-call keys, and `claim_airdrop` accepts signed authorizations of implicit accounts.
+    #[payable]
+    pub fn set_relayed_claims(&mut self, enabled: bool) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_relayed_claims");
+        self.relayed_claims = enabled;
+        log!("Relayed claims set to {}", enabled);
+    }
+
+    /// Registers `relayer_id` with a daily claim quota, or removes it with `None` (only callable
+    /// by the owner). With relayed claims, only registered relayers can submit claims.
+    #[payable]
+    pub fn set_relayer(&mut self, relayer_id: AccountId, daily_quota: Option<u32>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_relayer");
+        match daily_quota {
+            Some(daily_quota) => {
+                let quota = self
+                    .relayers
+                    .entry(relayer_id.clone())
+                    .or_insert(RelayerQuota {
+                        daily_quota,
+                        day: 0,
+                        claims: 0,
+                    });
+                quota.daily_quota = daily_quota;
+                log!(
+                    "Relayer @{} quota set to {} claims per day",
+                    relayer_id,
+                    daily_quota
+                );
+            }
+            None => {
+                self.relayers.remove(&relayer_id);
+                log!("Relayer @{} removed", relayer_id);
+            }
+        }
+    }
+
+    /// Returns the quota and counter of `relayer_id`, `None` if it is not registered.
+    pub fn get_relayer(&self, relayer_id: AccountId) -> Option<RelayerQuota> {
+        self.relayers.get(&relayer_id).cloned()
+    }
+
+    /// Returns whether claims submitted by relayers are enabled.
+    pub fn get_relayed_claims(&self) -> bool {
+        self.relayed_claims
+    }
+}
+
+impl AirdropContract {
+    /// With relayed claims, counts a claim submitted by a relayer against its daily quota. A
+    /// claim is relayed when it runs as a delegate action, i.e. the signer is not the caller,
+    /// or when the caller is a registered relayer.
+    pub(crate) fn internal_count_relayed_claim(&mut self) -> Result<(), ClaimError> {
+        if !self.relayed_claims {
+            return Ok(());
+        }
+        let relayer = env::signer_account_id();
+        if relayer == env::predecessor_account_id() && !self.relayers.contains_key(&relayer) {
+            return Ok(());
+        }
+        let quota = self
+            .relayers
+            .get_mut(&relayer)
+            .ok_or(ClaimError::RelayerNotAllowed)?;
+        let day = env::block_timestamp() / DAY;
+        if quota.day != day {
+            quota.day = day;
+            quota.claims = 0;
+        }
+        if quota.claims >= quota.daily_quota {
+            return Err(ClaimError::RelayerQuotaExceeded);
+        }
+        quota.claims += 1;
+        Ok(())
+    }
+
+    /// Account claiming the leaf with `nonce`: the signer of `authorization` if given, the
+    /// caller otherwise.
+    pub(crate) fn internal_claim_beneficiary(
+        &self,
+        authorization: Option<ClaimAuthorization>,
+        amount: U128,
+        nonce: Option<U64>,
+    ) -> Result<AccountId, ClaimError> {
+        let Some(authorization) = authorization else {
+            return Ok(env::predecessor_account_id());
+        };
+        if !self.relayed_claims {
+            return Err(ClaimError::InvalidAuthorization);
+        }
+        if !self.relayers.contains_key(&env::predecessor_account_id()) {
+            return Err(ClaimError::RelayerNotAllowed);
+        }
+        let message = claim_authorization_message(
+            &env::current_account_id(),
+            &authorization.account_id,
+            amount,
+            self.round,
+            nonce,
+        );
+        if !verify_account_signature(
+            &authorization.account_id,
+            &authorization.public_key,
+            &authorization.signature.0,
+            &message,
+        ) {
+            return Err(ClaimError::InvalidAuthorization);
+        }
+        Ok(authorization.account_id)
+    }
+}
+
+/// Whether `signature` is a signature of `message` by `public_key`, and `account_id` is the
+/// implicit account of `public_key`. Only implicit accounts can be tied to a key on chain.
+pub(crate) fn verify_account_signature(
+    account_id: &AccountId,
+    public_key: &PublicKey,
+    signature: &[u8],
+    message: &str,
+) -> bool {
+    let key = &public_key.as_bytes()[1..];
+    if public_key.curve_type() != CurveType::ED25519
+        || !is_implicit_account(account_id)
+        || account_id.as_str() != hex::encode(key)
+    {
+        return false;
+    }
+    match (<&[u8; 64]>::try_from(signature), <&[u8; 32]>::try_from(key)) {
+        (Ok(signature), Ok(key)) => env::ed25519_verify(signature, message.as_bytes(), key),
+        _ => false,
+    }
+}
@@ -0,0 +1,113 @@
+use near_sdk::json_types::{Base58CryptoHash, U128, U64};
+use near_sdk::{env, log, near, require, CryptoHash};
+
+use crate::{AirdropContract, AirdropContractExt, RootTotals};
+
+/// Maximum number of outstanding root commitments.
+const MAX_ROOT_COMMITMENTS: usize = 10;
+
+/// Commitment to a future Merkle root, revealed by `reveal_root`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RootCommitment {
+    /// sha256 of the 32 root bytes followed by the UTF-8 bytes of the salt.
+    pub commitment: Base58CryptoHash,
+    pub committed_at: U64,
+    pub block_height: U64,
+}
+
+#[near]
+impl AirdropContract {
+    /// Commits to a future Merkle root without disclosing it (only callable by the owner).
+    /// - `commitment`: sha256 of the 32 root bytes followed by the UTF-8 bytes of a secret
+    ///   salt, see `get_root_commitment_hash`.
+    #[payable]
+    pub fn commit_root(&mut self, commitment: Base58CryptoHash) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("commit_root");
+        require!(
+            !self
+                .root_commitments
+                .iter()
+                .any(|pending| pending.commitment == commitment),
+            "Already committed"
+        );
+        require!(
+            self.root_commitments.len() < MAX_ROOT_COMMITMENTS,
+            "Too many outstanding root commitments"
+        );
+        log!("Root commitment {}", String::from(&commitment));
+        self.root_commitments.push(RootCommitment {
+            commitment,
+            committed_at: U64(env::block_timestamp()),
+            block_height: U64(env::block_height()),
+        });
+    }
+
+    /// Activates a committed Merkle root with its declared totals, see `update_merkle_root`
+    /// (only callable by the owner, in a later block than the commitment). Root updates go
+    /// through the council instead if one is set.
+    #[payable]
+    pub fn reveal_root(
+        &mut self,
+        merkle_root: String,
+        salt: String,
+        total_amount: U128,
+        leaf_count: u32,
+    ) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("reveal_root");
+        self.assert_no_council();
+        let commitment = Base58CryptoHash::from(Self::root_commitment_hash(&merkle_root, &salt));
+        let index = self
+            .root_commitments
+            .iter()
+            .position(|pending| pending.commitment == commitment)
+            .unwrap_or_else(|| env::panic_str("No matching root commitment"));
+        require!(
+            env::block_height() > self.root_commitments[index].block_height.0,
+            "The root cannot be revealed in the block of its commitment"
+        );
+        self.root_commitments.remove(index);
+        self.internal_update_merkle_root(
+            &merkle_root,
+            RootTotals {
+                total_amount,
+                leaf_count,
+            },
+            None,
+        );
+    }
+
+    /// Withdraws an outstanding root commitment (only callable by the owner).
+    #[payable]
+    pub fn cancel_root_commitment(&mut self, commitment: Base58CryptoHash) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("cancel_root_commitment");
+        let len = self.root_commitments.len();
+        self.root_commitments
+            .retain(|pending| pending.commitment != commitment);
+        require!(self.root_commitments.len() < len, "No such root commitment");
+        log!("Root commitment {} cancelled", String::from(&commitment));
+    }
+
+    /// Returns the outstanding root commitments, oldest first.
+    pub fn get_root_commitments(&self) -> Vec<RootCommitment> {
+        self.root_commitments.clone()
+    }
+
+    /// Returns the commitment `commit_root` expects for `merkle_root` and `salt`. Compute it
+    /// locally instead to keep the salt secret until the reveal.
+    pub fn get_root_commitment_hash(&self, merkle_root: String, salt: String) -> Base58CryptoHash {
+        Self::root_commitment_hash(&merkle_root, &salt).into()
+    }
+}
+
+impl AirdropContract {
+    fn root_commitment_hash(merkle_root: &str, salt: &str) -> CryptoHash {
+        let mut preimage = Self::decode_hash(merkle_root)
+            .unwrap_or_else(|_| env::panic_str("Invalid Merkle root, expected a 32-byte hash."));
+        preimage.extend_from_slice(salt.as_bytes());
+        env::sha256_array(&preimage)
+    }
+}
@@ -0,0 +1,251 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, log, near, require, AccountId, CryptoHash};
+
+use crate::{AirdropContract, AirdropContractExt, ClaimError, RootTotals};
+
+/// How the claimed accounts of each round are recorded.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ClaimedStorage {
+    /// The full account ID.
+    #[default]
+    Accounts,
+    /// sha256 of the account ID, 32 bytes whatever its length, for campaigns with hundreds of
+    /// thousands of recipients. The claimed accounts can no longer be listed from the state.
+    Hashed,
+}
+
+/// Round of a recurring airdrop: its root and the claims against it. Accounts claim once per
+/// round.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Round {
+    /// Latest Merkle root of the round.
+    pub merkle_root: String,
+    /// Block time of the opening, in nanoseconds since the Unix epoch.
+    pub opened_at: U64,
+    /// Block time of the closing, if closed.
+    pub closed_at: Option<U64>,
+    /// Number of successful claims.
+    pub claims: u32,
+    /// Leaf amounts of the successful claims.
+    pub claimed_amount: U128,
+}
+
+#[near]
+impl AirdropContract {
+    /// Closes the current round to claims (only callable by the owner). Claims already in flight
+    /// complete normally.
+    #[payable]
+    pub fn close_round(&mut self) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("close_round");
+        let round = self
+            .rounds
+            .get_mut(&self.round)
+            .unwrap_or_else(|| env::abort());
+        require!(round.closed_at.is_none(), "The round is already closed");
+        round.closed_at = Some(U64(env::block_timestamp()));
+        log!("Round {} closed", self.round);
+    }
+
+    /// Opens the next round with a new Merkle root and its declared totals, see
+    /// `update_merkle_root` (only callable by the owner, once the current round is closed and
+    /// its claims have completed). Returns the new round.
+    #[payable]
+    pub fn open_round(&mut self, merkle_root: String, total_amount: U128, leaf_count: u32) -> u32 {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("open_round");
+        self.assert_no_council();
+        require!(
+            self.rounds
+                .get(&self.round)
+                .is_some_and(|round| round.closed_at.is_some()),
+            "The current round is still open"
+        );
+        require!(self.reserved == 0, "Claims are in flight or in escrow");
+        self.round += 1;
+        self.rounds.insert(
+            self.round,
+            Round {
+                merkle_root: String::new(),
+                opened_at: U64(env::block_timestamp()),
+                closed_at: None,
+                claims: 0,
+                claimed_amount: U128(0),
+            },
+        );
+        self.internal_update_merkle_root(
+            &merkle_root,
+            RootTotals {
+                total_amount,
+                leaf_count,
+            },
+            None,
+        );
+        log!("Round {} opened", self.round);
+        self.round
+    }
+
+    /// Returns the current round, 0 until `open_round` is first called.
+    pub fn get_current_round(&self) -> u32 {
+        self.round
+    }
+
+    /// Returns the root and stats of `round`, if opened.
+    pub fn get_round(&self, round: u32) -> Option<Round> {
+        self.rounds.get(&round).cloned()
+    }
+
+    /// Returns whether `account_id` claimed in `round`.
+    pub fn has_claimed_in_round(&self, account_id: AccountId, round: u32) -> bool {
+        match self.claimed_storage {
+            ClaimedStorage::Hashed => self
+                .hashed_claimed
+                .contains(&(round, Self::claimed_hash(&account_id))),
+            ClaimedStorage::Accounts if round == 0 => self.claimed.contains(&account_id),
+            ClaimedStorage::Accounts => self.round_claimed.contains(&(round, account_id)),
+        }
+    }
+
+    /// Sets how claimed accounts are recorded (only callable by the owner, before the first
+    /// claim and while the claimed set is empty).
+    #[payable]
+    pub fn set_claimed_storage(&mut self, claimed_storage: ClaimedStorage) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_claimed_storage");
+        require!(
+            !self.claims_started() && self.claimed.is_empty() && self.imported_claimed == 0,
+            "The claimed set is not empty"
+        );
+        self.claimed_storage = claimed_storage;
+        log!("Claimed storage set to {:?}", claimed_storage);
+    }
+
+    /// Returns how claimed accounts are recorded.
+    pub fn get_claimed_storage(&self) -> ClaimedStorage {
+        self.claimed_storage
+    }
+}
+
+impl AirdropContract {
+    /// Ensures the current round accepts claims.
+    pub(crate) fn assert_round_open(&self) -> Result<(), ClaimError> {
+        if self
+            .rounds
+            .get(&self.round)
+            .is_some_and(|round| round.closed_at.is_some())
+        {
+            return Err(ClaimError::RoundClosed);
+        }
+        Ok(())
+    }
+
+    /// Returns whether `account_id` claimed in the current round.
+    pub(crate) fn is_claimed(&self, account_id: &AccountId) -> bool {
+        self.has_claimed_in_round(account_id.clone(), self.round)
+    }
+
+    /// Marks `account_id` as claimed in the current round. Returns whether it was not already.
+    pub(crate) fn internal_mark_claimed(&mut self, account_id: AccountId) -> bool {
+        self.internal_mark_claimed_in_round(account_id, self.round)
+    }
+
+    /// Marks `account_id` as claimed in `round`. Returns whether it was not already.
+    pub(crate) fn internal_mark_claimed_in_round(
+        &mut self,
+        account_id: AccountId,
+        round: u32,
+    ) -> bool {
+        match self.claimed_storage {
+            ClaimedStorage::Hashed => self
+                .hashed_claimed
+                .insert((round, Self::claimed_hash(&account_id))),
+            ClaimedStorage::Accounts if round == 0 => self.claimed.insert(account_id),
+            ClaimedStorage::Accounts => self.round_claimed.insert((round, account_id)),
+        }
+    }
+
+    /// Removes `account_id` from the claimed accounts of `round`. Returns whether it had
+    /// claimed.
+    pub(crate) fn internal_unmark_claimed_in_round(
+        &mut self,
+        account_id: &AccountId,
+        round: u32,
+    ) -> bool {
+        match self.claimed_storage {
+            ClaimedStorage::Hashed => self
+                .hashed_claimed
+                .remove(&(round, Self::claimed_hash(account_id))),
+            ClaimedStorage::Accounts if round == 0 => self.claimed.remove(account_id),
+            ClaimedStorage::Accounts => self.round_claimed.remove(&(round, account_id.clone())),
+        }
+    }
+
+    /// Whether a claim has started, after which the claimed set can no longer be seeded or
+    /// change representation.
+    pub(crate) fn claims_started(&self) -> bool {
+        self.distributed > 0 || !self.claim_index.is_empty()
+    }
+
+    fn claimed_hash(account_id: &AccountId) -> CryptoHash {
+        env::sha256_array(account_id.as_bytes())
+    }
+
+    /// Returns whether the leaf of `account_id` with `nonce` was claimed in the current round,
+    /// or the account itself for leaves without a nonce.
+    pub(crate) fn is_leaf_claimed(&self, account_id: &AccountId, nonce: Option<U64>) -> bool {
+        match nonce {
+            Some(nonce) => self
+                .nonce_claimed
+                .contains(&(self.round, account_id.clone(), nonce.0)),
+            None => self.is_claimed(account_id),
+        }
+    }
+
+    /// Marks the leaf of `account_id` with `nonce` as claimed in the current round, or the
+    /// account itself for leaves without a nonce. Returns whether it was not already.
+    pub(crate) fn internal_mark_leaf_claimed(
+        &mut self,
+        account_id: AccountId,
+        nonce: Option<U64>,
+    ) -> bool {
+        match nonce {
+            Some(nonce) => self.nonce_claimed.insert((self.round, account_id, nonce.0)),
+            None => self.internal_mark_claimed(account_id),
+        }
+    }
+
+    /// Reverts `internal_mark_leaf_claimed`. Returns whether the leaf had been claimed.
+    pub(crate) fn internal_unmark_leaf_claimed(
+        &mut self,
+        account_id: &AccountId,
+        nonce: Option<U64>,
+    ) -> bool {
+        self.internal_unmark_leaf_claimed_in_round(account_id, nonce, self.round)
+    }
+
+    /// Removes the leaf of `account_id` with `nonce` from the claimed leaves of `round`.
+    /// Returns whether the leaf had been claimed.
+    pub(crate) fn internal_unmark_leaf_claimed_in_round(
+        &mut self,
+        account_id: &AccountId,
+        nonce: Option<U64>,
+        round: u32,
+    ) -> bool {
+        match nonce {
+            Some(nonce) => self
+                .nonce_claimed
+                .remove(&(round, account_id.clone(), nonce.0)),
+            None => self.internal_unmark_claimed_in_round(account_id, round),
+        }
+    }
+
+    /// Counts a successful claim of `amount` in the stats of the current round.
+    pub(crate) fn internal_count_round_claim(&mut self, amount: U128) {
+        if let Some(round) = self.rounds.get_mut(&self.round) {
+            round.claims += 1;
+            round.claimed_amount = U128(round.claimed_amount.0.saturating_add(amount.0));
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{env, log, near, require, AccountId, Gas, NearToken, Promise};
+
+use crate::{AirdropContract, AirdropContractExt, ClaimError, PendingClaim, NO_DEPOSIT};
+
+/// Gas of the near.social `set` call of a claim.
+pub(crate) const GAS_FOR_SOCIAL_POST: Gas = Gas::from_tgas(20);
+
+/// near.social posts announcing claims, opted into by claimers with `post_to_social`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SocialPosts {
+    /// near.social contract, e.g. `social.near`.
+    pub social_contract: AccountId,
+    /// Badge of this contract awarded to each claimer, e.g. `publicai-airdrop`.
+    pub badge: String,
+    /// Extra deposit a claim must attach for the storage of its post and badge.
+    pub deposit: NearToken,
+}
+
+#[near]
+impl AirdropContract {
+    /// Enables near.social posts for claimers who opt in, or disables them with `None` (only
+    /// callable by the owner).
+    #[payable]
+    pub fn set_social_posts(&mut self, social_posts: Option<SocialPosts>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_social_posts");
+        if let Some(social_posts) = &social_posts {
+            require!(
+                !social_posts.badge.is_empty()
+                    && !social_posts.badge.contains(['/', '*', '.', ' ']),
+                "Invalid badge name"
+            );
+            require!(
+                !social_posts.deposit.is_zero(),
+                "The deposit must cover the storage of the posts"
+            );
+        }
+        self.social_posts = social_posts;
+        log!("Social posts set to {:?}", self.social_posts);
+    }
+
+    /// Returns the near.social post settings, `None` if posts are disabled.
+    pub fn get_social_posts(&self) -> Option<SocialPosts> {
+        self.social_posts.clone()
+    }
+}
+
+impl AirdropContract {
+    /// Extra deposit of a claim opting into a near.social post, which must be enabled.
+    pub(crate) fn internal_social_deposit(
+        &self,
+        post_to_social: Option<bool>,
+    ) -> Result<NearToken, ClaimError> {
+        if post_to_social != Some(true) {
+            return Ok(NO_DEPOSIT);
+        }
+        self.social_posts
+            .as_ref()
+            .map(|social_posts| social_posts.deposit)
+            .ok_or(ClaimError::SocialPostsDisabled)
+    }
+
+    /// Posts the successful `claim` on near.social from this contract's account, mentioning the
+    /// claimer, and awards it the badge. Paid by the social deposit of the claim.
+    pub(crate) fn internal_post_to_social(&self, claim: &PendingClaim) {
+        let Some(social_posts) = &self.social_posts else {
+            // Disabled since the claim started, the deposit goes to the treasury
+            Promise::new(self.treasury_id.clone())
+                .transfer(NearToken::from_yoctonear(claim.social_deposit.0));
+            return;
+        };
+        Promise::new(social_posts.social_contract.clone()).function_call(
+            "set".to_string(),
+            self.social_post_args(&social_posts.badge, &claim.account_id, claim.amount),
+            NearToken::from_yoctonear(claim.social_deposit.0),
+            GAS_FOR_SOCIAL_POST,
+        );
+    }
+
+    /// Arguments of the near.social `set` call: a post of this contract mentioning `account_id`,
+    /// its notification, and the holder entry of the badge.
+    pub(crate) fn social_post_args(
+        &self,
+        badge: &str,
+        account_id: &AccountId,
+        amount: U128,
+    ) -> Vec<u8> {
+        let contract_id = env::current_account_id();
+        let campaign = self.campaign_metadata.as_ref().map_or_else(
+            || self.token_contract.to_string(),
+            |metadata| metadata.title.clone(),
+        );
+        let post = json!({
+            "type": "md",
+            "text": format!("@{} claimed {} tokens of the {} airdrop", account_id, amount.0, campaign),
+        });
+        let notify = json!({
+            "key": account_id,
+            "value": {
+                "type": "mention",
+                "item": { "type": "social", "path": format!("{}/post/main", contract_id) },
+            },
+        });
+        json!({
+            "data": {
+                contract_id.as_str(): {
+                    "post": { "main": post.to_string() },
+                    "index": {
+                        "post": json!({ "key": "main", "value": { "type": "md" } }).to_string(),
+                        "notify": notify.to_string(),
+                    },
+                    "badge": { badge: { "holder": { account_id.as_str(): "" } } },
+                },
+            },
+        })
+        .to_string()
+        .into_bytes()
+    }
+}
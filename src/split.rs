@@ -0,0 +1,301 @@
+use near_sdk::json_types::U128;
+use near_sdk::{
+    env, log, near, serde_json, AccountId, Gas, NearToken, Promise, PromiseOrValue, PromiseResult,
+};
+
+use crate::{
+    AirdropContract, AirdropContractExt, ClaimError, ClaimOutcome, ClaimStatus, Denomination,
+    LeafExtras, PendingClaim, GAS_FOR_FT_TRANSFER, GAS_FOR_STORAGE_DEPOSIT,
+};
+
+/// Maximum number of receivers of a split claim.
+pub const MAX_SPLIT_RECEIVERS: usize = 5;
+// Static gas of `on_split_leg_transferred`
+const GAS_FOR_SPLIT_LEG_CALLBACK: Gas = Gas::from_tgas(10);
+// Static gas of `on_split_then_claimed`
+const GAS_FOR_SPLIT_CALLBACK: Gas = Gas::from_tgas(5);
+
+#[near]
+impl AirdropContract {
+    /// Claims the caller's leaf and distributes it to several receivers, e.g. the wallets of a
+    /// team. The amounts of `receivers` must add up to the leaf amount. Requires the same
+    /// deposit as `claim_airdrop`, and goes through the same eligibility gates, escrow and
+    /// legal holds. Each receiver is paid in its own leg, so a failed leg does not affect the
+    /// others: its tokens are owed to the caller, claimable with `claim_shortfall`. The claim is
+    /// recorded for the tokens paid. Split claims pay no protocol fee and no bonuses.
+    #[payable]
+    #[handle_result]
+    pub fn claim_split(
+        &mut self,
+        receivers: Vec<(AccountId, U128)>,
+        amount: U128,
+        merkle_proof: Vec<String>,
+        extras: Option<LeafExtras>,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_claim_deposit()?;
+        self.assert_denomination(Denomination::Tokens)?;
+        if receivers.is_empty()
+            || receivers.len() > MAX_SPLIT_RECEIVERS
+            || receivers.iter().any(|(_, leg)| leg.0 == 0)
+            || receivers
+                .iter()
+                .try_fold(0u128, |total, (_, leg)| total.checked_add(leg.0))
+                != Some(amount.0)
+        {
+            return Err(ClaimError::InvalidSplit);
+        }
+        let account_id = env::predecessor_account_id();
+        let extras = extras.unwrap_or_default();
+        if let Some(outcome) = self.internal_repeated_claim(&account_id, extras.nonce) {
+            return Ok(PromiseOrValue::Value(outcome));
+        }
+        self.internal_count_relayed_claim()?;
+        if extras.stream_duration.is_some() {
+            return Err(ClaimError::StreamingUnavailable);
+        }
+        if extras.lockup_duration.is_some() {
+            return Err(ClaimError::LockupRequired);
+        }
+        self.internal_verify_leaf(&account_id, amount, &merkle_proof, &extras)?;
+
+        self.internal_start_split(PendingClaim {
+            nonce: extras.nonce,
+            split: receivers,
+            ..self.internal_new_claim(account_id, amount)
+        })
+    }
+
+    /// Callback: After the legs of a split claim, completes the claim for the tokens of the legs
+    /// that were paid and refunds the rest of the deposit. The outcome is `PartiallyPaid` if
+    /// some legs failed, and `TransferFailed` if all did, their tokens being owed to the claimed
+    /// account either way.
+    #[private]
+    pub fn on_split_then_claimed(&mut self, claim: PendingClaim) -> ClaimOutcome {
+        let paid: u128 = claim
+            .split
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| match env::promise_result(*index as u64) {
+                PromiseResult::Successful(result) => {
+                    serde_json::from_slice(&result).unwrap_or(false)
+                }
+                PromiseResult::Failed => false,
+            })
+            .map(|(_, (_, leg))| leg.0)
+            .sum();
+        if paid == 0 {
+            self.internal_refund(&claim);
+            return ClaimOutcome::failed(ClaimStatus::TransferFailed);
+        }
+        let status = if paid < claim.amount.0 {
+            ClaimStatus::PartiallyPaid
+        } else {
+            ClaimStatus::Success
+        };
+        // The completion pays out of the deposit the fee, badge, record and lockup parts
+        let refund = [
+            claim.fee,
+            claim.social_deposit,
+            claim.badge_deposit,
+            claim.record_deposit,
+            claim.lockup_deposit,
+        ]
+        .into_iter()
+        .fold(claim.deposit.0, |refund, part| {
+            refund.saturating_sub(part.0)
+        });
+        if refund > 0 {
+            Promise::new(claim.claimer.clone()).transfer(NearToken::from_yoctonear(refund));
+        }
+        let outcome = self.internal_complete_claim(PendingClaim {
+            amount: U128(paid),
+            ..claim
+        });
+        ClaimOutcome { status, ..outcome }
+    }
+
+    /// Callback: After the storage_deposit of a split leg, transfers its tokens to
+    /// `receiver_id`.
+    #[private]
+    pub fn on_split_storage_then_transfer(
+        &mut self,
+        account_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        root_version: u32,
+        #[callback_result] call_result: Result<Option<serde_json::Value>, near_sdk::PromiseError>,
+    ) -> PromiseOrValue<bool> {
+        if call_result.is_err() {
+            self.internal_release_split_leg(
+                &account_id,
+                amount,
+                root_version,
+                ClaimStatus::StorageFailed,
+            );
+            return PromiseOrValue::Value(false);
+        }
+        self.split_leg_transfer(account_id, receiver_id, amount, root_version)
+            .into()
+    }
+
+    /// Callback: After the ft_transfer of a split leg, logs it, or owes its tokens to
+    /// `account_id` if it failed. The reservation of paid legs is released once the claim
+    /// completes.
+    #[private]
+    pub fn on_split_leg_transferred(
+        &mut self,
+        account_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        root_version: u32,
+        #[callback_result] call_result: Result<(), near_sdk::PromiseError>,
+    ) -> bool {
+        if call_result.is_err() {
+            self.internal_release_split_leg(
+                &account_id,
+                amount,
+                root_version,
+                ClaimStatus::TransferFailed,
+            );
+            return false;
+        }
+        log!(
+            "Split leg of @{} paid {} tokens to @{}",
+            account_id,
+            amount.0,
+            receiver_id
+        );
+        true
+    }
+}
+
+impl AirdropContract {
+    /// Reserves the leaf of the split `claim`, without fees or bonuses, and marks it as
+    /// claimed, then pays its legs or holds it in escrow like `internal_start_claim`.
+    fn internal_start_split(
+        &mut self,
+        mut claim: PendingClaim,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_terms_accepted(&claim.account_id)?;
+        if self.held_claims.contains_key(&claim.account_id) {
+            return Err(ClaimError::AlreadyClaimed);
+        }
+        if self.escrow_period.is_none() {
+            self.assert_claim_gas(&claim)?;
+        }
+        self.assert_claim_rate()?;
+        self.internal_reserve_basket_leg(claim.amount.0)?;
+        self.internal_record_claim_rate();
+        claim.root_version = Some(self.root_version);
+        self.internal_mark_leaf_claimed(claim.account_id.clone(), claim.nonce);
+        log!(
+            "Claim of @{} split across {} receivers",
+            claim.account_id,
+            claim.split.len()
+        );
+        Ok(self.internal_escrow_or_transfer(claim))
+    }
+
+    /// Static gas of the legs of the split `claim` and of their completion.
+    pub(crate) fn split_gas(claim: &PendingClaim) -> Gas {
+        GAS_FOR_STORAGE_DEPOSIT
+            .saturating_add(GAS_FOR_FT_TRANSFER)
+            .saturating_add(GAS_FOR_SPLIT_LEG_CALLBACK.saturating_mul(2))
+            .saturating_mul(claim.split.len() as u64)
+            .saturating_add(GAS_FOR_SPLIT_CALLBACK)
+    }
+
+    /// Pays each receiver of the split `claim` in its own leg, then completes the claim.
+    /// Receivers are registered by the contract, or must already be registered in
+    /// claimer-paid storage mode.
+    pub(crate) fn internal_split_legs(&self, claim: PendingClaim) -> Promise {
+        let root_version = claim.root_version.unwrap_or(self.root_version);
+        claim
+            .split
+            .iter()
+            .map(|(receiver_id, leg)| {
+                if claim.storage_deposit.0 > 0 {
+                    return self.split_leg_transfer(
+                        claim.account_id.clone(),
+                        receiver_id.clone(),
+                        *leg,
+                        root_version,
+                    );
+                }
+                Self::storage_deposits(
+                    &self.token_contract,
+                    vec![(receiver_id.clone(), self.token_storage_deposit())],
+                )
+                .unwrap_or_else(|| env::abort())
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(
+                            GAS_FOR_FT_TRANSFER
+                                .saturating_add(GAS_FOR_SPLIT_LEG_CALLBACK.saturating_mul(2)),
+                        )
+                        .on_split_storage_then_transfer(
+                            claim.account_id.clone(),
+                            receiver_id.clone(),
+                            *leg,
+                            root_version,
+                        ),
+                )
+            })
+            .reduce(Promise::and)
+            .unwrap_or_else(|| env::abort())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_SPLIT_CALLBACK)
+                    .on_split_then_claimed(claim),
+            )
+    }
+
+    /// Transfers a split leg to `receiver_id`, then settles it.
+    fn split_leg_transfer(
+        &self,
+        account_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        root_version: u32,
+    ) -> Promise {
+        Promise::new(self.token_contract.clone())
+            .function_call(
+                "ft_transfer".to_string(),
+                Self::ft_transfer_args(&receiver_id, amount.0, None),
+                NearToken::from_yoctonear(1),
+                GAS_FOR_FT_TRANSFER,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_SPLIT_LEG_CALLBACK)
+                    .on_split_leg_transferred(account_id, receiver_id, amount, root_version),
+            )
+    }
+
+    /// Releases a failed split leg, reserved against the root of `root_version`, and adds its
+    /// tokens to the shortfall of `account_id`, which stays claimed.
+    fn internal_release_split_leg(
+        &mut self,
+        account_id: &AccountId,
+        amount: U128,
+        root_version: u32,
+        status: ClaimStatus,
+    ) {
+        self.distributed = self.distributed.saturating_sub(amount.0);
+        self.reserved = self.reserved.saturating_sub(amount.0);
+        if root_version == self.root_version {
+            self.root_claimed = self.root_claimed.saturating_sub(amount.0);
+        }
+        let shortfall = self
+            .shortfalls
+            .get(account_id)
+            .map_or(0, |shortfall| shortfall.0);
+        log!(
+            "Split leg of @{} failed, {} tokens owed",
+            account_id,
+            amount.0
+        );
+        self.internal_insert_shortfall(account_id.clone(), U128(shortfall + amount.0));
+        self.internal_record_failure(account_id, amount, status);
+    }
+}
@@ -0,0 +1,106 @@
+use near_sdk::json_types::U128;
+use near_sdk::{log, near, AccountId};
+
+use crate::{apply_bps, AirdropContract, AirdropContractExt, PendingClaim};
+
+/// Bonus of accounts claiming in consecutive rounds, see `open_round`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreakBonus {
+    /// Bonus in basis points of the claimed amount for each consecutive round before the
+    /// current one, e.g. 500 for +5% per round.
+    pub bonus_bps_per_round: u16,
+    /// Cap of the bonus, in basis points.
+    pub max_bonus_bps: u16,
+}
+
+impl StreakBonus {
+    /// Bonus in basis points of a claim extending a streak to `streak` rounds.
+    pub fn bonus_bps(&self, streak: u32) -> u16 {
+        (u32::from(self.bonus_bps_per_round) * streak.saturating_sub(1))
+            .min(u32::from(self.max_bonus_bps)) as u16
+    }
+}
+
+/// Consecutive rounds claimed by an account.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Streak {
+    /// Last round the account claimed in.
+    pub last_round: u32,
+    /// Number of consecutive rounds claimed, up to `last_round`.
+    pub length: u32,
+}
+
+#[near]
+impl AirdropContract {
+    /// Sets or clears the streak bonus and the pool reserved for it (only callable by the
+    /// owner). Bonuses stop once the pool is exhausted.
+    #[payable]
+    pub fn set_streak_bonus(&mut self, streak_bonus: Option<StreakBonus>, bonus_pool: U128) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_streak_bonus");
+        self.streak_bonus = streak_bonus;
+        self.streak_bonus_pool = bonus_pool.0;
+        log!("Streak bonus updated, bonus pool {}", bonus_pool.0);
+    }
+
+    /// Returns the streak bonus, if any.
+    pub fn get_streak_bonus(&self) -> Option<StreakBonus> {
+        self.streak_bonus.clone()
+    }
+
+    /// Returns the tokens left to pay streak bonuses.
+    pub fn get_streak_bonus_pool(&self) -> U128 {
+        U128(self.streak_bonus_pool)
+    }
+
+    /// Returns the number of consecutive rounds `account_id` claimed in, up to the current or
+    /// the previous round. 0 once the streak is broken.
+    pub fn get_streak(&self, account_id: AccountId) -> u32 {
+        self.streaks
+            .get(&account_id)
+            .filter(|streak| streak.last_round.saturating_add(1) >= self.round)
+            .map_or(0, |streak| streak.length)
+    }
+}
+
+impl AirdropContract {
+    /// Length of the streak of `account_id` with a claim in the current round.
+    fn next_streak(&self, account_id: &AccountId) -> u32 {
+        match self.streaks.get(account_id) {
+            Some(streak) if streak.last_round == self.round => streak.length,
+            Some(streak) if streak.last_round.saturating_add(1) == self.round => {
+                streak.length.saturating_add(1)
+            }
+            _ => 1,
+        }
+    }
+
+    /// Takes the streak bonus of `payout` claimed by `account_id` from the streak bonus pool.
+    pub(crate) fn internal_take_streak_bonus(
+        &mut self,
+        account_id: &AccountId,
+        payout: u128,
+    ) -> u128 {
+        let Some(streak_bonus) = &self.streak_bonus else {
+            return 0;
+        };
+        let bonus_bps = streak_bonus.bonus_bps(self.next_streak(account_id));
+        let bonus = apply_bps(payout, bonus_bps).min(self.streak_bonus_pool);
+        self.streak_bonus_pool -= bonus;
+        bonus
+    }
+
+    /// Extends the streak of the account of the successful `claim` to the current round.
+    pub(crate) fn internal_record_streak(&mut self, claim: &PendingClaim) {
+        if !claim.claims_leaf() || claim.pushed {
+            return;
+        }
+        let streak = Streak {
+            last_round: self.round,
+            length: self.next_streak(&claim.account_id),
+        };
+        self.streaks.insert(claim.account_id.clone(), streak);
+    }
+}
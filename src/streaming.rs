@@ -0,0 +1,68 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{env, log, near, AccountId};
+
+use crate::{AirdropContract, AirdropContractExt, ClaimError, PendingClaim, TransferCall};
+
+#[near]
+impl AirdropContract {
+    /// Sets the Roketo streaming contract paying out leaves with a stream duration, e.g.
+    /// `streaming.r-v2.near`, or disables streams with `None` (only callable by the owner).
+    #[payable]
+    pub fn set_streaming_contract(&mut self, streaming_contract: Option<AccountId>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_streaming_contract");
+        self.streaming_contract = streaming_contract;
+        log!("Streaming contract set to {:?}", self.streaming_contract);
+    }
+
+    /// Returns the streaming contract paying out leaves with a stream duration, if any.
+    pub fn get_streaming_contract(&self) -> Option<AccountId> {
+        self.streaming_contract.clone()
+    }
+}
+
+impl AirdropContract {
+    /// Streams can only be created for plain token payouts, while a streaming contract is set.
+    pub(crate) fn assert_streamable(&self, claim: &PendingClaim) -> Result<(), ClaimError> {
+        if claim.stream_duration.is_some()
+            && (self.streaming_contract.is_none()
+                || claim.transfer_call.is_some()
+                || claim.liquid_stake
+                || claim.lock_months.is_some())
+        {
+            return Err(ClaimError::StreamingUnavailable);
+        }
+        Ok(())
+    }
+
+    /// `ft_transfer_call` creating the stream of `claim` with the streaming contract, `None`
+    /// for lump-sum payouts.
+    pub(crate) fn stream_transfer_call(&self, claim: &PendingClaim) -> Option<TransferCall> {
+        let duration = claim.stream_duration?;
+        let streaming_contract = self.streaming_contract.clone()?;
+        Some(TransferCall {
+            receiver_id: streaming_contract,
+            msg: self.stream_msg(claim.receiver_id(), claim.net_amount(), duration.0),
+        })
+    }
+
+    /// Message of a Roketo stream of `amount` tokens to `receiver_id` over `duration` seconds.
+    /// The stream is owned by this contract and locked, so it can be neither paused nor
+    /// stopped. Roketo deducts its commission from the deposit, which ends the stream slightly
+    /// earlier.
+    fn stream_msg(&self, receiver_id: &AccountId, amount: u128, duration: u64) -> String {
+        json!({
+            "Create": {
+                "request": {
+                    "owner_id": env::current_account_id(),
+                    "receiver_id": receiver_id,
+                    "tokens_per_sec": U128((amount / u128::from(duration.max(1))).max(1)),
+                    "is_auto_start_enabled": true,
+                    "is_locked": true,
+                },
+            },
+        })
+        .to_string()
+    }
+}
@@ -0,0 +1,99 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{env, log, near, AccountId, PromiseOrValue};
+
+use crate::{
+    AirdropContract, AirdropContractExt, ClaimError, ClaimOutcome, Denomination, LeafExtras,
+    PendingClaim, TransferCall,
+};
+
+/// Ref Finance exchange swapping claimed tokens for `token_out`, see `claim_and_swap`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SwapRoute {
+    /// Exchange contract, which must support `swap_out_recipient` in its `ft_on_transfer`
+    /// message.
+    pub exchange_id: AccountId,
+    /// Token claimers receive, e.g. a stablecoin.
+    pub token_out: AccountId,
+}
+
+#[near]
+impl AirdropContract {
+    /// Sets the exchange and output token of `claim_and_swap`, or disables it with `None`
+    /// (only callable by the owner).
+    #[payable]
+    pub fn set_swap_route(&mut self, swap_route: Option<SwapRoute>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_swap_route");
+        self.swap_route = swap_route;
+        log!("Swap route set to {:?}", self.swap_route);
+    }
+
+    /// Returns the exchange and output token of `claim_and_swap`, if any.
+    pub fn get_swap_route(&self) -> Option<SwapRoute> {
+        self.swap_route.clone()
+    }
+
+    /// Claims like `claim_airdrop`, but swaps the tokens in pool `pool_id` of the configured
+    /// exchange, which sends the output token to the caller.
+    /// - `min_out`: Fails the swap if it yields fewer output tokens, guarding against slippage.
+    ///   The claim still succeeds, with the claimed tokens refunded to the caller.
+    #[payable]
+    #[handle_result]
+    pub fn claim_and_swap(
+        &mut self,
+        amount: U128,
+        merkle_proof: Vec<String>,
+        extras: Option<LeafExtras>,
+        pool_id: u64,
+        min_out: U128,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_claim_deposit()?;
+        self.assert_denomination(Denomination::Tokens)?;
+        let swap_route = self.swap_route.clone().ok_or(ClaimError::SwapUnavailable)?;
+        let account_id = env::predecessor_account_id();
+        let extras = extras.unwrap_or_default();
+        if let Some(outcome) = self.internal_repeated_claim(&account_id, extras.nonce) {
+            return Ok(PromiseOrValue::Value(outcome));
+        }
+        self.internal_count_relayed_claim()?;
+        self.internal_verify_leaf(&account_id, amount, &merkle_proof, &extras)?;
+
+        let msg = self.swap_msg(&swap_route, pool_id, min_out, &account_id);
+        self.internal_start_claim(PendingClaim {
+            referrer: extras.referrer,
+            transfer_call: Some(TransferCall {
+                receiver_id: swap_route.exchange_id,
+                msg,
+            }),
+            nonce: extras.nonce,
+            stream_duration: extras.stream_duration,
+            lockup_duration: extras.lockup_duration,
+            ..self.internal_new_claim(account_id, amount)
+        })
+    }
+}
+
+impl AirdropContract {
+    /// `ft_transfer_call` message of a Ref Finance swap of the whole transferred amount, whose
+    /// output goes to `recipient`. Ref refunds the tokens if the swap fails.
+    pub(crate) fn swap_msg(
+        &self,
+        swap_route: &SwapRoute,
+        pool_id: u64,
+        min_out: U128,
+        recipient: &AccountId,
+    ) -> String {
+        json!({
+            "actions": [{
+                "pool_id": pool_id,
+                "token_in": self.token_contract,
+                "token_out": swap_route.token_out,
+                "min_amount_out": min_out,
+            }],
+            "swap_out_recipient": recipient,
+        })
+        .to_string()
+    }
+}
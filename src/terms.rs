@@ -0,0 +1,125 @@
+use near_sdk::json_types::{Base58CryptoHash, U64};
+use near_sdk::{env, log, near, require, AccountId, Promise};
+
+use crate::{AirdropContract, AirdropContractExt, ClaimError};
+
+/// Terms of service accepted by an account.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TermsAcceptance {
+    /// Hash of the accepted terms.
+    pub terms_hash: Base58CryptoHash,
+    /// Block time of the acceptance, in nanoseconds since the Unix epoch.
+    pub accepted_at: U64,
+    pub block_height: U64,
+}
+
+#[near]
+impl AirdropContract {
+    /// Requires claimers to accept the terms of service with hash `terms_hash` before claiming,
+    /// or drops the requirement with `None` (only callable by the owner). Accounts that accepted
+    /// earlier terms must accept the new ones.
+    #[payable]
+    pub fn set_terms_hash(&mut self, terms_hash: Option<Base58CryptoHash>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_terms_hash");
+        log!("Terms set to {:?}", terms_hash.as_ref().map(String::from));
+        self.terms_hash = terms_hash;
+    }
+
+    /// Records the acceptance of the current terms of service by the caller, who attaches the
+    /// storage of the record. The excess deposit is refunded.
+    #[payable]
+    pub fn accept_terms(&mut self, terms_hash: Base58CryptoHash) {
+        require!(
+            self.terms_hash.as_ref() == Some(&terms_hash),
+            "Not the current terms"
+        );
+        let account_id = env::predecessor_account_id();
+        let storage_usage = env::storage_usage();
+        if !self.terms_accepted.contains_key(&account_id) {
+            self.terms_acceptors.push(account_id.clone());
+        }
+        self.terms_accepted.insert(
+            account_id.clone(),
+            TermsAcceptance {
+                terms_hash,
+                accepted_at: U64(env::block_timestamp()),
+                block_height: U64(env::block_height()),
+            },
+        );
+        // Write the cached entries so that their storage is measured
+        self.terms_accepted.flush();
+        self.terms_acceptors.flush();
+        let cost = env::storage_byte_cost()
+            .saturating_mul(env::storage_usage().saturating_sub(storage_usage).into());
+        let deposit = env::attached_deposit();
+        if deposit < cost {
+            env::panic_str(&format!(
+                "Attach at least {} yoctoNEAR for the storage of the acceptance",
+                cost.as_yoctonear()
+            ));
+        }
+        log!(
+            "@{} accepted the terms {}",
+            account_id,
+            String::from(&terms_hash)
+        );
+        let refund = deposit.saturating_sub(cost);
+        if !refund.is_zero() {
+            Promise::new(account_id).transfer(refund);
+        }
+    }
+
+    /// Returns the hash of the terms of service claimers must accept, if any.
+    pub fn get_terms_hash(&self) -> Option<Base58CryptoHash> {
+        self.terms_hash
+    }
+
+    /// Returns the latest terms of service accepted by `account_id`, if any.
+    pub fn get_terms_acceptance(&self, account_id: AccountId) -> Option<TermsAcceptance> {
+        self.terms_accepted.get(&account_id).cloned()
+    }
+
+    /// Returns up to `limit` accounts that accepted terms of service, with their latest
+    /// acceptance, starting at index `from_index` in the order of their first acceptance.
+    pub fn get_terms_acceptances(
+        &self,
+        from_index: u32,
+        limit: u32,
+    ) -> Vec<(AccountId, TermsAcceptance)> {
+        self.terms_acceptors
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .filter_map(|account_id| {
+                Some((
+                    account_id.clone(),
+                    self.terms_accepted.get(account_id)?.clone(),
+                ))
+            })
+            .collect()
+    }
+
+    /// Returns the number of accounts that accepted terms of service.
+    pub fn get_terms_acceptance_count(&self) -> u32 {
+        self.terms_acceptors.len()
+    }
+}
+
+impl AirdropContract {
+    /// Ensures `account_id` accepted the current terms of service, if any.
+    pub(crate) fn assert_terms_accepted(&self, account_id: &AccountId) -> Result<(), ClaimError> {
+        let Some(terms_hash) = &self.terms_hash else {
+            return Ok(());
+        };
+        if self
+            .terms_accepted
+            .get(account_id)
+            .is_some_and(|acceptance| &acceptance.terms_hash == terms_hash)
+        {
+            return Ok(());
+        }
+        Err(ClaimError::TermsNotAccepted)
+    }
+}
@@ -0,0 +1,182 @@
+//! Helpers for projects testing against the airdrop, built with the `test-utils` feature: unit
+//! test contexts, deterministic trees, and a near-workspaces sandbox driving the claim flow.
+
+use anyhow::{ensure, Result};
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::test_utils::VMContextBuilder;
+use near_sdk::{AccountId, Gas, NearToken};
+use near_workspaces::network::Sandbox;
+use near_workspaces::result::ExecutionFinalResult;
+use near_workspaces::{Account, Contract, Worker};
+
+use crate::MerkleTree;
+
+/// Total supply minted to the owner by `AirdropSandbox::deploy`.
+pub const SANDBOX_TOKEN_SUPPLY: u128 = 1_000_000_000;
+
+/// Unit test context for a call by `predecessor` with `attached_deposit` and enough gas for
+/// any claim.
+pub fn get_context(predecessor: AccountId, attached_deposit: NearToken) -> VMContextBuilder {
+    let mut builder = VMContextBuilder::new();
+    builder
+        .predecessor_account_id(predecessor)
+        .attached_deposit(attached_deposit)
+        .prepaid_gas(Gas::from_tgas(300));
+    builder
+}
+
+/// Entries `user{i}.{parent}` with `100 * (i + 1)` tokens, for `i` in `0..count`.
+pub fn deterministic_entries(parent: &AccountId, count: usize) -> Vec<(AccountId, u128)> {
+    (0..count)
+        .map(|i| {
+            let account_id = format!("user{}.{}", i, parent)
+                .parse()
+                .expect("Invalid parent account ID");
+            (account_id, 100 * (i as u128 + 1))
+        })
+        .collect()
+}
+
+/// Keccak256 `V1` tree of `deterministic_entries`.
+pub fn deterministic_tree(parent: &AccountId, count: usize) -> MerkleTree {
+    MerkleTree::from_entries(deterministic_entries(parent, count))
+}
+
+/// Sandbox with a NEP-141 token and an airdrop of `deterministic_tree` to `users`.
+pub struct AirdropSandbox {
+    pub worker: Worker<Sandbox>,
+    /// Owner of the token supply and of the airdrop.
+    pub owner: Account,
+    pub token: Contract,
+    pub airdrop: Contract,
+    pub tree: MerkleTree,
+    /// Accounts of the tree entries, in leaf order.
+    pub users: Vec<Account>,
+}
+
+impl AirdropSandbox {
+    /// Starts a sandbox, creates `users` accounts, deploys and initializes the token (any
+    /// contract with the standard `new(owner_id, total_supply, metadata)`) and the airdrop, and
+    /// registers the airdrop with the token. The airdrop is not funded, see `fund`.
+    pub async fn deploy(airdrop_wasm: &[u8], token_wasm: &[u8], users: usize) -> Result<Self> {
+        let worker = near_workspaces::sandbox().await?;
+        let owner = worker.root_account()?;
+        let tree = deterministic_tree(owner.id(), users);
+        let mut accounts = Vec::with_capacity(users);
+        for i in 0..users {
+            let account = owner
+                .create_subaccount(&format!("user{}", i))
+                .initial_balance(NearToken::from_near(10))
+                .transact()
+                .await?
+                .into_result()?;
+            accounts.push(account);
+        }
+
+        let token = worker.dev_deploy(token_wasm).await?;
+        owner
+            .call(token.id(), "new")
+            .args_json(json!({
+                "owner_id": owner.id(),
+                "total_supply": U128(SANDBOX_TOKEN_SUPPLY),
+                "metadata": {
+                    "spec": "ft-1.0.0",
+                    "name": "Test Token",
+                    "symbol": "TT",
+                    "decimals": 18,
+                },
+            }))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+
+        let airdrop = worker.dev_deploy(airdrop_wasm).await?;
+        owner
+            .call(airdrop.id(), "new")
+            .args_json(json!({
+                "owner_id": owner.id(),
+                "token_contract": token.id(),
+                "merkle_root": tree.root(),
+            }))
+            .transact()
+            .await?
+            .into_result()?;
+        // Benchmarks and tests swap roots back to back
+        owner
+            .call(airdrop.id(), "set_root_update_cooldown")
+            .args_json(json!({ "cooldown": "0" }))
+            .deposit(NearToken::from_yoctonear(1))
+            .transact()
+            .await?
+            .into_result()?;
+        owner
+            .call(token.id(), "storage_deposit")
+            .args_json(json!({ "account_id": airdrop.id(), "registration_only": true }))
+            .deposit(NearToken::from_millinear(1250))
+            .transact()
+            .await?
+            .into_result()?;
+
+        Ok(Self {
+            worker,
+            owner,
+            token,
+            airdrop,
+            tree,
+            users: accounts,
+        })
+    }
+
+    /// Transfers `amount` tokens from the owner to the airdrop.
+    pub async fn fund(&self, amount: u128) -> Result<()> {
+        self.owner
+            .call(self.token.id(), "ft_transfer")
+            .args_json(json!({ "receiver_id": self.airdrop.id(), "amount": U128(amount) }))
+            .deposit(NearToken::from_yoctonear(1))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+        Ok(())
+    }
+
+    /// Claims the entry of user `index` with its proof, running the whole promise chain.
+    pub async fn claim(&self, index: usize) -> Result<ExecutionFinalResult> {
+        let (_, amount) = self.tree.entries()[index];
+        let proof = self.tree.proof(index).unwrap_or_default();
+        let result = self.users[index]
+            .call(self.airdrop.id(), "claim_airdrop")
+            .args_json(json!({ "amount": U128(amount), "merkle_proof": proof }))
+            .deposit(NearToken::from_yoctonear(1))
+            .max_gas()
+            .transact()
+            .await?;
+        Ok(result)
+    }
+
+    /// Token balance of `account_id`.
+    pub async fn ft_balance_of(&self, account_id: &AccountId) -> Result<u128> {
+        let balance: U128 = self
+            .token
+            .view("ft_balance_of")
+            .args_json(json!({ "account_id": account_id }))
+            .await?
+            .json()?;
+        Ok(balance.0)
+    }
+
+    /// Fails unless `account_id` holds exactly `expected` tokens.
+    pub async fn assert_balance(&self, account_id: &AccountId, expected: u128) -> Result<()> {
+        let balance = self.ft_balance_of(account_id).await?;
+        ensure!(
+            balance == expected,
+            "@{} holds {} tokens, expected {}",
+            account_id,
+            balance,
+            expected
+        );
+        Ok(())
+    }
+}
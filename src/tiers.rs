@@ -0,0 +1,35 @@
+use near_sdk::json_types::U64;
+use near_sdk::{env, log, near};
+
+use crate::{AirdropContract, AirdropContractExt, ClaimError};
+
+#[near]
+impl AirdropContract {
+    /// Sets the time from which leaves of `tier` can be claimed, or locks the tier with `None`
+    /// (only callable by the owner). Leaves without a tier are not affected.
+    #[payable]
+    pub fn set_tier_start(&mut self, tier: u8, starts_at: Option<U64>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_tier_start");
+        match starts_at {
+            Some(starts_at) => self.tier_starts.insert(tier, starts_at),
+            None => self.tier_starts.remove(&tier),
+        };
+        log!("Tier {} claims start at {:?}", tier, starts_at);
+    }
+
+    /// Returns the time from which leaves of `tier` can be claimed, `None` while it is locked.
+    pub fn get_tier_start(&self, tier: u8) -> Option<U64> {
+        self.tier_starts.get(&tier).copied()
+    }
+}
+
+impl AirdropContract {
+    /// Ensures the claims of `tier` have started.
+    pub(crate) fn assert_tier_open(&self, tier: u8) -> Result<(), ClaimError> {
+        match self.tier_starts.get(&tier) {
+            Some(starts_at) if env::block_timestamp() >= starts_at.0 => Ok(()),
+            _ => Err(ClaimError::TierNotOpen),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Browser bindings of the proof verification, built with the `wasm-bindgen` feature so claim
+//! pages check proofs with the contract's own code. Hash algorithms and leaf formats are named
+//! like in the contract's JSON (`"Keccak256"`, `"Sha256"`, `"V1"`, `"V2"`), defaulting to
+//! keccak256 and `V1`.
+
+use near_sdk::json_types::U128;
+use near_sdk::serde::de::DeserializeOwned;
+use near_sdk::serde_json;
+use near_sdk::AccountId;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    normalize_account_id, AirdropContract, ComputedLeaf, LeafDomain, LeafExtras, LeafFormat,
+};
+
+/// Checks `proof` of `leaf` against `root`, like `claim_airdrop` does.
+#[wasm_bindgen(js_name = verifyMerkleProof)]
+pub fn verify_merkle_proof(
+    leaf: String,
+    root: String,
+    proof: Vec<String>,
+    hash_algorithm: Option<String>,
+    leaf_format: Option<String>,
+) -> Result<bool, JsError> {
+    AirdropContract::verify_merkle_proof_with(
+        parse_option(hash_algorithm)?,
+        parse_option(leaf_format)?,
+        leaf,
+        &root,
+        &proof,
+    )
+    .map_err(|err| JsError::new(&err.to_string()))
+}
+
+/// Returns the JSON of the `ComputedLeaf` the `compute_leaf` view would return. `amount` is a
+/// decimal string and `extras` the JSON of `LeafExtras`, if any. The leaf is prefixed with the
+/// domain of `contract_id` and `campaign_id` if the contract has a leaf domain.
+#[wasm_bindgen(js_name = computeLeaf)]
+pub fn compute_leaf(
+    account_id: String,
+    amount: String,
+    extras: Option<String>,
+    hash_algorithm: Option<String>,
+    leaf_format: Option<String>,
+    contract_id: Option<String>,
+    campaign_id: Option<String>,
+) -> Result<String, JsError> {
+    let account_id =
+        normalize_account_id(&account_id).ok_or_else(|| JsError::new("Invalid account ID."))?;
+    let amount: U128 = serde_json::from_value(serde_json::Value::String(amount))?;
+    let extras: LeafExtras = match extras {
+        Some(extras) => serde_json::from_str(&extras)?,
+        None => LeafExtras::default(),
+    };
+    let mut leaf = extras.encode_leaf(&account_id, amount.0);
+    if let Some(contract_id) = contract_id {
+        let contract_id: AccountId = contract_id
+            .parse()
+            .map_err(|_| JsError::new("Invalid contract ID."))?;
+        leaf = LeafDomain { campaign_id }.prefix_leaf(&contract_id, &leaf);
+    }
+    let hash = parse_option::<LeafFormat>(leaf_format)?
+        .hash_leaf(parse_option(hash_algorithm)?, leaf.as_bytes());
+    let computed = ComputedLeaf {
+        leaf,
+        hash: hex::encode(hash),
+    };
+    Ok(serde_json::to_string(&computed)?)
+}
+
+/// Parses a unit variant from its JSON name, the default if `None`.
+fn parse_option<T>(name: Option<String>) -> Result<T, JsError>
+where
+    T: Default + DeserializeOwned,
+{
+    match name {
+        Some(name) => Ok(serde_json::from_value(serde_json::Value::String(name))?),
+        None => Ok(T::default()),
+    }
+}
@@ -0,0 +1,94 @@
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::serde_json::{self, json};
+use near_sdk::{env, near, AccountId, NearToken};
+
+use crate::{AirdropContract, AirdropContractExt};
+
+/// Claim page served at `/`, with `__CONFIG__` replaced by the JSON of `/config.json`.
+const CLAIM_PAGE: &str = include_str!("web4.html");
+
+/// Request of the Web4 gateway, e.g. `https://airdrop.near.page/`.
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct Web4Request {
+    /// Account signed in on the gateway, if any.
+    #[serde(rename = "accountId")]
+    pub account_id: Option<AccountId>,
+    pub path: String,
+}
+
+/// Response to the Web4 gateway: a body with its content type, or an error status.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Web4Response {
+    #[serde(rename = "contentType", skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<Base64VecU8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+}
+
+impl Web4Response {
+    fn body(content_type: &str, body: String) -> Self {
+        Self {
+            content_type: Some(content_type.to_string()),
+            body: Some(body.into_bytes().into()),
+            status: None,
+        }
+    }
+
+    fn status(status: u16) -> Self {
+        Self {
+            content_type: None,
+            body: None,
+            status: Some(status),
+        }
+    }
+}
+
+#[near]
+impl AirdropContract {
+    /// Serves a claim page over the Web4 convention: `/` is the page and `/config.json` the
+    /// airdrop settings it reads. Claims are sent through the gateway and signed by the wallet.
+    pub fn web4_get(&self, request: Web4Request) -> Web4Response {
+        match request.path.as_str() {
+            "/" | "/index.html" => {
+                let mut config = self.web4_config();
+                config["account_id"] = json!(request.account_id);
+                // Escaped so campaign metadata cannot close the script tag
+                let config = config.to_string().replace('<', "\\u003c");
+                Web4Response::body(
+                    "text/html; charset=UTF-8",
+                    CLAIM_PAGE.replace("__CONFIG__", &config),
+                )
+            }
+            "/config.json" => {
+                Web4Response::body("application/json", self.web4_config().to_string())
+            }
+            _ => Web4Response::status(404),
+        }
+    }
+}
+
+impl AirdropContract {
+    /// Settings of the claim page, including the exact deposit `claim_airdrop` expects.
+    fn web4_config(&self) -> serde_json::Value {
+        let claim_deposit = self
+            .claim_deposit()
+            .saturating_add(NearToken::from_yoctonear(1));
+        json!({
+            "contract_id": env::current_account_id(),
+            "token_contract": self.token_contract,
+            "merkle_root": self.merkle_root,
+            "hash_algorithm": self.hash_algorithm,
+            "leaf_format": self.leaf_format,
+            "leaf_domain": self.leaf_domain,
+            "campaign": self.campaign_metadata,
+            "token": self.token_info,
+            "claim_deadline": self.claim_deadline,
+            "claim_deposit": U128(claim_deposit.as_yoctonear()),
+            "finalized": self.finalized,
+        })
+    }
+}
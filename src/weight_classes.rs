@@ -0,0 +1,94 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, log, near, require, PromiseOrValue};
+
+use crate::{
+    AirdropContract, AirdropContractExt, ClaimError, ClaimOutcome, Denomination, LeafExtras,
+    PendingClaim,
+};
+
+/// Token amount paid to leaves whose weight reaches `min_weight`, see `claim_weighted`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WeightClass {
+    /// Lowest leaf weight of the class.
+    pub min_weight: U128,
+    /// Tokens paid to each leaf of the class.
+    pub amount: U128,
+}
+
+#[near]
+impl AirdropContract {
+    /// Sets the weight classes mapping leaf weights to token amounts (only callable by the
+    /// owner, before the first claim). Classes must be sorted by strictly increasing
+    /// `min_weight`. From then on, leaf amounts are weights claimed with `claim_weighted`. An
+    /// empty list goes back to token leaves.
+    #[payable]
+    pub fn set_weight_classes(&mut self, weight_classes: Vec<WeightClass>) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_weight_classes");
+        require!(
+            !self.claims_started(),
+            "Claims have started, the weight classes can no longer change"
+        );
+        require!(
+            weight_classes
+                .windows(2)
+                .all(|pair| pair[0].min_weight.0 < pair[1].min_weight.0),
+            "Weight classes must be sorted by increasing min_weight"
+        );
+        self.weight_classes = weight_classes;
+        log!("{} weight classes set", self.weight_classes.len());
+    }
+
+    /// Returns the weight classes mapping leaf weights to token amounts.
+    pub fn get_weight_classes(&self) -> Vec<WeightClass> {
+        self.weight_classes.clone()
+    }
+
+    /// Returns the tokens paid to a leaf of `weight`, if it reaches a weight class.
+    pub fn get_weighted_amount(&self, weight: U128) -> Option<U128> {
+        self.weight_class_amount(weight.0)
+    }
+
+    /// Claims a leaf whose amount is a weight, paying the token amount of its weight class.
+    #[payable]
+    #[handle_result]
+    pub fn claim_weighted(
+        &mut self,
+        weight: U128,
+        merkle_proof: Vec<String>,
+        extras: Option<LeafExtras>,
+    ) -> Result<PromiseOrValue<ClaimOutcome>, ClaimError> {
+        self.assert_claim_deposit()?;
+        self.assert_denomination(Denomination::WeightClass)?;
+        let account_id = env::predecessor_account_id();
+        let extras = extras.unwrap_or_default();
+        if let Some(outcome) = self.internal_repeated_claim(&account_id, extras.nonce) {
+            return Ok(PromiseOrValue::Value(outcome));
+        }
+        self.internal_count_relayed_claim()?;
+        self.internal_verify_leaf(&account_id, weight, &merkle_proof, &extras)?;
+        let amount = self
+            .weight_class_amount(weight.0)
+            .ok_or(ClaimError::NoWeightClass)?;
+
+        self.internal_start_claim(PendingClaim {
+            referrer: extras.referrer,
+            nonce: extras.nonce,
+            stream_duration: extras.stream_duration,
+            lockup_duration: extras.lockup_duration,
+            ..self.internal_new_claim(account_id, amount)
+        })
+    }
+}
+
+impl AirdropContract {
+    /// Token amount of the highest weight class `weight` reaches.
+    fn weight_class_amount(&self, weight: u128) -> Option<U128> {
+        self.weight_classes
+            .iter()
+            .rev()
+            .find(|class| class.min_weight.0 <= weight)
+            .map(|class| class.amount)
+    }
+}
@@ -0,0 +1,143 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, log, near, require};
+
+use crate::{AirdropContract, AirdropContractExt, AirdropEvent, WithdrawalEvent};
+
+/// Withdrawal announced by `announce_withdrawal`. Unless vetoed, it is executable from
+/// `executable_at` by `withdraw_token`, a council `WithdrawToken` action, `distribute`,
+/// `add_claim_keys` or the finalization sweep, which deduct the tokens they send from `amount`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingWithdrawal {
+    pub amount: U128,
+    /// Block time of the announcement, in nanoseconds since the Unix epoch.
+    pub announced_at: U64,
+    pub executable_at: U64,
+}
+
+#[near]
+impl AirdropContract {
+    /// Requires withdrawals to be announced `withdrawal_delay` nanoseconds in advance (only
+    /// callable by the owner). The delay can only be increased, so leftover tokens cannot be
+    /// swept without notice once it is set.
+    #[payable]
+    pub fn set_withdrawal_delay(&mut self, withdrawal_delay: U64) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_withdrawal_delay");
+        require!(
+            self.withdrawal_delay
+                .is_none_or(|delay| withdrawal_delay.0 >= delay.0),
+            "The withdrawal delay can only be increased"
+        );
+        self.withdrawal_delay = Some(withdrawal_delay);
+        log!("Withdrawal delay set to {}", withdrawal_delay.0);
+    }
+
+    /// Announces the withdrawal of `amount` tokens, executable once the withdrawal delay has
+    /// passed (only callable by the owner). Replaces any pending announcement.
+    #[payable]
+    pub fn announce_withdrawal(&mut self, amount: U128) -> PendingWithdrawal {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("announce_withdrawal");
+        let delay = self
+            .withdrawal_delay
+            .unwrap_or_else(|| env::panic_str("No withdrawal delay is set"));
+        let announced_at = env::block_timestamp();
+        let withdrawal = PendingWithdrawal {
+            amount,
+            announced_at: U64(announced_at),
+            executable_at: U64(announced_at.saturating_add(delay.0)),
+        };
+        AirdropEvent::WithdrawalAnnounced(vec![WithdrawalEvent {
+            amount,
+            executable_at: withdrawal.executable_at,
+        }])
+        .emit();
+        self.pending_withdrawal = Some(withdrawal.clone());
+        withdrawal
+    }
+
+    /// Vetoes the pending withdrawal (only callable by the guardian or the owner).
+    #[payable]
+    pub fn veto_withdrawal(&mut self) {
+        if self.guardian_id.as_ref() != Some(&env::predecessor_account_id()) {
+            self.assert_owner("Only the owner or the guardian can veto withdrawals");
+        }
+        self.record_admin_action("veto_withdrawal");
+        let withdrawal = self
+            .pending_withdrawal
+            .take()
+            .unwrap_or_else(|| env::panic_str("No pending withdrawal"));
+        AirdropEvent::WithdrawalVetoed(vec![WithdrawalEvent {
+            amount: withdrawal.amount,
+            executable_at: withdrawal.executable_at,
+        }])
+        .emit();
+    }
+
+    /// Returns the delay between the announcement and the execution of withdrawals, in
+    /// nanoseconds, `None` if withdrawals need no announcement.
+    pub fn get_withdrawal_delay(&self) -> Option<U64> {
+        self.withdrawal_delay
+    }
+
+    /// Returns the announced withdrawal, if any.
+    pub fn get_pending_withdrawal(&self) -> Option<PendingWithdrawal> {
+        self.pending_withdrawal.clone()
+    }
+}
+
+impl AirdropContract {
+    /// With a withdrawal delay, ensures a withdrawal of at least `amount` tokens was announced
+    /// and its delay has passed.
+    pub(crate) fn assert_announced_withdrawal(&self, amount: U128) {
+        if self.withdrawal_delay.is_none() {
+            return;
+        }
+        let withdrawal = self
+            .pending_withdrawal
+            .as_ref()
+            .unwrap_or_else(|| env::panic_str("The withdrawal must be announced first"));
+        require!(
+            amount.0 <= withdrawal.amount.0,
+            "The amount exceeds the announced withdrawal"
+        );
+        if env::block_timestamp() < withdrawal.executable_at.0 {
+            env::panic_str(&format!(
+                "The withdrawal is executable from {}",
+                withdrawal.executable_at.0
+            ));
+        }
+    }
+
+    /// With a withdrawal delay, deducts `amount` tokens from the announced withdrawal, checked
+    /// like `assert_announced_withdrawal`. The announcement is consumed once used up.
+    pub(crate) fn internal_take_announced_withdrawal(&mut self, amount: U128) {
+        self.assert_announced_withdrawal(amount);
+        let Some(withdrawal) = self.pending_withdrawal.as_mut() else {
+            return;
+        };
+        withdrawal.amount.0 -= amount.0;
+        if withdrawal.amount.0 == 0 {
+            self.pending_withdrawal = None;
+        }
+    }
+
+    /// Tokens the finalization may sweep. With a withdrawal delay, consumes the announced
+    /// withdrawal once its delay has passed and returns its amount, `None` until then.
+    pub(crate) fn internal_take_sweep_allowance(&mut self) -> Option<u128> {
+        if self.withdrawal_delay.is_none() {
+            return Some(u128::MAX);
+        }
+        let executable = self
+            .pending_withdrawal
+            .as_ref()
+            .is_some_and(|withdrawal| env::block_timestamp() >= withdrawal.executable_at.0);
+        if !executable {
+            return None;
+        }
+        self.pending_withdrawal
+            .take()
+            .map(|withdrawal| withdrawal.amount.0)
+    }
+}
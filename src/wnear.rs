@@ -0,0 +1,93 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{env, log, near, require, Gas, NearToken, Promise, PromiseError, PromiseOrValue};
+
+use crate::{
+    AirdropContract, AirdropContractExt, ClaimOutcome, ClaimStatus, PendingClaim,
+    GAS_FOR_LIQUID_STAKING,
+};
+
+/// wNEAR contracts, whose claims can be unwrapped to native NEAR.
+const WNEAR_CONTRACTS: [&str; 2] = ["wrap.near", "wrap.testnet"];
+/// Gas of the `near_withdraw` call unwrapping a claim.
+const GAS_FOR_NEAR_WITHDRAW: Gas = Gas::from_tgas(10);
+/// Gas of `on_unwrapped`, without the calls it schedules.
+const GAS_FOR_UNWRAP_CALLBACK: Gas = Gas::from_tgas(10);
+/// Gas the transfer of a claim needs to unwrap it, `GAS_FOR_NEAR_WITHDRAW` and
+/// `GAS_FOR_UNWRAP_CALLBACK`.
+pub(crate) const GAS_FOR_UNWRAP: Gas = Gas::from_tgas(20);
+
+#[near]
+impl AirdropContract {
+    /// Pays claims out in native NEAR instead of wNEAR (only callable by the owner, when the
+    /// airdropped token is wNEAR). Claims paid with `ft_transfer_call` are not unwrapped.
+    #[payable]
+    pub fn set_unwrap_wnear(&mut self, enabled: bool) {
+        self.assert_owner("Owner's method");
+        self.record_admin_action("set_unwrap_wnear");
+        if enabled {
+            require!(self.is_wnear(), "The airdropped token is not wNEAR");
+        }
+        self.unwrap_wnear = enabled;
+        log!("wNEAR unwrapping set to {}", enabled);
+    }
+
+    /// Returns whether claims are paid out in native NEAR.
+    pub fn get_unwrap_wnear(&self) -> bool {
+        self.unwrap_wnear
+    }
+
+    /// Callback: After the wNEAR of `claim` is unwrapped, sends the NEAR to the claimed account,
+    /// or stakes it for `claim_and_liquid_stake`. A failed unwrap fails the claim like a
+    /// failed transfer.
+    #[private]
+    pub fn on_unwrapped(
+        &mut self,
+        claim: PendingClaim,
+        #[callback_result] call_result: Result<(), PromiseError>,
+    ) -> PromiseOrValue<ClaimOutcome> {
+        if call_result.is_err() {
+            if self.ious_enabled {
+                return PromiseOrValue::Value(self.internal_record_iou(claim));
+            }
+            self.internal_rollback(&claim, ClaimStatus::TransferFailed);
+            return PromiseOrValue::Value(ClaimOutcome::failed(ClaimStatus::TransferFailed));
+        }
+        if claim.liquid_stake {
+            return self.internal_liquid_stake(claim);
+        }
+        Promise::new(claim.receiver_id().clone())
+            .transfer(NearToken::from_yoctonear(claim.net_amount()));
+        PromiseOrValue::Value(self.internal_complete_claim(claim))
+    }
+}
+
+impl AirdropContract {
+    /// Whether the airdropped token is wNEAR.
+    pub(crate) fn is_wnear(&self) -> bool {
+        WNEAR_CONTRACTS.contains(&self.token_contract.as_str())
+    }
+
+    /// Unwraps the wNEAR of `claim` in the `transfer` batch of its treasury and referrer legs,
+    /// then pays it out.
+    pub(crate) fn internal_unwrap(&self, transfer: Promise, claim: PendingClaim) -> Promise {
+        let mut callback_gas = Self::completion_gas(&claim).saturating_add(GAS_FOR_UNWRAP_CALLBACK);
+        if claim.liquid_stake {
+            callback_gas = callback_gas.saturating_add(GAS_FOR_LIQUID_STAKING);
+        }
+        transfer
+            .function_call(
+                "near_withdraw".to_string(),
+                json!({ "amount": U128(claim.net_amount()) })
+                    .to_string()
+                    .into_bytes(),
+                NearToken::from_yoctonear(1),
+                GAS_FOR_NEAR_WITHDRAW,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(callback_gas)
+                    .on_unwrapped(claim),
+            )
+    }
+}
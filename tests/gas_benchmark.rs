@@ -0,0 +1,151 @@
+use anyhow::{ensure, Result};
+use near_sdk::json_types::U128;
+use near_workspaces::types::NearToken;
+use near_workspaces::{compile_project, Account};
+use publicai_airdrop::merkle::process_proof;
+use publicai_airdrop::test_utils::AirdropSandbox;
+use publicai_airdrop::{HashAlgorithm, LeafExtras, LeafFormat};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Deepest proof benchmarked, a tree of about a billion leaves.
+const MAX_DEPTH: usize = 30;
+/// Depth of the proofs of the repeat-registration and duplicate claims.
+const REFERENCE_DEPTH: usize = 16;
+const CLAIM_AMOUNT: u128 = 100;
+/// Increase over the baseline above which a measurement fails, in percent.
+const REGRESSION_TOLERANCE: u64 = 10;
+
+/// Records the gas burnt by claims with proofs of depth 1 to `MAX_DEPTH`, by a claim of an
+/// account already registered with the token, and by a rejected duplicate claim. The report is
+/// written to `GAS_REPORT` (default `target/gas-report.json`); if `GAS_BASELINE` points to a
+/// previous report, any measurement more than `REGRESSION_TOLERANCE` percent above it fails.
+///
+/// `cargo test --features test-utils --test gas_benchmark`
+#[tokio::test]
+async fn test_claim_gas_benchmark() -> Result<()> {
+    let token_wasm = compile_project("../publicai-token").await?;
+    let airdrop_wasm = compile_project(".").await?;
+    let sandbox = AirdropSandbox::deploy(&airdrop_wasm, &token_wasm, 1).await?;
+    sandbox.fund(CLAIM_AMOUNT * (MAX_DEPTH as u128 + 1)).await?;
+
+    let mut report = BTreeMap::new();
+    for depth in 1..=MAX_DEPTH {
+        let user = create_user(&sandbox, &format!("depth{}", depth)).await?;
+        let gas = claim_with_depth(&sandbox, &user, depth).await?;
+        report.insert(format!("claim_depth_{:02}", depth), gas);
+    }
+
+    // Receiver already registered with the token, so no storage_deposit leg
+    let user = create_user(&sandbox, "registered").await?;
+    user.call(sandbox.token.id(), "storage_deposit")
+        .args_json(json!({ "account_id": user.id(), "registration_only": true }))
+        .deposit(NearToken::from_millinear(1250))
+        .transact()
+        .await?
+        .into_result()?;
+    let gas = claim_with_depth(&sandbox, &user, REFERENCE_DEPTH).await?;
+    report.insert("claim_registered_receiver".to_string(), gas);
+    sandbox.assert_balance(user.id(), CLAIM_AMOUNT).await?;
+
+    // Rejected before any cross-contract call
+    let result = user
+        .call(sandbox.airdrop.id(), "claim_airdrop")
+        .args_json(json!({
+            "amount": U128(CLAIM_AMOUNT),
+            "merkle_proof": synthetic_proof(REFERENCE_DEPTH),
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    ensure!(result.is_failure(), "The duplicate claim should fail");
+    report.insert(
+        "claim_duplicate".to_string(),
+        result.total_gas_burnt.as_gas(),
+    );
+
+    let report_path =
+        std::env::var("GAS_REPORT").unwrap_or_else(|_| "target/gas-report.json".to_string());
+    std::fs::write(&report_path, serde_json::to_string_pretty(&report)?)?;
+    println!("Gas report written to {}", report_path);
+
+    if let Ok(baseline_path) = std::env::var("GAS_BASELINE") {
+        let baseline: BTreeMap<String, u64> =
+            serde_json::from_str(&std::fs::read_to_string(&baseline_path)?)?;
+        for (name, gas) in &report {
+            if let Some(before) = baseline.get(name) {
+                ensure!(
+                    *gas <= before + before * REGRESSION_TOLERANCE / 100,
+                    "{} burns {} gas, {} in the baseline",
+                    name,
+                    gas,
+                    before
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn create_user(sandbox: &AirdropSandbox, name: &str) -> Result<Account> {
+    let user = sandbox
+        .owner
+        .create_subaccount(name)
+        .initial_balance(NearToken::from_near(10))
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(user)
+}
+
+/// Deterministic siblings of a proof of `depth` hashes.
+fn synthetic_proof(depth: usize) -> Vec<String> {
+    (0..depth)
+        .map(|i| hex::encode(HashAlgorithm::Keccak256.hash(&[depth as u8, i as u8])))
+        .collect()
+}
+
+/// Sets the root to one with a leaf of `user` at `depth`, claims it and returns the gas burnt by
+/// the whole claim, including the cross-contract calls.
+async fn claim_with_depth(sandbox: &AirdropSandbox, user: &Account, depth: usize) -> Result<u64> {
+    let proof = synthetic_proof(depth);
+    let leaf = LeafExtras::default().encode_leaf(user.id(), CLAIM_AMOUNT);
+    let siblings: Vec<Vec<u8>> = proof
+        .iter()
+        .map(|sibling| hex::decode(sibling).unwrap())
+        .collect();
+    let root = process_proof(
+        HashAlgorithm::Keccak256,
+        LeafFormat::V1,
+        leaf.as_bytes(),
+        &siblings,
+    );
+    sandbox
+        .owner
+        .call(sandbox.airdrop.id(), "update_merkle_root")
+        .args_json(json!({
+            "merkle_root": hex::encode(root),
+            "total_amount": U128(CLAIM_AMOUNT),
+            "leaf_count": 1,
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let result = user
+        .call(sandbox.airdrop.id(), "claim_airdrop")
+        .args_json(json!({ "amount": U128(CLAIM_AMOUNT), "merkle_proof": proof }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    ensure!(
+        result.is_success(),
+        "Claim at depth {} failed: {:?}",
+        depth,
+        result
+    );
+    Ok(result.total_gas_burnt.as_gas())
+}
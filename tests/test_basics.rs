@@ -51,6 +51,8 @@ async fn test_claim_airdrop_cross_contract_failure() -> Result<()> {
             root_account.id(),
             token_contract.id(),
             "eef6e78d1a41f5778535f2f88c437a38ad2b693c13e1f8146de64687c5d7144a",
+            Option::<String>::None,
+            Option::<String>::None,
         ))
         .transact()
         .await?